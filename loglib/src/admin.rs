@@ -0,0 +1,306 @@
+//! HTTP-управление логгером (фича `http_admin`) — для контейнеров, где проще открыть
+//! порт, чем сокет: `GET`/`PUT /level`, `POST /rotate`, `POST /flush`, `GET /stats`,
+//! ответы в JSON. Свой сервер запускает `LoggerBuilder::http_admin`; в приложение на
+//! axum или hyper обработчик встраивается через `Logger::admin_handler`:
+//!
+//! ```ignore
+//! let admin = logger.admin_handler();
+//! let route = move |method: Method, uri: Uri, body: Bytes| async move {
+//!     let response = admin.handle(method.as_str(), uri.path(), &body);
+//!     (StatusCode::from_u16(response.status).unwrap(), response.body)
+//! };
+//! ```
+
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use crate::control::{Handle, LevelsView, Listening, Target};
+use crate::{write_json_str, LogLevel};
+
+// Заголовки запроса и тело PUT /level бывают только маленькими
+const MAX_HEAD: u64 = 8 * 1024;
+const MAX_BODY: usize = 1024;
+
+// Клиент, который замолчал, не держит сервер дольше
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Обработчик HTTP-запросов управления; дешёво клонируется. Не продлевает жизнь
+/// логгера: после его закрытия отвечает 503.
+#[derive(Clone)]
+pub struct AdminHandler {
+    target: Arc<Target>,
+}
+
+/// Ответ обработчика; тело всегда JSON (`CONTENT_TYPE`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdminResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl AdminResponse {
+    pub const CONTENT_TYPE: &'static str = "application/json";
+
+    fn ok(body: String) -> Self {
+        AdminResponse { status: 200, body }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        let mut body = b"{\"error\":".to_vec();
+        let _ = write_json_str(&mut body, message);
+        body.push(b'}');
+        AdminResponse {
+            status,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        }
+    }
+
+    fn from_io(error: io::Error) -> Self {
+        let status = match error.kind() {
+            io::ErrorKind::InvalidInput => 400,
+            io::ErrorKind::NotConnected => 503, // логгер закрыт
+            _ => 500,
+        };
+        Self::error(status, &error.to_string())
+    }
+}
+
+impl AdminHandler {
+    pub(crate) fn new(target: Target) -> Self {
+        AdminHandler {
+            target: Arc::new(target),
+        }
+    }
+
+    /// Ответ на запрос: метод (`GET`, `PUT`, `POST`), путь без query-строки и тело.
+    /// Тело `PUT /level` — `{"level": "debug"}` или просто `debug`; `null` или `reset`
+    /// возвращают уровни из настроек.
+    pub fn handle(&self, method: &str, path: &str, body: &[u8]) -> AdminResponse {
+        let result = match (method, path.trim_end_matches('/')) {
+            ("GET", "/level") => self.target.levels().map(levels_json),
+            ("PUT", "/level") => requested_level(body)
+                .and_then(|level| self.target.set_level(level))
+                .map(levels_json),
+            ("POST", "/rotate") => self.target.rotate().map(|()| "{\"ok\":true}".to_owned()),
+            ("POST", "/flush") => self.target.flush().map(|()| "{\"ok\":true}".to_owned()),
+            ("GET", "/stats") => self
+                .target
+                .stats()
+                .map(|(stats, file_size)| stats_json(&stats, file_size)),
+            (_, "/level" | "/rotate" | "/flush" | "/stats") => {
+                return AdminResponse::error(405, "method not allowed")
+            }
+            _ => return AdminResponse::error(404, "not found"),
+        };
+        result.map_or_else(AdminResponse::from_io, AdminResponse::ok)
+    }
+}
+
+// {"level": "debug"}, {"level": null}, "debug" или debug
+fn requested_level(body: &[u8]) -> io::Result<Option<LogLevel>> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let text = std::str::from_utf8(body)
+        .map_err(|e| invalid(e.to_string()))?
+        .trim();
+    let value = match text.strip_prefix('{').and_then(|t| t.strip_suffix('}')) {
+        Some(object) => match object.split_once(':') {
+            Some((key, value)) if key.trim() == "\"level\"" => value.trim(),
+            _ => return Err(invalid("expected {\"level\": \"<level>\"}".to_owned())),
+        },
+        None => text,
+    };
+    match value.trim_matches('"') {
+        "null" | "reset" => Ok(None),
+        name => name
+            .parse()
+            .map(Some)
+            .map_err(|e| invalid(format!("{}", e))),
+    }
+}
+
+fn levels_json(view: LevelsView) -> String {
+    let level = |level: Option<LogLevel>| level.map_or("null".to_owned(), |l| format!("\"{}\"", l));
+    format!(
+        "{{\"override\":{},\"min\":\"{}\",\"global\":{}}}",
+        level(view.overridden),
+        view.min,
+        level(view.global)
+    )
+}
+
+fn stats_json(stats: &crate::LogStats, file_size: Option<u64>) -> String {
+    let mut out = String::from("{\"records\":{");
+    for (i, level) in LogLevel::ALL[..stats.records_by_level.len()]
+        .iter()
+        .enumerate()
+    {
+        if i > 0 {
+            out.push(',');
+        }
+        let _ = write!(out, "\"{}\":{}", level, stats.records_by_level[i]);
+    }
+    let _ = write!(
+        out,
+        "}},\"total_records\":{},\"dropped\":{},\"bytes_written\":{},\"rotations\":{},\
+         \"file_errors\":{},\"system_log_errors\":{},\"buffered_records\":{},\
+         \"maintenance_backlog\":{},\"write_batches\":{:?}",
+        stats.total_records(),
+        stats.dropped,
+        stats.bytes_written,
+        stats.rotations,
+        stats.file_errors,
+        stats.system_log_errors,
+        stats.buffered_records,
+        stats.maintenance_backlog,
+        stats.write_batches,
+    );
+    match file_size {
+        Some(size) => {
+            let _ = write!(out, ",\"file_size\":{}", size);
+        }
+        None => out.push_str(",\"file_size\":null"),
+    }
+    out.push_str(",\"last_error\":");
+    match stats.last_error {
+        Some(ref error) => {
+            let mut escaped = Vec::new();
+            let _ = write_json_str(&mut escaped, error);
+            out.push_str(&String::from_utf8_lossy(&escaped));
+        }
+        None => out.push_str("null"),
+    }
+    out.push('}');
+    out
+}
+
+// Адреса, которые слушает этот процесс
+static SERVERS: Mutex<Vec<(SocketAddr, Weak<Listening<AdminHandler>>)>> = Mutex::new(Vec::new());
+
+// Встроенный сервер: по одному соединению за раз, Connection: close. Адрес, который уже
+// слушает этот процесс, переходит к новому логгеру (см. `control::Listening`).
+pub(crate) struct HttpServer {
+    addr: SocketAddr,
+    _handle: Handle<AdminHandler>,
+}
+
+impl HttpServer {
+    pub fn start(addr: SocketAddr, handler: AdminHandler) -> io::Result<HttpServer> {
+        let mut servers = SERVERS.lock().unwrap();
+        servers.retain(|(_, listening)| listening.strong_count() > 0);
+        // Порт 0 — «любой свободный», такой слушатель не переиспользуем
+        let running = servers
+            .iter()
+            .filter(|(listened, _)| addr.port() != 0 && *listened == addr)
+            .find_map(|(_, listening)| listening.upgrade());
+        let handler = match running {
+            Some(listening) => match listening.join(handler) {
+                Ok(handle) => {
+                    return Ok(HttpServer {
+                        addr,
+                        _handle: handle,
+                    })
+                }
+                Err(handler) => handler,
+            },
+            None => handler,
+        };
+        let listener = TcpListener::bind(addr)?;
+        let local = listener.local_addr()?;
+        let (listening, handle) = Listening::new(handler, move || wake(local));
+        let shared = Arc::clone(&listening);
+        std::thread::Builder::new()
+            .name("loglib-http-admin".to_owned())
+            .spawn(move || {
+                for client in listener.incoming() {
+                    if shared.stopped() {
+                        break;
+                    }
+                    if let (Ok(client), Some(handler)) = (client, shared.current()) {
+                        let _ = serve(client, &handler);
+                    }
+                }
+            })?;
+        servers.push((addr, Arc::downgrade(&listening)));
+        Ok(HttpServer {
+            addr: local,
+            _handle: handle,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+// Будит поток в accept, чтобы он увидел остановку; на 0.0.0.0 подключаемся через loopback
+fn wake(mut addr: SocketAddr) {
+    if addr.ip().is_unspecified() {
+        addr.set_ip(match addr {
+            SocketAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+            SocketAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+        });
+    }
+    let _ = TcpStream::connect_timeout(&addr, Duration::from_secs(1));
+}
+
+fn serve(client: TcpStream, handler: &AdminHandler) -> io::Result<()> {
+    client.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    client.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut reader = BufReader::new(&client);
+
+    let mut head = reader.by_ref().take(MAX_HEAD);
+    let mut request_line = String::new();
+    head.read_line(&mut request_line)?;
+    let mut length = 0;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if head.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().unwrap_or(usize::MAX);
+            }
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        _ if length > MAX_BODY => AdminResponse::error(413, "request body is too large"),
+        (Some(method), Some(target)) => {
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body)?;
+            let path = target.split('?').next().unwrap_or(target);
+            handler.handle(method, path, &body)
+        }
+        _ => AdminResponse::error(400, "malformed request"),
+    };
+
+    let mut client = &client;
+    write!(
+        client,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason(response.status),
+        AdminResponse::CONTENT_TYPE,
+        response.body.len(),
+        response.body
+    )?;
+    client.flush()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
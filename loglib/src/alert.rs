@@ -0,0 +1,175 @@
+//! Оповещение о всплеске ошибок: «N записей не ниже Error за время T» — один раз,
+//! а не на каждую строку.
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::{Clock, LogLevel, Record, Sink, SystemClock};
+
+/// Сработавшее правило
+#[derive(Debug, Clone)]
+pub struct AlertEvent {
+    /// Сколько записей набралось с прошлого срабатывания, без перерывов дольше окна
+    pub count: usize,
+    pub window: Duration,
+    /// Самый высокий уровень среди этих записей
+    pub level: LogLevel,
+    /// Текст последней записи
+    pub last_message: String,
+}
+
+type AlertCallback = Box<dyn Fn(&AlertEvent) + Send + Sync>;
+
+/// Приёмник-правило: когда за `window` набирается `threshold` записей не ниже `min_level`,
+/// вызывает `on_alert` и/или передаёт одну сводную запись приёмнику из `notify`.
+/// После срабатывания молчит `cooldown` (по умолчанию равен окну).
+/// ```ignore
+/// let storm = ThresholdAlert::new(50, Duration::from_secs(60))
+///     .notify(WebhookSink::new(url).min_level(LogLevel::Error));
+/// let logger = Logger::builder().add_sink(storm).build()?;
+/// ```
+pub struct ThresholdAlert {
+    threshold: usize,
+    window: Duration,
+    min_level: LogLevel,
+    cooldown: Duration,
+    callback: Option<AlertCallback>,
+    sink: Option<Box<dyn Sink>>,
+    clock: Arc<dyn Clock>,
+    state: Mutex<AlertState>,
+}
+
+#[derive(Default)]
+struct AlertState {
+    // Время последних threshold записей: для порога старше не нужны, а в шторм их тысячи
+    seen: VecDeque<Duration>,
+    count: usize,
+    level: Option<LogLevel>,
+    last_fired: Option<Duration>,
+}
+
+impl ThresholdAlert {
+    pub fn new(threshold: usize, window: Duration) -> Self {
+        ThresholdAlert {
+            threshold: threshold.max(1),
+            window,
+            min_level: LogLevel::Error,
+            cooldown: window,
+            callback: None,
+            sink: None,
+            clock: Arc::new(SystemClock),
+            state: Mutex::new(AlertState::default()),
+        }
+    }
+
+    /// Считать записи не ниже `level` (по умолчанию Error).
+    pub fn min_level(mut self, level: LogLevel) -> Self {
+        self.min_level = level;
+        self
+    }
+
+    /// Сколько молчать после срабатывания.
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Вызывается при срабатывании, в потоке, который записал последнюю запись.
+    pub fn on_alert<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&AlertEvent) + Send + Sync + 'static,
+    {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    /// При срабатывании записать в `sink` одну сводную запись с уровнем события.
+    pub fn notify<S: Sink + 'static>(mut self, sink: S) -> Self {
+        self.sink = Some(Box::new(sink));
+        self
+    }
+
+    /// Источник времени для окна и паузы — в тестах `test::ManualClock`.
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    // Событие, если правило сработало на этой записи
+    fn check(&self, record: &Record) -> Option<AlertEvent> {
+        let now = self.clock.monotonic();
+        let mut state = self.state.lock().unwrap();
+        while let Some(&time) = state.seen.front() {
+            if now.saturating_sub(time) <= self.window {
+                break;
+            }
+            state.seen.pop_front();
+        }
+        if state.seen.is_empty() {
+            state.count = 0;
+            state.level = None;
+        }
+        state.seen.push_back(now);
+        if state.seen.len() > self.threshold {
+            state.seen.pop_front();
+        }
+        state.count += 1;
+        state.level = state.level.max(Some(record.level()));
+        if state.seen.len() < self.threshold {
+            return None;
+        }
+        if let Some(fired) = state.last_fired {
+            if now.saturating_sub(fired) < self.cooldown {
+                return None;
+            }
+        }
+        let event = AlertEvent {
+            count: state.count,
+            window: self.window,
+            level: state.level?,
+            last_message: record.args().to_string(),
+        };
+        state.last_fired = Some(now);
+        state.seen.clear();
+        state.count = 0;
+        state.level = None;
+        Some(event)
+    }
+}
+
+impl Sink for ThresholdAlert {
+    fn write(&self, record: &Record) -> io::Result<()> {
+        if record.level() < self.min_level {
+            return Ok(());
+        }
+        // Обработчики вызываются без блокировки: они могут и сами писать в лог
+        let Some(event) = self.check(record) else {
+            return Ok(());
+        };
+        if let Some(ref callback) = self.callback {
+            callback(&event);
+        }
+        match self.sink {
+            Some(ref sink) => sink.write(
+                &Record::new(
+                    event.level,
+                    format_args!(
+                        "{} records at {} or above within {:?}, last: {}",
+                        event.count, self.min_level, event.window, event.last_message
+                    ),
+                )
+                .with_target("loglib::alert"),
+            ),
+            None => Ok(()),
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        match self.sink {
+            Some(ref sink) => sink.flush(),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,380 @@
+//! Настройки логгера в виде данных: их можно хранить в YAML, JSON, etcd и собирать
+//! логгер без кода. С фичей `serde` тип (де)сериализуется.
+//!
+//! ```ignore
+//! let config: LoggerConfig = serde_yaml::from_str(r#"
+//! app_name: billing
+//! file: { directory: /var/log/billing, filename: app.log, max_size: 10485760, max_files: 5 }
+//! flush_policies: [{ interval: 0.5 }, { on_level: error }]
+//! "#)?;
+//! let logger = Logger::from_config(&config)?;
+//! ```
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[cfg(feature = "regex")]
+use crate::SuppressRule;
+use crate::{
+    AppendRole, ArchiveCommand, BuildInfo, Durability, FlushPolicy, LineEnding, LineFormat,
+    LogLevel, Logger, LoggerBuilder, OpenMode, RetryPolicy, RotatingWriter, RotationNaming,
+    RotationPolicy, SyslogFacility, SyslogFormat, ThreadFormat, TimestampMode,
+};
+
+/// Все настройки `LoggerBuilder`, которые выражаются данными. Отсутствующие при
+/// разборе поля берут значения по умолчанию построителя. Приёмники, перехватчики,
+/// дополнительные файлы и часы добавляются к `LoggerConfig::builder()` кодом.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct LoggerConfig {
+    pub app_name: Option<String>,
+    /// Сведения о сборке (`with_build_info!()`)
+    pub build_info: Option<BuildInfo>,
+    /// Основной файл лога; `None` — без файла
+    pub file: Option<FileOutput>,
+    pub system_log: bool,
+    pub syslog_facility: SyslogFacility,
+    pub syslog_ident: Option<String>,
+    pub syslog_pid: bool,
+    pub syslog_format: SyslogFormat,
+    pub file_mode: Option<u32>,
+    pub restrict_file_acl: bool,
+    pub multi_process: bool,
+    /// Общий файл без блокировок и роль этого процесса
+    pub shared_append: Option<AppendRole>,
+    /// PID в имени файла; `instance_id` имеет приоритет
+    pub per_process_file: bool,
+    pub instance_id: Option<String>,
+    pub rotation_naming: RotationNaming,
+    pub rotation_policy: RotationPolicy,
+    pub rotate_on_open: bool,
+    pub open_mode: OpenMode,
+    pub retry_policy: RetryPolicy,
+    pub durability: Durability,
+    /// Запись через io_uring (фича `io_uring`)
+    #[cfg(feature = "io_uring")]
+    pub io_uring: bool,
+    /// Каталог архивов; относительный путь — от каталога лога
+    pub archive_dir: Option<PathBuf>,
+    /// Сжимать архивы gzip (фича `gzip`)
+    #[cfg(feature = "gzip")]
+    pub compress: bool,
+    /// Команда после ротации: программа и аргументы, путь архива добавляется последним
+    pub archive_command: Vec<String>,
+    /// В сериализованном виде — секунды
+    #[cfg_attr(feature = "serde", serde(with = "duration_secs"))]
+    pub archive_command_timeout: Duration,
+    pub current_link: bool,
+    pub min_free_space: Option<u64>,
+    pub source_location: bool,
+    pub thread_format: ThreadFormat,
+    pub error_file: Option<String>,
+    pub timestamp_mode: TimestampMode,
+    pub sequence_numbers: bool,
+    /// Шаблон заголовка файла (см. `LoggerBuilder::rotation_header`); `None` — без него
+    pub rotation_header: Option<String>,
+    pub header_on_open: bool,
+    pub line_ending: LineEnding,
+    /// BOM в начале каждого нового файла
+    pub utf8_bom: bool,
+    /// Кодовая страница файлов (фича `encoding`); `None` — UTF-8
+    #[cfg(feature = "encoding")]
+    pub output_encoding: Option<String>,
+    pub hostname: bool,
+    pub process_name: bool,
+    pub static_fields: BTreeMap<String, String>,
+    pub flush_policies: Vec<FlushPolicy>,
+    pub buffer_capacity: Option<usize>,
+    /// Очередь фонового писателя, записей; `None` — пишет вызывающий поток
+    pub background_writer: Option<usize>,
+    /// Интервал heartbeat; в сериализованном виде — секунды
+    #[cfg_attr(feature = "serde", serde(with = "duration_secs::option"))]
+    pub heartbeat: Option<Duration>,
+    pub heartbeat_system_log: bool,
+    /// Запись `STARTUP` со сведениями о системе
+    pub startup_banner: bool,
+    /// Запись `SHUTDOWN` с итогом работы
+    pub shutdown_summary: bool,
+    /// Канал управления (см. `LoggerBuilder::control_socket`)
+    pub control_socket: Option<PathBuf>,
+    /// Адрес HTTP-управления (фича `http_admin`)
+    #[cfg(feature = "http_admin")]
+    pub http_admin: Option<std::net::SocketAddr>,
+    /// SIGUSR1/SIGUSR2 меняют уровень (unix)
+    pub verbosity_signals: bool,
+    /// Префикс именованных событий (Windows)
+    pub control_events: Option<String>,
+    /// Число флагов `-v` (см. `LoggerBuilder::verbosity`); `None` — без уровня логгера
+    pub verbosity: Option<u8>,
+    pub quiet: bool,
+    /// Уровень логгера вместо глобального
+    pub level_override: Option<LogLevel>,
+    pub line_format: LineFormat,
+    pub system_log_format: LineFormat,
+    /// Правила подавления шумных сообщений (фича `regex`)
+    #[cfg(feature = "regex")]
+    pub suppress: Vec<SuppressRule>,
+}
+
+/// Основной файл: каталог, имя, размер файла до ротации и число архивов
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+pub struct FileOutput {
+    pub directory: PathBuf,
+    pub filename: String,
+    pub max_size: u64,
+    /// Число архивов; 0 — хранить все
+    pub max_files: usize,
+}
+
+impl Default for LoggerConfig {
+    fn default() -> Self {
+        LoggerConfig {
+            app_name: None,
+            build_info: None,
+            file: None,
+            system_log: false,
+            syslog_facility: SyslogFacility::User,
+            syslog_ident: None,
+            syslog_pid: true,
+            syslog_format: SyslogFormat::Rfc3164,
+            file_mode: None,
+            restrict_file_acl: false,
+            multi_process: false,
+            shared_append: None,
+            per_process_file: false,
+            instance_id: None,
+            rotation_naming: RotationNaming::Numbered,
+            rotation_policy: RotationPolicy::Size,
+            rotate_on_open: false,
+            open_mode: OpenMode::Append,
+            retry_policy: RetryPolicy::default(),
+            durability: Durability::None,
+            #[cfg(feature = "io_uring")]
+            io_uring: false,
+            archive_dir: None,
+            #[cfg(feature = "gzip")]
+            compress: false,
+            archive_command: Vec::new(),
+            archive_command_timeout: ArchiveCommand::DEFAULT_TIMEOUT,
+            current_link: true,
+            min_free_space: None,
+            source_location: false,
+            thread_format: ThreadFormat::NameOrId,
+            error_file: None,
+            timestamp_mode: TimestampMode::WallClock,
+            sequence_numbers: false,
+            rotation_header: Some(RotatingWriter::DEFAULT_HEADER.to_owned()),
+            header_on_open: false,
+            line_ending: LineEnding::Lf,
+            utf8_bom: false,
+            #[cfg(feature = "encoding")]
+            output_encoding: None,
+            hostname: false,
+            process_name: false,
+            static_fields: BTreeMap::new(),
+            flush_policies: Vec::new(),
+            buffer_capacity: None,
+            background_writer: None,
+            heartbeat: None,
+            heartbeat_system_log: false,
+            startup_banner: false,
+            shutdown_summary: false,
+            control_socket: None,
+            #[cfg(feature = "http_admin")]
+            http_admin: None,
+            verbosity_signals: false,
+            control_events: None,
+            verbosity: None,
+            quiet: false,
+            level_override: None,
+            line_format: LineFormat::Text,
+            system_log_format: LineFormat::Text,
+            #[cfg(feature = "regex")]
+            suppress: Vec::new(),
+        }
+    }
+}
+
+impl LoggerConfig {
+    /// Построитель с этими настройками — чтобы добавить приёмники и файлы перед `build()`.
+    pub fn builder(&self) -> LoggerBuilder {
+        let mut builder = Logger::builder()
+            .system_log(self.system_log)
+            .syslog_facility(self.syslog_facility)
+            .syslog_pid(self.syslog_pid)
+            .syslog_format(self.syslog_format)
+            .restrict_file_acl(self.restrict_file_acl)
+            .multi_process(self.multi_process)
+            .per_process_file(self.per_process_file)
+            .rotation_naming(self.rotation_naming)
+            .rotation_policy(self.rotation_policy)
+            .rotate_on_open(self.rotate_on_open)
+            .open_mode(self.open_mode)
+            .retry_policy(self.retry_policy)
+            .durability(self.durability)
+            .archive_command(&self.archive_command, self.archive_command_timeout)
+            .current_link(self.current_link)
+            .source_location(self.source_location)
+            .thread_format(self.thread_format)
+            .timestamp_mode(self.timestamp_mode)
+            .sequence_numbers(self.sequence_numbers)
+            .rotation_header(self.rotation_header.as_deref())
+            .header_on_open(self.header_on_open)
+            .line_ending(self.line_ending)
+            .utf8_bom(self.utf8_bom)
+            .hostname(self.hostname)
+            .process_name(self.process_name)
+            .line_format(self.line_format)
+            .system_log_format(self.system_log_format)
+            .heartbeat_system_log(self.heartbeat_system_log)
+            .startup_banner(self.startup_banner)
+            .shutdown_summary(self.shutdown_summary)
+            .verbosity_signals(self.verbosity_signals);
+
+        if let Some(ref app_name) = self.app_name {
+            builder = builder.app_name(app_name);
+        }
+        if let Some(ref info) = self.build_info {
+            builder = builder.build_info(info.clone());
+        }
+        if let Some(ref file) = self.file {
+            builder = builder.file(
+                &file.directory,
+                &file.filename,
+                file.max_size,
+                file.max_files,
+            );
+        }
+        if let Some(ref ident) = self.syslog_ident {
+            builder = builder.syslog_ident(ident);
+        }
+        if let Some(mode) = self.file_mode {
+            builder = builder.file_mode(mode);
+        }
+        if let Some(role) = self.shared_append {
+            builder = builder.shared_append(role);
+        }
+        if let Some(ref id) = self.instance_id {
+            builder = builder.instance_id(id);
+        }
+        if let Some(bytes) = self.min_free_space {
+            builder = builder.min_free_space(bytes);
+        }
+        if let Some(ref dir) = self.archive_dir {
+            builder = builder.archive_dir(dir);
+        }
+        if let Some(ref error_file) = self.error_file {
+            builder = builder.error_file(error_file);
+        }
+        for (key, value) in &self.static_fields {
+            builder = builder.with_static_field(key, value);
+        }
+        for &policy in &self.flush_policies {
+            builder = builder.flush_policy(policy);
+        }
+        if let Some(bytes) = self.buffer_capacity {
+            builder = builder.buffer_capacity(bytes);
+        }
+        if let Some(queue) = self.background_writer {
+            builder = builder.background_writer(queue);
+        }
+        if let Some(interval) = self.heartbeat {
+            builder = builder.heartbeat(interval);
+        }
+        if let Some(ref path) = self.control_socket {
+            builder = builder.control_socket(path);
+        }
+        if let Some(ref prefix) = self.control_events {
+            builder = builder.control_events(prefix);
+        }
+        if let Some(count) = self.verbosity {
+            builder = builder.verbosity(count);
+        }
+        if self.quiet {
+            builder = builder.quiet(true);
+        }
+        if let Some(level) = self.level_override {
+            builder = builder.level_override(level);
+        }
+        #[cfg(feature = "gzip")]
+        {
+            builder = builder.compress(self.compress);
+        }
+        #[cfg(feature = "io_uring")]
+        {
+            builder = builder.io_uring(self.io_uring);
+        }
+        #[cfg(feature = "encoding")]
+        if let Some(ref label) = self.output_encoding {
+            builder = builder.output_encoding(label);
+        }
+        #[cfg(feature = "http_admin")]
+        if let Some(addr) = self.http_admin {
+            builder = builder.http_admin(addr);
+        }
+        #[cfg(feature = "regex")]
+        for rule in &self.suppress {
+            builder = builder.suppress(rule.clone());
+        }
+        builder
+    }
+}
+
+impl Logger {
+    /// Логгер по настройкам из `LoggerConfig`.
+    pub fn from_config(config: &LoggerConfig) -> io::Result<Logger> {
+        config.builder().build()
+    }
+}
+
+// Интервалы в конфигурации — секунды с дробной частью: `0.5`, `30`
+#[cfg(feature = "serde")]
+pub(crate) mod duration_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(value.as_secs_f64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let secs = f64::deserialize(deserializer)?;
+        Duration::try_from_secs_f64(secs).map_err(serde::de::Error::custom)
+    }
+
+    pub mod option {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(
+            value: &Option<Duration>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match value {
+                Some(duration) => serializer.serialize_some(&duration.as_secs_f64()),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Duration>, D::Error> {
+            match Option::<f64>::deserialize(deserializer)? {
+                Some(secs) => Duration::try_from_secs_f64(secs)
+                    .map(Some)
+                    .map_err(serde::de::Error::custom),
+                None => Ok(None),
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! Поля контекста задачи tokio: `request_id` и подобные добавляются ко всем записям,
+//! сделанным внутри `scope`, на каком бы потоке ни выполнялась задача.
+//!
+//! ```ignore
+//! context::scope(&[("request_id", &id)], async {
+//!     info!(logger, "handling request"); // ... request_id=7f3a handling request
+//!     call_backend().await;
+//! })
+//! .await;
+//! ```
+//!
+//! Контекст не переходит в задачи, запущенные через `tokio::spawn`: оберните их в свой `scope`.
+
+use std::future::Future;
+
+use crate::{innermost_level, LogLevel, Record};
+
+tokio::task_local! {
+    static CONTEXT: Vec<(String, String)>;
+    // Области Logger::with_level_scope_async: логгер (адрес) и уровень
+    static LEVEL_SCOPES: Vec<(usize, LogLevel)>;
+}
+
+/// Выполняет `future` с полями контекста. Вложенный scope дополняет внешний,
+/// поле с тем же ключом заменяется.
+pub async fn scope<F: Future>(fields: &[(&str, &str)], future: F) -> F::Output {
+    CONTEXT.scope(nested(fields), future).await
+}
+
+/// Как `scope`, но для синхронного кода, например внутри `spawn_blocking`.
+pub fn sync_scope<R>(fields: &[(&str, &str)], f: impl FnOnce() -> R) -> R {
+    CONTEXT.sync_scope(nested(fields), f)
+}
+
+/// Поля текущего контекста; вне `scope` — пусто.
+pub fn current() -> Vec<(String, String)> {
+    CONTEXT.try_with(Clone::clone).unwrap_or_default()
+}
+
+pub(crate) async fn level_scope<F: Future>(logger: usize, level: LogLevel, future: F) -> F::Output {
+    let mut scopes = LEVEL_SCOPES.try_with(Clone::clone).unwrap_or_default();
+    scopes.push((logger, level));
+    LEVEL_SCOPES.scope(scopes, future).await
+}
+
+pub(crate) fn scoped_level(logger: usize) -> Option<LogLevel> {
+    LEVEL_SCOPES
+        .try_with(|scopes| innermost_level(scopes, logger))
+        .ok()
+        .flatten()
+}
+
+fn nested(fields: &[(&str, &str)]) -> Vec<(String, String)> {
+    let mut merged = current();
+    merged.retain(|(key, _)| !fields.iter().any(|(k, _)| k == key));
+    merged.extend(fields.iter().map(|&(k, v)| (k.to_owned(), v.to_owned())));
+    merged
+}
+
+// Вызывает `f` с записью, к полям которой спереди добавлен контекст задачи
+pub(crate) fn with_context<R>(record: &Record, f: impl FnOnce(&Record) -> R) -> R {
+    let mut f = Some(f);
+    let in_scope = CONTEXT.try_with(|context| {
+        if context.is_empty() {
+            return None;
+        }
+        let fields: Vec<(&str, &str)> = context
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .chain(record.fields.iter().copied())
+            .collect();
+        let f = f.take()?;
+        Some(f(&Record {
+            fields: &fields,
+            ..*record
+        }))
+    });
+    match (in_scope, f) {
+        (Ok(Some(result)), _) => result,
+        (_, Some(f)) => f(record),
+        (_, None) => unreachable!("callback is taken only when it returns a result"),
+    }
+}
@@ -0,0 +1,428 @@
+//! Канал управления работающим логгером (`LoggerBuilder::control_socket`): Unix-сокет
+//! или именованный канал Windows. Команда — строка, ответ — одна строка `ok ...` или
+//! `error: ...`; в одном соединении можно отправить несколько команд.
+//!
+//! ```text
+//! $ echo 'level debug' | nc -U /run/billing/log.sock
+//! ok override=DEBUG min=TRACE global=INFO
+//! ```
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+use crate::{
+    flush_outputs, stats_summary, AttachedSinks, Levels, LogLevel, LogStats, Record,
+    RotatingWriter, Sink, StatsCounters, GLOBAL_LOG_LEVEL,
+};
+
+const HELP: &str = "commands: level [<level>|reset], rotate, flush, stats, help";
+
+// Длиннее команд не бывает; защищает от клиента, который шлёт байты без перевода строки
+const MAX_COMMAND: u64 = 256;
+
+// Что канал видит у логгера. Ссылки слабые: логгер закрывается, не дожидаясь канала.
+// Команды разбирают и канал, и HTTP-обработчик (admin)
+pub(crate) struct Target {
+    pub levels: Weak<Levels>,
+    pub writers: Vec<Weak<RotatingWriter>>,
+    pub sinks: Vec<Weak<dyn Sink>>,
+    pub attached: Weak<AttachedSinks>,
+    pub stats: Weak<StatsCounters>,
+}
+
+// Уровни логгера для ответа
+pub(crate) struct LevelsView {
+    pub overridden: Option<LogLevel>,
+    pub min: LogLevel,
+    pub global: Option<LogLevel>,
+}
+
+impl Target {
+    pub fn levels(&self) -> io::Result<LevelsView> {
+        let levels = upgrade(&self.levels)?;
+        Ok(LevelsView {
+            overridden: levels.overridden(),
+            min: levels.min(),
+            global: LogLevel::from_index(GLOBAL_LOG_LEVEL.load(Ordering::SeqCst) as u8),
+        })
+    }
+
+    // Уровень вместо глобального и verbosity; None — вернуть уровни из настроек
+    pub fn set_level(&self, level: Option<LogLevel>) -> io::Result<LevelsView> {
+        let levels = upgrade(&self.levels)?;
+        match level {
+            Some(level) => {
+                // min_level из verbosity иначе отсёк бы то, что просит оператор
+                levels.set_min(LogLevel::Trace);
+                levels.set_overridden(Some(level));
+                self.audit(&format!("level set to {}", level));
+            }
+            None => {
+                levels.reset();
+                self.audit("level reset to the configured one");
+            }
+        }
+        self.levels()
+    }
+
+    // На уровень ниже того, что сейчас пропускает логгер; Trace остаётся Trace.
+    // SIGUSR1 и именованное событие Windows
+    pub fn more_verbose(&self) -> io::Result<()> {
+        let view = self.levels()?;
+        let current = view
+            .overridden
+            .or(view.global)
+            .unwrap_or(LogLevel::Off)
+            .max(view.min);
+        let next = LogLevel::from_index((current as u8).saturating_sub(1)).unwrap_or(current);
+        self.set_level(Some(next)).map(drop)
+    }
+
+    pub fn rotate(&self) -> io::Result<()> {
+        let writers = self.writers()?;
+        self.audit("rotating log files");
+        let mut result = Ok(());
+        for writer in &writers {
+            if let Err(e) = writer.rotate_now() {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+
+    pub fn flush(&self) -> io::Result<()> {
+        let writers = self.writers()?;
+        let mut sinks: Vec<Arc<dyn Sink>> = self.sinks.iter().filter_map(Weak::upgrade).collect();
+        if let Some(attached) = self.attached.upgrade() {
+            let attached = attached.read().unwrap();
+            sinks.extend(attached.iter().map(|(_, sink)| Arc::clone(sink)));
+        }
+        flush_outputs(writers.iter(), &sinks)
+    }
+
+    // Счётчики и размер основного файла
+    pub fn stats(&self) -> io::Result<(LogStats, Option<u64>)> {
+        let stats = upgrade(&self.stats)?;
+        let file_size = self
+            .writers
+            .first()
+            .and_then(Weak::upgrade)
+            .map(|writer| writer.size.load(Ordering::Relaxed));
+        Ok((stats.snapshot(), file_size))
+    }
+
+    fn execute(&self, command: &str) -> String {
+        match self.run(command) {
+            Ok(reply) => format!("ok {}", reply).trim_end().to_owned(),
+            Err(e) => format!("error: {}", e),
+        }
+    }
+
+    fn run(&self, command: &str) -> io::Result<String> {
+        let words: Vec<&str> = command.split_whitespace().collect();
+        match words.as_slice() {
+            ["level"] => self.levels().map(|view| view.to_string()),
+            ["level", "reset"] => self.set_level(None).map(|view| view.to_string()),
+            ["level", name] => {
+                let level = name
+                    .parse()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                self.set_level(Some(level)).map(|view| view.to_string())
+            }
+            ["rotate"] => self.rotate().map(|()| String::new()),
+            ["flush"] => self.flush().map(|()| String::new()),
+            ["stats"] => self
+                .stats()
+                .map(|(stats, file_size)| stats_summary(&stats, file_size)),
+            ["help"] => Ok(HELP.to_owned()),
+            [] => Err(io::Error::new(io::ErrorKind::InvalidInput, "empty command")),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown command {:?}; {}", command, HELP),
+            )),
+        }
+    }
+
+    fn writers(&self) -> io::Result<Vec<Arc<RotatingWriter>>> {
+        upgrade(&self.stats)?; // логгер закрыт
+        Ok(self.writers.iter().filter_map(Weak::upgrade).collect())
+    }
+
+    // Изменения по команде видны в самом логе
+    fn audit(&self, action: &str) {
+        for writer in self.writers.iter().filter_map(Weak::upgrade) {
+            let _ = writer.write(
+                &Record::new(LogLevel::Info, format_args!("Control: {}", action))
+                    .with_target("loglib::control"),
+            );
+        }
+    }
+}
+
+impl std::fmt::Display for LevelsView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "override={} min={} global={}",
+            self.overridden.map_or("none", |l| l.as_str()),
+            self.min,
+            self.global.map_or("none", |l| l.as_str()),
+        )
+    }
+}
+
+fn upgrade<T: ?Sized>(weak: &Weak<T>) -> io::Result<Arc<T>> {
+    weak.upgrade()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "logger is closed"))
+}
+
+// Слушатель, которым могут владеть несколько логгеров подряд: логгер, собранный с тем же
+// адресом (например, в `reconfigure_global`, пока прежний ещё работает), не открывает
+// второй слушатель, а присоединяется к первому. Команды получает самый новый из живых
+// логгеров; когда закрывается последний, поток слушателя завершается.
+pub(crate) struct Listening<T> {
+    targets: Mutex<Vec<(u64, Arc<T>)>>,
+    next_id: AtomicU64,
+    stop: AtomicBool,
+    wake: Box<dyn Fn() + Send + Sync>, // будит поток в accept
+}
+
+impl<T> Listening<T> {
+    pub fn new(target: T, wake: impl Fn() + Send + Sync + 'static) -> (Arc<Self>, Handle<T>) {
+        let listening = Arc::new(Listening {
+            targets: Mutex::new(vec![(0, Arc::new(target))]),
+            next_id: AtomicU64::new(1),
+            stop: AtomicBool::new(false),
+            wake: Box::new(wake),
+        });
+        let handle = Handle {
+            listening: Arc::clone(&listening),
+            id: 0,
+        };
+        (listening, handle)
+    }
+
+    // Err — слушатель уже останавливается, нужен новый
+    pub fn join(self: &Arc<Self>, target: T) -> Result<Handle<T>, T> {
+        let mut targets = self.targets.lock().unwrap();
+        if targets.is_empty() {
+            return Err(target);
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        targets.push((id, Arc::new(target)));
+        Ok(Handle {
+            listening: Arc::clone(self),
+            id,
+        })
+    }
+
+    pub fn current(&self) -> Option<Arc<T>> {
+        let targets = self.targets.lock().unwrap();
+        targets.last().map(|(_, target)| Arc::clone(target))
+    }
+
+    pub fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Acquire)
+    }
+}
+
+// Доля логгера в слушателе
+pub(crate) struct Handle<T> {
+    listening: Arc<Listening<T>>,
+    id: u64,
+}
+
+impl<T> Drop for Handle<T> {
+    fn drop(&mut self) {
+        let mut targets = self.listening.targets.lock().unwrap();
+        targets.retain(|&(id, _)| id != self.id);
+        if targets.is_empty() {
+            self.listening.stop.store(true, Ordering::Release);
+            drop(targets);
+            (self.listening.wake)();
+        }
+    }
+}
+
+// Каналы, которые слушает этот процесс
+static SERVERS: Mutex<Vec<(PathBuf, Weak<Listening<Target>>)>> = Mutex::new(Vec::new());
+
+/// Слушает канал в потоке `loglib-control`; при закрытии логгера поток завершается,
+/// а сокет удаляется. Канал, который уже слушает этот процесс, переходит к новому логгеру.
+pub(crate) struct Server {
+    _handle: Handle<Target>,
+}
+
+impl Server {
+    pub fn start(path: &Path, target: Target) -> io::Result<Server> {
+        let mut servers = SERVERS.lock().unwrap();
+        servers.retain(|(_, listening)| listening.strong_count() > 0);
+        let running = servers
+            .iter()
+            .filter(|(listened, _)| listened == path)
+            .find_map(|(_, listening)| listening.upgrade());
+        let target = match running {
+            Some(listening) => match listening.join(target) {
+                Ok(handle) => return Ok(Server { _handle: handle }),
+                Err(target) => target,
+            },
+            None => target,
+        };
+        let listener = platform::bind(path)?;
+        let wake_path = path.to_path_buf();
+        let (listening, handle) = Listening::new(target, move || platform::wake(&wake_path));
+        let shared = Arc::clone(&listening);
+        std::thread::Builder::new()
+            .name("loglib-control".to_owned())
+            .spawn(move || {
+                while let Ok(client) = platform::accept(&listener) {
+                    if shared.stopped() {
+                        break;
+                    }
+                    if let Some(target) = shared.current() {
+                        let _ = session(client, &target);
+                    }
+                }
+            })?;
+        servers.push((path.to_path_buf(), Arc::downgrade(&listening)));
+        Ok(Server { _handle: handle })
+    }
+}
+
+fn session<S: Read + Write>(client: S, target: &Target) -> io::Result<()> {
+    let mut reader = BufReader::new(client);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.by_ref().take(MAX_COMMAND).read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        if !line.ends_with('\n') && line.len() as u64 == MAX_COMMAND {
+            writeln!(reader.get_mut(), "error: command is too long")?;
+            return Ok(());
+        }
+        let reply = target.execute(line.trim());
+        let client = reader.get_mut();
+        writeln!(client, "{}", reply)?;
+        client.flush()?;
+    }
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::fs::{self, Permissions};
+    use std::io;
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+    use std::time::Duration;
+
+    // Клиент, который замолчал, не держит канал дольше
+    const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+    pub fn bind(path: &Path) -> io::Result<UnixListener> {
+        // Сокет упавшего процесса мешает bind; сокет, который отвечает, не трогаем
+        let stale = fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket())
+            && UnixStream::connect(path).is_err();
+        if stale {
+            let _ = fs::remove_file(path);
+        }
+        let listener = UnixListener::bind(path)?;
+        fs::set_permissions(path, Permissions::from_mode(0o600))?;
+        Ok(listener)
+    }
+
+    pub fn accept(listener: &UnixListener) -> io::Result<UnixStream> {
+        let (client, _) = listener.accept()?;
+        client.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+        client.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+        Ok(client)
+    }
+
+    // Будит поток в accept, чтобы он увидел остановку
+    pub fn wake(path: &Path) {
+        let _ = UnixStream::connect(path);
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::cell::Cell;
+    use std::ffi::OsStr;
+    use std::fs::{File, OpenOptions};
+    use std::io;
+    use std::os::windows::ffi::OsStrExt;
+    use std::os::windows::io::{AsRawHandle, FromRawHandle};
+    use std::path::Path;
+
+    use windows_sys::Win32::Foundation::{
+        GetLastError, ERROR_PIPE_CONNECTED, INVALID_HANDLE_VALUE,
+    };
+    use windows_sys::Win32::Storage::FileSystem::{
+        FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX,
+    };
+    use windows_sys::Win32::System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS,
+        PIPE_TYPE_BYTE, PIPE_WAIT,
+    };
+
+    // Имя вида \\.\pipe\billing-log; один экземпляр канала, клиенты по очереди
+    pub struct Listener {
+        name: Vec<u16>,
+        first: Cell<Option<File>>,
+    }
+
+    fn create(name: &[u16], first: bool) -> io::Result<File> {
+        let mut mode = PIPE_ACCESS_DUPLEX;
+        if first {
+            mode |= FILE_FLAG_FIRST_PIPE_INSTANCE; // имя не занято другим процессом
+        }
+        let handle = unsafe {
+            CreateNamedPipeW(
+                name.as_ptr(),
+                mode,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                1,
+                4096,
+                4096,
+                0,
+                std::ptr::null(),
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(unsafe { File::from_raw_handle(handle as _) })
+    }
+
+    pub fn bind(path: &Path) -> io::Result<Listener> {
+        let name: Vec<u16> = OsStr::new(path).encode_wide().chain(Some(0)).collect();
+        let first = create(&name, true)?;
+        Ok(Listener {
+            name,
+            first: Cell::new(Some(first)),
+        })
+    }
+
+    pub fn accept(listener: &Listener) -> io::Result<File> {
+        let pipe = match listener.first.take() {
+            Some(pipe) => pipe,
+            None => create(&listener.name, false)?,
+        };
+        let connected =
+            unsafe { ConnectNamedPipe(pipe.as_raw_handle() as _, std::ptr::null_mut()) };
+        if connected == 0 && unsafe { GetLastError() } != ERROR_PIPE_CONNECTED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(pipe)
+    }
+
+    pub fn wake(path: &Path) {
+        let _ = OpenOptions::new().read(true).write(true).open(path);
+    }
+}
@@ -0,0 +1,197 @@
+//! Сквозной идентификатор запроса: приходит в заголовке HTTP (или создаётся), попадает
+//! в каждую запись как поле `correlation_id` и уходит дальше в заголовке исходящих
+//! запросов. По нему склеиваются логи нескольких сервисов.
+//!
+//! ```ignore
+//! let id = CorrelationId::from_headers_or_generate(request.headers().iter()
+//!     .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?))));
+//! let _guard = id.enter();            // синхронный код: до конца области
+//! info!(logger, "handling request");  // ... correlation_id=4f1c... handling request
+//! let (name, value) = id.header();
+//! client.get(url).header(name, value).send()?;
+//! ```
+
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::Record;
+
+// Открытые CorrelationGuard во всех потоках: пока их нет, запись не заглядывает в thread-local
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static CURRENT: RefCell<Option<CorrelationId>> = const { RefCell::new(None) };
+}
+
+/// Идентификатор запроса: 1–128 символов из букв, цифр и `-_.:`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CorrelationId(String);
+
+impl CorrelationId {
+    /// Имя поля в записях
+    pub const FIELD: &'static str = "correlation_id";
+    /// Заголовок, который пишет `header()`
+    pub const HEADER: &'static str = "X-Correlation-ID";
+    /// Заголовки, из которых читает `from_headers`, по приоритету
+    pub const INCOMING_HEADERS: [&'static str; 2] = ["X-Correlation-ID", "X-Request-ID"];
+
+    /// Новый случайный идентификатор: 32 шестнадцатеричных символа
+    pub fn generate() -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos());
+        let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let half = |salt: u64| {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u128(nanos);
+            hasher.write_u64(count ^ salt);
+            hasher.write_u32(std::process::id());
+            hasher.finish()
+        };
+        CorrelationId(format!("{:016x}{:016x}", half(0), half(u64::MAX)))
+    }
+
+    /// Идентификатор из чужой строки; `None`, если он пуст, длиннее 128 символов или
+    /// содержит что-то кроме букв, цифр и `-_.:` (защита от инъекций в логи)
+    pub fn new(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let valid = !value.is_empty()
+            && value.len() <= 128
+            && value
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"-_.:".contains(&b));
+        valid.then(|| CorrelationId(value.to_owned()))
+    }
+
+    /// Идентификатор из заголовков входящего запроса (`INCOMING_HEADERS`, имена без
+    /// учёта регистра). Недопустимое значение пропускается.
+    pub fn from_headers<'h>(headers: impl IntoIterator<Item = (&'h str, &'h str)>) -> Option<Self> {
+        let mut found: [Option<CorrelationId>; 2] = [None, None];
+        for (name, value) in headers {
+            let index = Self::INCOMING_HEADERS
+                .iter()
+                .position(|header| header.eq_ignore_ascii_case(name.trim()));
+            if let Some(index) = index {
+                if found[index].is_none() {
+                    found[index] = Self::new(value);
+                }
+            }
+        }
+        let [correlation, request] = found;
+        correlation.or(request)
+    }
+
+    /// Как `from_headers`, но без заголовка создаёт новый идентификатор
+    pub fn from_headers_or_generate<'h>(
+        headers: impl IntoIterator<Item = (&'h str, &'h str)>,
+    ) -> Self {
+        Self::from_headers(headers).unwrap_or_else(Self::generate)
+    }
+
+    /// Заголовок для исходящего запроса: `(HEADER, значение)`
+    pub fn header(&self) -> (&'static str, &str) {
+        (Self::HEADER, &self.0)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Делает идентификатор текущим для потока до drop возвращённого guard: все записи
+    /// потока получат поле `correlation_id`. Вложенные области восстанавливают внешнюю.
+    /// В async-коде используйте `scope` — задача может сменить поток.
+    pub fn enter(&self) -> CorrelationGuard {
+        let previous = CURRENT.with(|current| current.replace(Some(self.clone())));
+        ACTIVE.fetch_add(1, Ordering::Relaxed);
+        CorrelationGuard {
+            previous,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Выполняет `future` с этим идентификатором в контексте задачи (см. `context::scope`)
+    #[cfg(feature = "tokio")]
+    pub async fn scope<F: std::future::Future>(&self, future: F) -> F::Output {
+        crate::context::scope(&[(Self::FIELD, self.as_str())], future).await
+    }
+
+    /// Текущий идентификатор: из контекста задачи tokio, иначе из `enter` этого потока
+    pub fn current() -> Option<Self> {
+        #[cfg(feature = "tokio")]
+        if let Some((_, value)) = crate::context::current()
+            .into_iter()
+            .rev()
+            .find(|(key, _)| key == Self::FIELD)
+        {
+            return Some(CorrelationId(value));
+        }
+        if ACTIVE.load(Ordering::Relaxed) == 0 {
+            return None;
+        }
+        CURRENT
+            .try_with(|current| current.borrow().clone())
+            .ok()
+            .flatten()
+    }
+}
+
+impl fmt::Display for CorrelationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Область `CorrelationId::enter`; привязана к потоку
+#[must_use = "идентификатор действует, пока guard жив"]
+pub struct CorrelationGuard {
+    previous: Option<CorrelationId>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for CorrelationGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        let _ = CURRENT.try_with(|current| *current.borrow_mut() = previous);
+        ACTIVE.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// Добавляет поле correlation_id из области потока, если его ещё нет (его могли
+// поставить контекст задачи или сама запись)
+pub(crate) fn with_correlation<R>(record: &Record, f: impl FnOnce(&Record) -> R) -> R {
+    if ACTIVE.load(Ordering::Relaxed) == 0
+        || record
+            .fields
+            .iter()
+            .any(|(key, _)| *key == CorrelationId::FIELD)
+    {
+        return f(record);
+    }
+    let mut f = Some(f);
+    let added = CURRENT.try_with(|current| {
+        let current = current.borrow();
+        let id = current.as_ref()?;
+        let fields: Vec<(&str, &str)> = record
+            .fields
+            .iter()
+            .copied()
+            .chain([(CorrelationId::FIELD, id.as_str())])
+            .collect();
+        let f = f.take()?;
+        Some(f(&Record {
+            fields: &fields,
+            ..*record
+        }))
+    });
+    match (added, f) {
+        (Ok(Some(result)), _) => result,
+        (_, Some(f)) => f(record),
+        (_, None) => unreachable!("callback is taken only when it returns a result"),
+    }
+}
@@ -0,0 +1,64 @@
+//! Перекодировка файлов лога из UTF-8 в устаревшие кодовые страницы (фича `encoding`)
+//! для программ, которые читают только Windows-1251, Shift_JIS (cp932) и подобные.
+//! Строка собирается в UTF-8 как обычно и перекодируется перед записью; символы, которых
+//! нет в кодовой странице, заменяются на `?`.
+
+use std::io;
+
+use encoding_rs::{EncoderResult, Encoding, SHIFT_JIS, UTF_8};
+
+pub(crate) struct Transcoder(&'static Encoding);
+
+impl Transcoder {
+    /// Кодировка по имени WHATWG (`windows-1251`, `cp1251`, `shift_jis`, `windows-31j`...)
+    /// или `cp932`.
+    /// `None` — UTF-8, перекодировать нечего.
+    pub fn new(label: &str) -> io::Result<Option<Transcoder>> {
+        let label = label.trim();
+        // Имена кодовых страниц Windows, которых нет среди меток WHATWG
+        let encoding = match label.to_ascii_lowercase().as_str() {
+            "cp932" | "windows-932" => Some(SHIFT_JIS),
+            _ => Encoding::for_label(label.as_bytes()),
+        };
+        let encoding = encoding.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown output encoding {:?}", label),
+            )
+        })?;
+        // UTF-16 кодировщиков нет: encoding_rs пишет их в UTF-8
+        let encoding = encoding.output_encoding();
+        Ok((encoding != UTF_8).then_some(Transcoder(encoding)))
+    }
+
+    pub fn transcode(&self, line: &mut Vec<u8>) {
+        let Ok(text) = std::str::from_utf8(line) else {
+            return; // строки собираются в UTF-8; на всякий случай пишем как есть
+        };
+        let mut encoder = self.0.new_encoder();
+        let mut out = Vec::with_capacity(line.len());
+        let mut input = text;
+        loop {
+            if let Some(needed) =
+                encoder.max_buffer_length_from_utf8_without_replacement(input.len())
+            {
+                out.reserve(needed);
+            }
+            let (result, read) =
+                encoder.encode_from_utf8_to_vec_without_replacement(input, &mut out, true);
+            input = &input[read..];
+            match result {
+                EncoderResult::InputEmpty => break,
+                EncoderResult::OutputFull => {}
+                // Через кодировщик, чтобы не сбить состояние ISO-2022-JP
+                EncoderResult::Unmappable(_) => {
+                    out.reserve(8);
+                    let _ =
+                        encoder.encode_from_utf8_to_vec_without_replacement("?", &mut out, false);
+                }
+            }
+        }
+        line.clear();
+        line.extend_from_slice(&out);
+    }
+}
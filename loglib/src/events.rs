@@ -0,0 +1,68 @@
+//! Именованные события Windows (`LoggerBuilder::control_events`) — аналог SIGUSR1/SIGUSR2
+//! для служб: администратор взводит событие, поток `loglib-events` ротирует файлы или
+//! меняет уровень.
+//!
+//! ```text
+//! PS> [System.Threading.EventWaitHandle]::OpenExisting('Global\billing-log-debug').Set()
+//! ```
+
+use std::ffi::OsStr;
+use std::io;
+use std::os::windows::ffi::OsStrExt;
+
+use windows_sys::Win32::Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0, WAIT_TIMEOUT};
+use windows_sys::Win32::System::Threading::{CreateEventW, WaitForMultipleObjects};
+
+use crate::control::Target;
+
+// Как часто поток проверяет, жив ли логгер
+const POLL_MS: u32 = 1000;
+
+// Суффиксы имён событий, порядок — индекс в WaitForMultipleObjects
+const SUFFIXES: [&str; 3] = ["-rotate", "-debug", "-reset"];
+
+struct Events([HANDLE; 3]);
+
+impl Drop for Events {
+    fn drop(&mut self) {
+        for &handle in &self.0 {
+            if handle != 0 {
+                unsafe { CloseHandle(handle) };
+            }
+        }
+    }
+}
+
+pub(crate) fn watch(prefix: &str, target: Target) -> io::Result<()> {
+    let mut events = Events([0; 3]);
+    for (handle, suffix) in events.0.iter_mut().zip(SUFFIXES) {
+        let name: Vec<u16> = OsStr::new(&format!("{}{}", prefix, suffix))
+            .encode_wide()
+            .chain(Some(0))
+            .collect();
+        // Автосброс: одно Set() — одно действие
+        *handle = unsafe { CreateEventW(std::ptr::null(), 0, 0, name.as_ptr()) };
+        if *handle == 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    std::thread::Builder::new()
+        .name("loglib-events".to_owned())
+        .spawn(move || loop {
+            let signaled = unsafe {
+                WaitForMultipleObjects(events.0.len() as u32, events.0.as_ptr(), 0, POLL_MS)
+            };
+            // Ошибки ротации логгер сам пишет в системный лог
+            let _ = match signaled.wrapping_sub(WAIT_OBJECT_0) {
+                0 => target.rotate(),
+                1 => target.more_verbose(),
+                2 => target.set_level(None).map(drop),
+                _ if signaled == WAIT_TIMEOUT => Ok(()),
+                _ => break, // WAIT_FAILED
+            };
+            if target.levels.strong_count() == 0 {
+                break;
+            }
+        })?;
+    Ok(())
+}
@@ -1,25 +1,95 @@
-use chrono::Local;
+use chrono::{DateTime, Local};
 use once_cell::sync::Lazy;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
 use std::fs::{self, File, OpenOptions};
-use std::io::{self, Seek, SeekFrom, Write};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 use std::process;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Arc, Mutex};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, RwLock, Weak};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "http_admin")]
+pub mod admin;
+pub mod alert;
+mod config;
+#[cfg(feature = "tokio")]
+pub mod context;
+mod control;
+mod correlation;
+#[cfg(feature = "encoding")]
+mod encoding;
+#[cfg(target_os = "windows")]
+mod events;
+mod maintenance;
+#[cfg(unix)]
+pub mod mmap;
+pub mod reader;
+#[cfg(feature = "s3")]
+pub mod s3;
+#[cfg(unix)]
+mod signals;
+#[cfg(feature = "smtp")]
+pub mod smtp;
+mod startup;
+#[cfg(feature = "regex")]
+mod suppress;
+#[cfg(feature = "test-util")]
+pub mod test;
+mod trace;
+#[cfg(all(feature = "io_uring", target_os = "linux"))]
+mod uring;
+#[cfg(feature = "webhook")]
+pub mod webhook;
+#[cfg(target_os = "windows")]
+pub mod windows_service;
+
+pub use config::{FileOutput, LoggerConfig};
+pub use correlation::{CorrelationGuard, CorrelationId};
+use maintenance::{ArchivePlan, Maintenance};
+#[cfg(feature = "regex")]
+pub use suppress::{SuppressAction, SuppressRule};
+pub use trace::{TraceContext, TraceGuard};
 
 // ===== Уровни логгирования =====
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum LogLevel {
     Trace,
     Debug,
     Info,
+    #[cfg_attr(feature = "serde", serde(alias = "warn"))]
     Warning,
     Error,
     Fatal,
+    /// Только для фильтра: `set_global_log_level(LogLevel::Off)` отключает весь вывод
+    Off,
 }
 
 impl LogLevel {
+    const ALL: [LogLevel; 7] = [
+        LogLevel::Trace,
+        LogLevel::Debug,
+        LogLevel::Info,
+        LogLevel::Warning,
+        LogLevel::Error,
+        LogLevel::Fatal,
+        LogLevel::Off,
+    ];
+
+    // Обратно из `level as u8`; для уровней в атомарных переменных
+    fn from_index(index: u8) -> Option<LogLevel> {
+        Self::ALL.get(index as usize).copied()
+    }
+
     fn as_str(&self) -> &'static str {
         match self {
             LogLevel::Trace => "TRACE",
@@ -28,30 +98,68 @@ impl LogLevel {
             LogLevel::Warning => "WARNING",
             LogLevel::Error => "ERROR",
             LogLevel::Fatal => "FATAL",
+            LogLevel::Off => "OFF",
         }
     }
 
-    #[cfg(target_os = "linux")]
-    fn to_syslog_level(&self) -> syslog::Severity {
+    #[cfg(unix)]
+    fn to_syslog_level(self) -> syslog::Severity {
         use syslog::Severity::*;
         match self {
             LogLevel::Trace | LogLevel::Debug | LogLevel::Info => LOG_INFO,
             LogLevel::Warning => LOG_WARNING,
-            LogLevel::Error | LogLevel::Fatal => LOG_ERR,
+            LogLevel::Error | LogLevel::Fatal | LogLevel::Off => LOG_ERR,
         }
     }
 
     #[cfg(target_os = "windows")]
-    fn to_winlog_level(&self) -> winlog_rs::LogLevel {
+    fn to_winlog_level(self) -> winlog_rs::LogLevel {
         match self {
             LogLevel::Trace => winlog_rs::LogLevel::Trace,
             LogLevel::Debug | LogLevel::Info => winlog_rs::LogLevel::Debug,
             LogLevel::Warning => winlog_rs::LogLevel::Warning,
-            LogLevel::Error | LogLevel::Fatal => winlog_rs::LogLevel::Error,
+            LogLevel::Error | LogLevel::Fatal | LogLevel::Off => winlog_rs::LogLevel::Error,
         }
     }
 }
 
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// Ошибка разбора уровня из строки
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseLogLevelError(String);
+
+impl fmt::Display for ParseLogLevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown log level: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseLogLevelError {}
+
+impl FromStr for LogLevel {
+    type Err = ParseLogLevelError;
+
+    /// Принимает имя уровня в любом регистре ("info", "WARNING", "warn") или число 0..=6
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let level = match s.trim().to_ascii_lowercase().as_str() {
+            "trace" | "0" => LogLevel::Trace,
+            "debug" | "1" => LogLevel::Debug,
+            "info" | "2" => LogLevel::Info,
+            "warning" | "warn" | "3" => LogLevel::Warning,
+            "error" | "4" => LogLevel::Error,
+            "fatal" | "5" => LogLevel::Fatal,
+            "off" | "none" | "6" => LogLevel::Off,
+            _ => return Err(ParseLogLevelError(s.to_owned())),
+        };
+        Ok(level)
+    }
+}
+
 // ===== Глобальный уровень фильтрации =====
 
 static GLOBAL_LOG_LEVEL: AtomicUsize = AtomicUsize::new(1); // по умолчанию Debug
@@ -61,450 +169,6283 @@ pub fn set_global_log_level(level: LogLevel) {
 }
 
 fn should_log(level: LogLevel) -> bool {
-    (level as usize) >= GLOBAL_LOG_LEVEL.load(Ordering::SeqCst)
+    level != LogLevel::Off && (level as usize) >= GLOBAL_LOG_LEVEL.load(Ordering::SeqCst)
 }
 
-// ===== Системные логгеры (платформозависимо) =====
+// Logger::level_override не задан
+const NO_LEVEL_OVERRIDE: u8 = u8::MAX;
 
-#[cfg(target_os = "linux")]
-type SystemLogger = syslog::Logger;
+// Уровни одного логгера; общие с каналом управления
+struct Levels {
+    min: AtomicU8,        // записи ниже уровня отбрасываются этим логгером
+    overridden: AtomicU8, // уровень вместо глобального; NO_LEVEL_OVERRIDE — нет
+    configured: (LogLevel, Option<LogLevel>), // из построителя, для reset
+}
 
-#[cfg(target_os = "windows")]
-type SystemLogger = winlog_rs::WinEventLogger;
+impl Levels {
+    fn new(min: LogLevel, overridden: Option<LogLevel>) -> Arc<Self> {
+        Arc::new(Levels {
+            min: AtomicU8::new(min as u8),
+            overridden: AtomicU8::new(overridden.map_or(NO_LEVEL_OVERRIDE, |l| l as u8)),
+            configured: (min, overridden),
+        })
+    }
 
-// ===== Кастомный ротирующий писатель =====
+    fn reset(&self) {
+        self.set_min(self.configured.0);
+        self.set_overridden(self.configured.1);
+    }
 
-struct RotatingWriter {
-    dir: PathBuf,
-    basename: String,
-    max_size: u64,
-    max_files: usize,
-    file: Arc<Mutex<Option<File>>>,
-    app_info: String,
-    system_logger: Option<SystemLogger>, // для логов об ошибках
+    fn min(&self) -> LogLevel {
+        LogLevel::from_index(self.min.load(Ordering::Relaxed)).unwrap_or(LogLevel::Trace)
+    }
+
+    fn set_min(&self, level: LogLevel) {
+        self.min.store(level as u8, Ordering::Relaxed);
+    }
+
+    fn overridden(&self) -> Option<LogLevel> {
+        LogLevel::from_index(self.overridden.load(Ordering::Relaxed))
+    }
+
+    fn set_overridden(&self, level: Option<LogLevel>) {
+        let value = level.map_or(NO_LEVEL_OVERRIDE, |level| level as u8);
+        self.overridden.store(value, Ordering::Relaxed);
+    }
 }
 
-impl RotatingWriter {
-    const MIN_SIZE: u64 = 256; // минимальный размер, чтобы вместить заголовок + пару строк
+// ===== Временное повышение подробности =====
 
-    fn new<P: AsRef<Path>>(
-        dir: P,
-        basename: &str,
-        max_size: u64,
-        max_files: usize,
-        app_info: &str,
-        system_logger: Option<SystemLogger>,
-    ) -> io::Result<Self> {
-        if max_size < Self::MIN_SIZE {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                format!("max_size must be at least {} bytes", Self::MIN_SIZE),
-            ));
+// Открытые области with_level_scope во всех потоках и задачах: пока их нет,
+// проверка уровня не заглядывает в thread-local
+static LEVEL_SCOPES_ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    // Логгер (адрес) и уровень области; вложенные — в конце
+    static LEVEL_SCOPES: RefCell<Vec<(usize, LogLevel)>> = const { RefCell::new(Vec::new()) };
+}
+
+// Учитывает область в LEVEL_SCOPES_ACTIVE, пока жива
+struct LevelScopeActive;
+
+impl LevelScopeActive {
+    fn new() -> Self {
+        LEVEL_SCOPES_ACTIVE.fetch_add(1, Ordering::Relaxed);
+        LevelScopeActive
+    }
+}
+
+impl Drop for LevelScopeActive {
+    fn drop(&mut self) {
+        LEVEL_SCOPES_ACTIVE.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// Снимает область потока и при панике внутри неё
+struct ThreadLevelScope {
+    _active: LevelScopeActive,
+}
+
+impl Drop for ThreadLevelScope {
+    fn drop(&mut self) {
+        let _ = LEVEL_SCOPES.try_with(|scopes| scopes.borrow_mut().pop());
+    }
+}
+
+// Уровень самой внутренней области этого логгера
+fn innermost_level(scopes: &[(usize, LogLevel)], logger: usize) -> Option<LogLevel> {
+    scopes
+        .iter()
+        .rev()
+        .find(|(key, _)| *key == logger)
+        .map(|&(_, level)| level)
+}
+
+// ===== Подробность для CLI =====
+
+/// Флаги `-v`/`-q` командной строки: сколько раз указан `-v` и есть ли `-q`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Verbosity {
+    pub verbose: u8,
+    pub quiet: bool,
+}
+
+impl Verbosity {
+    /// Флаги из `std::env::args`: `-v`, `-vv`, `--verbose` (можно повторять), `-q`, `--quiet`.
+    /// Остальные аргументы пропускаются, после `--` разбор останавливается.
+    pub fn from_args() -> Self {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    /// Как `from_args`, но по заданному списку аргументов (без имени программы).
+    pub fn parse<I, S>(args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut verbosity = Verbosity::default();
+        for arg in args {
+            match arg.as_ref() {
+                "--" => break,
+                "--verbose" => verbosity.verbose = verbosity.verbose.saturating_add(1),
+                "--quiet" => verbosity.quiet = true,
+                short => {
+                    // -v, -vvv, -vq; прочие короткие флаги не трогаем
+                    let flags = match short.strip_prefix('-') {
+                        Some(flags) if !flags.is_empty() && !flags.starts_with('-') => flags,
+                        _ => continue,
+                    };
+                    if !flags.chars().all(|c| c == 'v' || c == 'q') {
+                        continue;
+                    }
+                    for c in flags.chars() {
+                        match c {
+                            'v' => verbosity.verbose = verbosity.verbose.saturating_add(1),
+                            _ => verbosity.quiet = true,
+                        }
+                    }
+                }
+            }
+        }
+        verbosity
+    }
+
+    /// Без флагов — Warning, `-v` — Info, `-vv` — Debug, `-vvv` и больше — Trace.
+    /// `-q` важнее `-v`: только Error и Fatal.
+    pub fn level(self) -> LogLevel {
+        if self.quiet {
+            return LogLevel::Error;
+        }
+        match self.verbose {
+            0 => LogLevel::Warning,
+            1 => LogLevel::Info,
+            2 => LogLevel::Debug,
+            _ => LogLevel::Trace,
         }
+    }
+}
 
-        let dir = dir.as_ref().to_path_buf();
-        let path = dir.join(basename);
+// ===== Уровень, заданный при компиляции =====
 
-        fs::create_dir_all(&dir)?;
+// Индекс минимального уровня, который остаётся в макросах (Off — все вырезаны).
+// Если включено несколько фич, действует самая строгая.
+#[doc(hidden)]
+pub const __STATIC_MIN_LEVEL: usize = if cfg!(any(
+    feature = "max_level_off",
+    all(not(debug_assertions), feature = "release_max_level_off")
+)) {
+    LogLevel::Off as usize
+} else if cfg!(any(
+    feature = "max_level_fatal",
+    all(not(debug_assertions), feature = "release_max_level_fatal")
+)) {
+    LogLevel::Fatal as usize
+} else if cfg!(any(
+    feature = "max_level_error",
+    all(not(debug_assertions), feature = "release_max_level_error")
+)) {
+    LogLevel::Error as usize
+} else if cfg!(any(
+    feature = "max_level_warning",
+    all(not(debug_assertions), feature = "release_max_level_warning")
+)) {
+    LogLevel::Warning as usize
+} else if cfg!(any(
+    feature = "max_level_info",
+    all(not(debug_assertions), feature = "release_max_level_info")
+)) {
+    LogLevel::Info as usize
+} else if cfg!(any(
+    feature = "max_level_debug",
+    all(not(debug_assertions), feature = "release_max_level_debug")
+)) {
+    LogLevel::Debug as usize
+} else {
+    LogLevel::Trace as usize
+};
 
-        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+// ===== Запись лога =====
 
-        Ok(RotatingWriter {
-            dir,
-            basename: basename.to_owned(),
-            max_size,
-            max_files,
-            file: Arc::new(Mutex::new(Some(file))),
-            app_info: app_info.to_owned(),
-            system_logger,
-        })
+/// Одна запись лога — её получают приёмники, фильтры и форматтеры. Сообщение хранится
+/// как `fmt::Arguments` и форматируется сразу в буфер строки, без промежуточного `String`.
+/// С фичей `serde` запись сериализуется картой `time`, `level`, `target`, `module_path`,
+/// `file`, `line`, `thread`, `pid`, `message`, `fields`.
+#[derive(Debug, Clone, Copy)]
+pub struct Record<'a> {
+    level: LogLevel,
+    args: fmt::Arguments<'a>,
+    location: Option<SourceLocation>,
+    target: Option<&'a str>,
+    fields: &'a [(&'a str, &'a str)],
+    time: Option<DateTime<Local>>,
+    thread: u64,
+}
+
+/// Место в исходниках, откуда пришла запись (заполняют макросы)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SourceLocation {
+    pub file: &'static str,
+    pub line: u32,
+    pub module_path: &'static str,
+}
+
+impl<'a> Record<'a> {
+    pub fn new(level: LogLevel, args: fmt::Arguments<'a>) -> Self {
+        Record {
+            level,
+            args,
+            location: None,
+            target: None,
+            fields: &[],
+            time: None,
+            thread: THREAD_TAG.try_with(|tag| tag.id).unwrap_or(0),
+        }
     }
 
-    fn write(&self, level: LogLevel, message: &str) {
-        if !should_log(level) {
-            return;
+    /// Время события. Логгер ставит его по своим часам (`LoggerBuilder::clock`), если
+    /// оно не задано, поэтому во всех файлах и приёмниках у записи одно время.
+    pub fn with_time(mut self, time: DateTime<Local>) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// Поля `ключ=значение`: в файле пишутся после контекста, в syslog RFC 5424 —
+    /// как structured data.
+    pub fn with_fields(mut self, fields: &'a [(&'a str, &'a str)]) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// Категория записи для маршрутизации по файлам (`audit`, `net::client`)
+    pub fn with_target(mut self, target: &'a str) -> Self {
+        self.target = Some(target);
+        self
+    }
+
+    pub fn with_location(mut self, location: SourceLocation) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    pub fn level(&self) -> LogLevel {
+        self.level
+    }
+
+    pub fn args(&self) -> &fmt::Arguments<'a> {
+        &self.args
+    }
+
+    pub fn location(&self) -> Option<&SourceLocation> {
+        self.location.as_ref()
+    }
+
+    pub fn fields(&self) -> &[(&'a str, &'a str)] {
+        self.fields
+    }
+
+    /// `None` — запись ещё не прошла через логгер
+    pub fn time(&self) -> Option<DateTime<Local>> {
+        self.time
+    }
+
+    /// Номер потока, создавшего запись (как в `TID:` при `ThreadFormat::Id`)
+    pub fn thread_id(&self) -> u64 {
+        self.thread
+    }
+
+    pub fn pid(&self) -> u32 {
+        process::id()
+    }
+
+    pub fn module_path(&self) -> Option<&'static str> {
+        self.location.map(|location| location.module_path)
+    }
+
+    pub fn file(&self) -> Option<&'static str> {
+        self.location.map(|location| location.file)
+    }
+
+    pub fn line(&self) -> Option<u32> {
+        self.location.map(|location| location.line)
+    }
+
+    /// Явно заданная категория, иначе модуль вызова макроса, иначе пустая строка
+    pub fn target(&self) -> &str {
+        match (self.target, self.location) {
+            (Some(target), _) => target,
+            (None, Some(location)) => location.module_path,
+            (None, None) => "",
         }
+    }
 
-        let mut file_lock = self.file.lock().unwrap();
+    // Со временем по часам логгера, если его ещё нет
+    fn stamped(&self, clock: &dyn Clock) -> Self {
+        Record {
+            time: self.time.or_else(|| Some(clock.now())),
+            ..*self
+        }
+    }
+}
 
-        // Проверяем размер
-        let need_rotate = if let Some(ref mut file) = *file_lock {
-            let pos = file.seek(SeekFrom::End(0)).unwrap_or(0);
-            pos >= self.max_size
-        } else {
-            false
-        };
+#[cfg(feature = "serde")]
+impl serde::Serialize for Record<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
 
-        if need_rotate {
-            drop(file_lock); // освобождаем
+        struct Message<'a>(&'a fmt::Arguments<'a>);
+        impl serde::Serialize for Message<'_> {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self.0)
+            }
+        }
+        struct Fields<'a>(&'a [(&'a str, &'a str)]);
+        impl serde::Serialize for Fields<'_> {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_map(self.0.iter().copied())
+            }
+        }
+
+        let mut map = serializer.serialize_map(None)?;
+        if let Some(time) = self.time {
+            let time = time.to_rfc3339_opts(chrono::SecondsFormat::Millis, false);
+            map.serialize_entry("time", &time)?;
+        }
+        map.serialize_entry("level", &self.level)?;
+        map.serialize_entry("target", self.target())?;
+        if let Some(location) = self.location {
+            map.serialize_entry("module_path", location.module_path)?;
+            map.serialize_entry("file", location.file)?;
+            map.serialize_entry("line", &location.line)?;
+        }
+        map.serialize_entry("thread", &self.thread)?;
+        map.serialize_entry("pid", &self.pid())?;
+        map.serialize_entry("message", &Message(&self.args))?;
+        map.serialize_entry("fields", &Fields(self.fields))?;
+        map.end()
+    }
+}
+
+/// Запись с собственными данными — её получают и возвращают перехватчики
+/// (`LoggerBuilder::intercept`): можно поменять уровень, категорию, текст и поля.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedRecord {
+    pub level: LogLevel,
+    pub target: String,
+    pub message: String,
+    pub fields: Vec<(String, String)>,
+    pub location: Option<SourceLocation>,
+}
+
+impl OwnedRecord {
+    pub fn from_record(record: &Record) -> Self {
+        OwnedRecord {
+            level: record.level,
+            target: record.target().to_owned(),
+            message: record.args.to_string(),
+            fields: record
+                .fields
+                .iter()
+                .map(|&(k, v)| (k.to_owned(), v.to_owned()))
+                .collect(),
+            location: record.location,
+        }
+    }
+
+    /// Добавить поле `key=value`
+    pub fn with_field(mut self, key: &str, value: &str) -> Self {
+        self.fields.push((key.to_owned(), value.to_owned()));
+        self
+    }
+}
+
+type Interceptor = Arc<dyn Fn(OwnedRecord) -> Option<OwnedRecord> + Send + Sync>;
+
+// Дописывает поля к записи (см. Logger::add_enricher)
+type Enricher = Arc<dyn Fn(&Record, &mut Vec<(String, String)>) + Send + Sync>;
+
+// Фильтр выхода: false — запись в этот файл или приёмник не попадает
+type RecordFilter = Arc<dyn Fn(&Record) -> bool + Send + Sync>;
+
+// Вызывается с путём только что закрытого при ротации файла
+type ArchiveHook = Arc<dyn Fn(&Path) + Send + Sync>;
 
-            if let Err(e) = self.rotate() {
-                self.log_to_system(LogLevel::Error, &format!("Failed to rotate log: {}", e));
+// Внешняя команда после ротации; путь архива — последний аргумент
+struct ArchiveCommand {
+    program: String,
+    args: Vec<String>,
+    timeout: Duration,
+}
+
+impl ArchiveCommand {
+    const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+    fn new<S: AsRef<str>>(command: &[S], timeout: Duration) -> Option<Self> {
+        let (program, args) = command.split_first()?;
+        Some(ArchiveCommand {
+            program: program.as_ref().to_owned(),
+            args: args.iter().map(|arg| arg.as_ref().to_owned()).collect(),
+            timeout,
+        })
+    }
+
+    fn run(&self, archive: &Path) -> io::Result<()> {
+        let mut child = std::process::Command::new(&self.program)
+            .args(&self.args)
+            .arg(archive)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()?;
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return if status.success() {
+                    Ok(())
+                } else {
+                    Err(io::Error::other(format!("exited with {}", status)))
+                };
             }
+            if Instant::now() >= deadline {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("killed after {:?}", self.timeout),
+                ));
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
 
-            let mut file_lock = self.file.lock().unwrap();
-            *file_lock = match self.reopen_with_header() {
-                Ok(f) => f,
-                Err(e) => {
-                    self.log_to_system(LogLevel::Error, &format!("Failed to reopen log: {}", e));
-                    return;
-                }
-            };
+#[cfg(feature = "tokio")]
+use context::with_context;
 
-            // Пишем в новый файл
-            if let Some(ref mut file) = *file_lock {
-                let line = self.format_log_line(level, message);
-                let _ = writeln!(file, "{}", line);
-                let _ = file.flush();
+// Без фичи tokio контекста задачи нет
+#[cfg(not(feature = "tokio"))]
+fn with_context<R>(record: &Record, f: impl FnOnce(&Record) -> R) -> R {
+    f(record)
+}
+
+// Шаблон маршрута: "audit.*" и "net::*" — категория и всё внутри неё, иначе точное совпадение
+fn target_matches(pattern: &str, target: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some("") => true,
+        Some(prefix) => {
+            let parent = prefix.trim_end_matches(['.', ':']);
+            target.starts_with(prefix) || target == parent
+        }
+        None => target == pattern,
+    }
+}
+
+/// Какое время писать в начале строки
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum TimestampMode {
+    /// Местное время: `[2025-04-05 14:30:22.123]` (по умолчанию)
+    WallClock,
+    /// Время с создания логгера: `[+00:03:42.117]`
+    Elapsed,
+    /// Оба: `[2025-04-05 14:30:22.123] [+00:03:42.117]`
+    Both,
+}
+
+// [+ЧЧ:ММ:СС.ммм] — часы не ограничены 24
+fn write_elapsed<W: Write>(out: &mut W, elapsed: Duration) -> io::Result<()> {
+    let secs = elapsed.as_secs();
+    write!(
+        out,
+        "[+{:02}:{:02}:{:02}.{:03}]",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        elapsed.subsec_millis()
+    )
+}
+
+/// Как писать поле потока `TID:` в строке лога
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ThreadFormat {
+    /// Имя потока, если задано, иначе числовой id: `TID:worker-1`, `TID:7` (по умолчанию)
+    NameOrId,
+    /// Только числовой id: `TID:7`
+    Id,
+    /// Не писать поле
+    Off,
+}
+
+/// Формат строки в файле или тела сообщения в системном логе
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum LineFormat {
+    /// `[время] LEVEL PID:.. TID:.. поля сообщение` (по умолчанию)
+    #[default]
+    Text,
+    /// Одна JSON-строка на запись, для сборщиков логов:
+    /// `{"time":"..","level":"INFO","pid":..,"thread":"..","target":"..","message":"..","fields":{..}}`
+    Json,
+    /// JSON по Elastic Common Schema — для Elasticsearch/Kibana без своих шаблонов индекса:
+    /// `{"@timestamp":"..","log.level":"info","message":"..","process.pid":..,"host.name":..,"labels":{..}}`
+    Ecs,
+    /// Только текст сообщения, без времени, уровня и полей
+    Message,
+}
+
+/// Конец строки в файле
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum LineEnding {
+    /// `\n` (по умолчанию)
+    #[default]
+    Lf,
+    /// `\r\n` — для Блокнота и старых разборщиков под Windows
+    CrLf,
+}
+
+impl LineEnding {
+    fn as_bytes(self) -> &'static [u8] {
+        match self {
+            LineEnding::Lf => b"\n",
+            LineEnding::CrLf => b"\r\n",
+        }
+    }
+}
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+// Имя и номер потока не меняются, поэтому вычисляются один раз на поток
+struct ThreadTag {
+    id: u64,
+    name: Option<String>,
+}
+
+thread_local! {
+    static THREAD_TAG: ThreadTag = {
+        let thread = std::thread::current();
+        // ThreadId::as_u64 нестабилен — берём число из Debug-вывода "ThreadId(7)"
+        let id = format!("{:?}", thread.id())
+            .chars()
+            .filter(char::is_ascii_digit)
+            .collect::<String>()
+            .parse()
+            .unwrap_or(0);
+        ThreadTag {
+            id,
+            name: thread.name().map(str::to_owned),
+        }
+    };
+}
+
+// Буферы строк, которые поток переиспользует от записи к записи: форматирование не
+// выделяет память на каждую запись. Стек, а не один буфер — Display сообщения может
+// сам писать в лог
+thread_local! {
+    static FORMAT_BUFFERS: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+}
+
+const FORMAT_BUFFER_CAPACITY: usize = 256; // начальный размер буфера
+const FORMAT_BUFFER_LIMIT: usize = 64 * 1024; // больший буфер после длинной записи не хранится
+
+fn with_format_buffer<R>(f: impl FnOnce(&mut Vec<u8>) -> R) -> R {
+    let mut buf = FORMAT_BUFFERS
+        .try_with(|pool| pool.borrow_mut().pop())
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| Vec::with_capacity(FORMAT_BUFFER_CAPACITY));
+    let result = f(&mut buf);
+    if buf.capacity() <= FORMAT_BUFFER_LIMIT {
+        buf.clear();
+        // При завершении потока пула уже может не быть
+        let _ = FORMAT_BUFFERS.try_with(|pool| pool.borrow_mut().push(buf));
+    }
+    result
+}
+
+// То же для текста
+fn with_format_string<R>(f: impl FnOnce(&mut String) -> R) -> R {
+    with_format_buffer(|buf| {
+        let mut text = String::from_utf8(std::mem::take(buf)).unwrap_or_default(); // буфер пуст
+        let result = f(&mut text);
+        *buf = text.into_bytes();
+        result
+    })
+}
+
+fn write_thread_field<W: Write>(out: &mut W, format: ThreadFormat) -> io::Result<()> {
+    THREAD_TAG.with(|tag| match (format, &tag.name) {
+        (ThreadFormat::NameOrId, Some(name)) => write!(out, " TID:{}", name),
+        (ThreadFormat::NameOrId, None) | (ThreadFormat::Id, _) => write!(out, " TID:{}", tag.id),
+        (ThreadFormat::Off, _) => Ok(()),
+    })
+}
+
+// То же для JSON: `,"thread":"worker-1"` или `,"thread":7`
+fn write_json_thread_field<W: Write>(out: &mut W, format: ThreadFormat) -> io::Result<()> {
+    THREAD_TAG.with(|tag| match (format, &tag.name) {
+        (ThreadFormat::NameOrId, Some(name)) => {
+            out.write_all(b",\"thread\":")?;
+            write_json_str(out, name)
+        }
+        (ThreadFormat::NameOrId, None) | (ThreadFormat::Id, _) => {
+            write!(out, ",\"thread\":{}", tag.id)
+        }
+        (ThreadFormat::Off, _) => Ok(()),
+    })
+}
+
+// ===== JSON =====
+
+// Экранирует строку JSON прямо при форматировании, без промежуточной String
+struct JsonEscape<'a, W: Write> {
+    out: &'a mut W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> fmt::Write for JsonEscape<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut plain = 0;
+        for (i, b) in s.bytes().enumerate() {
+            let escaped: &[u8] = match b {
+                b'"' => b"\\\"",
+                b'\\' => b"\\\\",
+                b'\n' => b"\\n",
+                b'\r' => b"\\r",
+                b'\t' => b"\\t",
+                0..=0x1f => b"",
+                _ => continue,
+            };
+            let result = self.out.write_all(&s.as_bytes()[plain..i]).and_then(|()| {
+                if escaped.is_empty() {
+                    write!(self.out, "\\u{:04x}", b)
+                } else {
+                    self.out.write_all(escaped)
+                }
+            });
+            if let Err(e) = result {
+                self.error = Some(e);
+                return Err(fmt::Error);
             }
+            plain = i + 1;
+        }
+        self.out.write_all(&s.as_bytes()[plain..]).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+// Строка JSON в кавычках из любого Display
+fn write_json_display<W: Write, T: fmt::Display + ?Sized>(
+    out: &mut W,
+    value: &T,
+) -> io::Result<()> {
+    out.write_all(b"\"")?;
+    let mut escape = JsonEscape { out, error: None };
+    if fmt::write(&mut escape, format_args!("{}", value)).is_err() {
+        return Err(escape
+            .error
+            .unwrap_or_else(|| io::Error::other("formatting failed")));
+    }
+    escape.out.write_all(b"\"")
+}
+
+fn write_json_str<W: Write>(out: &mut W, value: &str) -> io::Result<()> {
+    write_json_display(out, value)
+}
+
+// `,"fields":{"k":"v",...}`, если полей нет — ничего
+fn write_json_fields<'a, W: Write>(
+    out: &mut W,
+    name: &str,
+    fields: impl Iterator<Item = (&'a str, &'a str)>,
+) -> io::Result<()> {
+    let mut first = true;
+    for (key, value) in fields {
+        if first {
+            write!(out, ",\"{}\":{{", name)?;
         } else {
-            // Пишем в текущий файл
-            if let Some(ref mut file) = *file_lock {
-                let line = self.format_log_line(level, message);
-                let _ = writeln!(file, "{}", line);
-                let _ = file.flush();
-            }
+            out.write_all(b",")?;
         }
+        first = false;
+        write_json_str(out, key)?;
+        out.write_all(b":")?;
+        write_json_str(out, value)?;
+    }
+    if !first {
+        out.write_all(b"}")?;
+    }
+    Ok(())
+}
+
+// Тело сообщения для системного лога в формате Json: время и PID добавляет сам системный лог
+fn system_log_json(out: &mut Vec<u8>, record: &Record) {
+    let _ = (|| -> io::Result<()> {
+        write!(out, "{{\"level\":\"{}\"", record.level.as_str())?;
+        out.write_all(b",\"target\":")?;
+        write_json_str(out, record.target())?;
+        out.write_all(b",\"message\":")?;
+        write_json_display(out, &record.args)?;
+        write_json_fields(out, "fields", record.fields.iter().copied())?;
+        out.write_all(b"}")
+    })();
+}
+
+// Версия ECS, которой соответствуют имена полей
+const ECS_VERSION: &str = "8.11.0";
+
+// Уровень в ECS принято писать строчными буквами
+fn ecs_level(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::Trace => "trace",
+        LogLevel::Debug => "debug",
+        LogLevel::Info => "info",
+        LogLevel::Warning => "warning",
+        LogLevel::Error => "error",
+        LogLevel::Fatal => "fatal",
+        LogLevel::Off => "off",
+    }
+}
+
+// То же, что system_log_json, но с именами полей ECS
+fn system_log_ecs(out: &mut Vec<u8>, record: &Record) {
+    let _ = (|| -> io::Result<()> {
+        write!(out, "{{\"log.level\":\"{}\"", ecs_level(record.level))?;
+        out.write_all(b",\"log.logger\":")?;
+        write_json_str(out, record.target())?;
+        out.write_all(b",\"message\":")?;
+        write_json_display(out, &record.args)?;
+        write_ecs_trace(out, record.fields)?;
+        write_json_fields(out, "labels", ecs_labels(record.fields))?;
+        write!(out, ",\"ecs.version\":\"{}\"}}", ECS_VERSION)
+    })();
+}
+
+// trace_id и span_id в ECS — поля схемы trace.id и span.id, а не labels
+fn write_ecs_trace<W: Write>(out: &mut W, fields: &[(&str, &str)]) -> io::Result<()> {
+    for (key, value) in fields {
+        let name = match *key {
+            TraceContext::TRACE_FIELD => "trace.id",
+            TraceContext::SPAN_FIELD => "span.id",
+            _ => continue,
+        };
+        write!(out, ",\"{}\":", name)?;
+        write_json_str(out, value)?;
+    }
+    Ok(())
+}
+
+fn ecs_labels<'a>(fields: &'a [(&'a str, &'a str)]) -> impl Iterator<Item = (&'a str, &'a str)> {
+    fields
+        .iter()
+        .copied()
+        .filter(|(key, _)| *key != TraceContext::TRACE_FIELD && *key != TraceContext::SPAN_FIELD)
+}
+
+// ===== Часы =====
+
+/// Источник времени для меток в строках, имён файлов и ограничений частоты.
+/// По умолчанию `SystemClock`; в тестах — `test::ManualClock` (фича `test-util`).
+pub trait Clock: Send + Sync {
+    /// Местное время для меток в строках и именах файлов
+    fn now(&self) -> DateTime<Local>;
+
+    /// Монотонное время от произвольной точки: для `TimestampMode::Elapsed` и интервалов.
+    /// Переводы системных часов на него не влияют.
+    fn monotonic(&self) -> Duration {
+        MONOTONIC_ORIGIN.elapsed()
+    }
+}
+
+static MONOTONIC_ORIGIN: Lazy<Instant> = Lazy::new(Instant::now);
+
+/// Системные часы: `Local::now()`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+// ===== Системные логгеры (платформозависимо) =====
+
+/// Facility для syslog: по ней rsyslog/journald раскладывают сообщения по файлам.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum SyslogFacility {
+    User,
+    Daemon,
+    Local0,
+    Local1,
+    Local2,
+    Local3,
+    Local4,
+    Local5,
+    Local6,
+    Local7,
+}
+
+impl SyslogFacility {
+    #[cfg(unix)]
+    fn to_syslog(self) -> syslog::Facility {
+        use syslog::Facility::*;
+        match self {
+            SyslogFacility::User => LOG_USER,
+            SyslogFacility::Daemon => LOG_DAEMON,
+            SyslogFacility::Local0 => LOG_LOCAL0,
+            SyslogFacility::Local1 => LOG_LOCAL1,
+            SyslogFacility::Local2 => LOG_LOCAL2,
+            SyslogFacility::Local3 => LOG_LOCAL3,
+            SyslogFacility::Local4 => LOG_LOCAL4,
+            SyslogFacility::Local5 => LOG_LOCAL5,
+            SyslogFacility::Local6 => LOG_LOCAL6,
+            SyslogFacility::Local7 => LOG_LOCAL7,
+        }
+    }
+}
+
+/// Формат сообщений syslog
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum SyslogFormat {
+    /// Классический BSD-формат, как у syslog(3); поля записи дописываются к тексту
+    Rfc3164,
+    /// Поля записи и статические поля идут в structured data: `[loglib@32473 env="prod"]`
+    Rfc5424,
+}
+
+// Настройки syslog из построителя; в Windows не используются
+#[derive(Debug, Clone)]
+#[cfg_attr(not(unix), allow(dead_code))]
+struct SyslogOptions {
+    facility: SyslogFacility,
+    ident: Option<String>, // None — имя приложения или исполняемого файла
+    include_pid: bool,
+    format: SyslogFormat,
+}
+
+// SD-ID для полей записи (32473 — номер из примеров RFC 5424)
+#[cfg(unix)]
+const SYSLOG_SD_ID: &str = "loglib@32473";
+
+// Formatter3164 из крейта syslog всегда пишет PID и не умеет structured data, поэтому формат свой
+#[cfg(unix)]
+#[derive(Clone)]
+struct SyslogFormatter {
+    facility: syslog::Facility,
+    ident: String,
+    pid: Option<u32>,
+    format: SyslogFormat,
+    hostname: String,
+    static_fields: Vec<(String, String)>,
+}
+
+#[cfg(unix)]
+impl SyslogFormatter {
+    fn format_message(
+        &self,
+        out: &mut String,
+        severity: syslog::Severity,
+        msg: &str,
+        fields: &[(&str, &str)],
+    ) {
+        use std::fmt::Write as _;
+
+        let priority = self.facility as u8 | severity as u8;
+        match self.format {
+            // "<PRI>Mmm dd hh:mm:ss ident[pid]: msg k=v"
+            SyslogFormat::Rfc3164 => {
+                let stamp = Local::now().format("%b %e %H:%M:%S");
+                let _ = match self.pid {
+                    Some(pid) => write!(
+                        out,
+                        "<{}>{} {}[{}]: {}",
+                        priority, stamp, self.ident, pid, msg
+                    ),
+                    None => write!(out, "<{}>{} {}: {}", priority, stamp, self.ident, msg),
+                };
+                for (key, value) in fields {
+                    let _ = write!(out, " {}={}", key, value);
+                }
+            }
+            // "<PRI>1 TIMESTAMP HOST APP PROCID MSGID [SD] msg"
+            SyslogFormat::Rfc5424 => {
+                let stamp = Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, false);
+                let _ = write!(
+                    out,
+                    "<{}>1 {} {} {} ",
+                    priority, stamp, self.hostname, self.ident
+                );
+                let _ = match self.pid {
+                    Some(pid) => write!(out, "{} - ", pid),
+                    None => write!(out, "- - "),
+                };
+                let statics = self
+                    .static_fields
+                    .iter()
+                    .map(|(k, v)| (k.as_str(), v.as_str()));
+                let mut params = statics.chain(fields.iter().copied()).peekable();
+                if params.peek().is_none() {
+                    out.push('-');
+                } else {
+                    out.push('[');
+                    out.push_str(SYSLOG_SD_ID);
+                    for (key, value) in params {
+                        out.push(' ');
+                        push_sd_name(out, key);
+                        out.push_str("=\"");
+                        push_sd_value(out, value);
+                        out.push('"');
+                    }
+                    out.push(']');
+                }
+                out.push(' ');
+                out.push_str(msg);
+            }
+        }
+    }
+}
+
+// PARAM-NAME: до 32 печатных ASCII-символов, кроме '=', ' ', ']' и '"'
+#[cfg(unix)]
+fn push_sd_name(out: &mut String, name: &str) {
+    for c in name.chars().take(32) {
+        let valid = c.is_ascii_graphic() && !matches!(c, '=' | ']' | '"');
+        out.push(if valid { c } else { '_' });
+    }
+}
+
+// PARAM-VALUE: '"', '\' и ']' экранируются обратной косой чертой
+#[cfg(unix)]
+fn push_sd_value(out: &mut String, value: &str) {
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+}
+
+#[cfg(unix)]
+type SystemLogger = Arc<Mutex<syslog::Logger<syslog::LoggerBackend, SyslogFormatter>>>;
+
+#[cfg(target_os = "windows")]
+type SystemLogger = winlog_rs::WinEventLogger;
+
+#[cfg(unix)]
+fn write_syslog(
+    logger: &SystemLogger,
+    level: LogLevel,
+    msg: &str,
+    fields: &[(&str, &str)],
+) -> bool {
+    match logger.lock() {
+        Ok(mut guard) => {
+            let logger = &mut *guard;
+            with_format_string(|message| {
+                logger
+                    .formatter
+                    .format_message(message, level.to_syslog_level(), msg, fields);
+                // одна запись — одна датаграмма
+                logger.backend.write_all(message.as_bytes()).is_ok()
+            })
+        }
+        Err(_) => false,
+    }
+}
+
+// ===== Статистика =====
+
+/// Снимок внутренних счётчиков логгера (см. `Logger::stats`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LogStats {
+    /// Записанные сообщения по уровням, индекс — `LogLevel as usize`
+    pub records_by_level: [u64; 6],
+    pub bytes_written: u64,
+    pub rotations: u64,
+    /// Сообщения, отброшенные фильтром уровня или режимом деградации
+    pub dropped: u64,
+    pub file_errors: u64,
+    pub system_log_errors: u64,
+    /// Последняя ошибка записи в файл или системный лог
+    pub last_error: Option<String>,
+    /// Закрытые ротацией файлы, которые ещё ждут обслуживания: переноса, сжатия, выгрузки
+    pub maintenance_backlog: u64,
+    /// Записи в памяти, которые ждут недоступного файла (см. `RetryPolicy`)
+    pub buffered_records: u64,
+    /// Сколько записей уходит в файл одним `write()`: число вызовов с 1, 2–15, 16–127,
+    /// 128–1023 и 1024+ записями. Записи склеивает фоновый писатель
+    /// (`LoggerBuilder::background_writer`) или буфер файла (см. `FlushPolicy`)
+    pub write_batches: [u64; 5],
+}
+
+impl LogStats {
+    pub fn records(&self, level: LogLevel) -> u64 {
+        // для Off записей не бывает
+        self.records_by_level
+            .get(level as usize)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn total_records(&self) -> u64 {
+        self.records_by_level.iter().sum()
+    }
+}
+
+/// Состояние логгера для readiness-проб (см. `Logger::health`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogHealth {
+    /// Удалась ли последняя запись в файл; `None` — записей ещё не было
+    pub file_ok: Option<bool>,
+    /// Удалась ли последняя запись в системный лог; `None` — записей ещё не было
+    pub system_log_ok: Option<bool>,
+    /// Записи в буферах файлов, ещё не сброшенные на диск
+    pub pending_records: u64,
+    /// Время с последнего успешного сброса файлов (самого давнего из них).
+    /// Без записей буфер пуст, и большое значение не говорит о проблеме.
+    pub since_last_flush: Option<Duration>,
+}
+
+impl LogHealth {
+    /// Последние записи в файл и в системный лог не завершились ошибкой
+    pub fn is_healthy(&self) -> bool {
+        self.file_ok != Some(false) && self.system_log_ok != Some(false)
+    }
+}
+
+#[derive(Default)]
+struct StatsCounters {
+    records_by_level: [AtomicU64; 6],
+    bytes_written: AtomicU64,
+    rotations: AtomicU64,
+    dropped: AtomicU64,
+    file_errors: AtomicU64,
+    system_log_errors: AtomicU64,
+    last_error: Mutex<Option<String>>,
+    // исход последней записи: OUTCOME_*
+    last_file_write: AtomicU8,
+    last_system_write: AtomicU8,
+    maintenance_backlog: AtomicU64,
+    buffered_records: AtomicU64,
+    write_batches: [AtomicU64; 5],
+}
+
+const OUTCOME_UNKNOWN: u8 = 0;
+const OUTCOME_OK: u8 = 1;
+const OUTCOME_FAILED: u8 = 2;
+
+impl StatsCounters {
+    fn record(&self, level: LogLevel, bytes: usize) {
+        self.records_by_level[level as usize].fetch_add(1, Ordering::Relaxed);
+        self.add_bytes(bytes);
+    }
+
+    fn add_bytes(&self, bytes: usize) {
+        self.bytes_written
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn inc(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn write_batch(&self, records: u64) {
+        let bucket = match records {
+            0..=1 => 0,
+            2..=15 => 1,
+            16..=127 => 2,
+            128..=1023 => 3,
+            _ => 4,
+        };
+        Self::inc(&self.write_batches[bucket]);
+    }
+
+    fn set_last_error(&self, error: String) {
+        *self.last_error.lock().unwrap() = Some(error);
+    }
+
+    fn set_outcome(cell: &AtomicU8, ok: bool) {
+        cell.store(
+            if ok { OUTCOME_OK } else { OUTCOME_FAILED },
+            Ordering::Relaxed,
+        );
+    }
+
+    fn outcome(cell: &AtomicU8) -> Option<bool> {
+        match cell.load(Ordering::Relaxed) {
+            OUTCOME_UNKNOWN => None,
+            outcome => Some(outcome == OUTCOME_OK),
+        }
+    }
+
+    fn snapshot(&self) -> LogStats {
+        let load = |c: &AtomicU64| c.load(Ordering::Relaxed);
+        LogStats {
+            records_by_level: std::array::from_fn(|i| load(&self.records_by_level[i])),
+            bytes_written: load(&self.bytes_written),
+            rotations: load(&self.rotations),
+            dropped: load(&self.dropped),
+            file_errors: load(&self.file_errors),
+            system_log_errors: load(&self.system_log_errors),
+            last_error: self.last_error.lock().unwrap().clone(),
+            maintenance_backlog: load(&self.maintenance_backlog),
+            buffered_records: load(&self.buffered_records),
+            write_batches: std::array::from_fn(|i| load(&self.write_batches[i])),
+        }
+    }
+}
+
+// ===== Кастомный ротирующий писатель =====
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum RotationNaming {
+    /// app.log → app.log.1 → app.log.2 ... (по умолчанию)
+    Numbered,
+    /// Каждый файл получает метку времени создания: app.20250405-143022-123.log
+    Timestamped,
+}
+
+/// Когда ротировать файл
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum RotationPolicy {
+    /// Когда файл дорастёт до `max_size` (по умолчанию)
+    #[default]
+    Size,
+    /// После N записей, независимо от размера. Заголовок ротации не пишется, так что
+    /// в каждом файле ровно N записей. У уже существующего файла считаются его строки.
+    Lines(u64),
+}
+
+/// Что делать с активным файлом, который уже есть на диске при запуске
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum OpenMode {
+    /// Дописывать в конец (по умолчанию)
+    #[default]
+    Append,
+    /// Очистить: каждый запуск начинается с пустого файла, архивы не трогаются
+    Truncate,
+    /// Не запускаться, если файл уже есть: ошибка `AlreadyExists`
+    CreateNew,
+}
+
+/// Что делать, если файл лога не открывается (каталог ненадолго пропал, файл
+/// заблокирован антивирусом). Пока файла нет, записи копятся в памяти; попытки открыть
+/// его делаются при записях с растущей паузой, а при удаче накопленное дописывается.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
+pub struct RetryPolicy {
+    /// Пауза перед первой повторной попыткой, дальше удваивается; в конфигурации — секунды
+    #[cfg_attr(feature = "serde", serde(with = "config::duration_secs"))]
+    pub initial_delay: Duration,
+    /// Наибольшая пауза между попытками
+    #[cfg_attr(feature = "serde", serde(with = "config::duration_secs"))]
+    pub max_delay: Duration,
+    /// Сколько ждать файл при запуске, прежде чем `build()` вернёт ошибку; 0 — не ждать
+    #[cfg_attr(feature = "serde", serde(with = "config::duration_secs"))]
+    pub startup_timeout: Duration,
+    /// Байт записей в памяти; при переполнении вытесняются самые старые. 0 — не копить
+    pub buffer_size: usize,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(30),
+            startup_timeout: Duration::ZERO,
+            buffer_size: 1024 * 1024,
+        }
+    }
+}
+
+/// Роль процесса в режиме `shared_append`: несколько процессов дописывают один файл
+/// без блокировок, а ротирует его только один из них.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum AppendRole {
+    /// Ротирует файл; такой процесс должен быть один
+    Rotator,
+    /// Только дописывает; после ротации переходит на новый файл
+    Writer,
+}
+
+/// Насколько надёжно записи доходят до диска. Политика сброса решает, когда данные
+/// уходят из буфера процесса в ОС; fsync дожидается, пока ОС запишет их на носитель.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Durability {
+    /// Как решит политика сброса (по умолчанию)
+    #[default]
+    None,
+    /// Каждая запись сразу уходит в ОС при любой политике сброса: переживает падение
+    /// процесса, но не сбой питания
+    FlushOnly,
+    /// fsync после каждой записи: запись на диске, когда вызов вернулся. Медленно
+    FsyncEveryRecord,
+    /// fsync не реже раза в интервал (фоновым потоком); теряется не больше интервала
+    FsyncInterval(#[cfg_attr(feature = "serde", serde(with = "config::duration_secs"))] Duration),
+}
+
+impl Durability {
+    fn syncs(self) -> bool {
+        matches!(
+            self,
+            Durability::FsyncEveryRecord | Durability::FsyncInterval(_)
+        )
+    }
+}
+
+/// Когда сбрасывать буфер файла на диск. Несколько политик объединяются:
+/// сброс происходит, если сработала любая из них.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum FlushPolicy {
+    /// После каждой записи (по умолчанию)
+    EveryRecord,
+    /// После каждых N записей
+    EveryN(u32),
+    /// Не реже раза в интервал; если записей нет, буфер сбрасывает фоновый поток
+    Interval(#[cfg_attr(feature = "serde", serde(with = "config::duration_secs"))] Duration),
+    /// Сразу после записи с уровнем не ниже заданного
+    OnLevel(LogLevel),
+}
+
+type LogFile = BufWriter<OutputFile>;
+
+// Активный файл под буфером. С io_uring содержимое буфера уходит в кольцо отправки
+// без ожидания, иначе — обычным write()
+struct OutputFile {
+    file: File,
+    #[cfg(all(feature = "io_uring", target_os = "linux"))]
+    ring: Option<uring::Ring>,
+    pending: u64,  // записи в буфере BufWriter, уйдут следующим write()
+    partial: bool, // прошлый write() записал не всё, следующий — её продолжение
+    stats: Arc<StatsCounters>,
+}
+
+impl OutputFile {
+    fn new(file: File, stats: Arc<StatsCounters>) -> Self {
+        OutputFile {
+            file,
+            #[cfg(all(feature = "io_uring", target_os = "linux"))]
+            ring: None,
+            pending: 0,
+            partial: false,
+            stats,
+        }
+    }
+
+    // Вызывается после flush, когда отправленное кольцом уже записано
+    fn sync_data(&self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Без записей в буфере BufWriter пишет строку мимо него — это пачка из одной
+        if !std::mem::take(&mut self.partial) {
+            self.stats.write_batch(self.pending.max(1));
+            self.pending = 0;
+        }
+        #[cfg(all(feature = "io_uring", target_os = "linux"))]
+        if let Some(ref mut ring) = self.ring {
+            ring.write(buf)?;
+            return Ok(buf.len());
+        }
+        let written = self.file.write(buf)?;
+        self.partial = written < buf.len();
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        #[cfg(all(feature = "io_uring", target_os = "linux"))]
+        if let Some(ref mut ring) = self.ring {
+            ring.drain()?;
+        }
+        self.file.flush()
+    }
+}
+
+// Только для общих файлов, где кольца не бывает
+impl Seek for OutputFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+#[derive(Clone)]
+struct FileConfig {
+    dir: PathBuf,
+    basename: String,
+    max_size: u64,
+    max_files: usize,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    mode: Option<u32>, // права для unix (например, 0o600)
+    #[cfg_attr(not(windows), allow(dead_code))]
+    restrict_acl: bool, // DACL только для SYSTEM/Administrators на Windows
+    multi_process: bool,               // ротация под межпроцессной блокировкой
+    shared_append: Option<AppendRole>, // общий файл без блокировок
+    naming: RotationNaming,
+    rotation: RotationPolicy,
+    rotate_on_open: bool,   // каждый запуск начинает новый файл
+    open_mode: OpenMode,    // что делать с файлом прошлого запуска
+    retry: RetryPolicy,     // если файл не открывается
+    durability: Durability, // fsync поверх политики сброса
+    #[cfg_attr(not(all(feature = "io_uring", target_os = "linux")), allow(dead_code))]
+    io_uring: bool, // запись через io_uring, если ядро позволяет
+    archive_dir: Option<PathBuf>, // куда уходят архивы; относительный путь — от dir
+    on_archive: Vec<ArchiveHook>,
+    archive_command: Option<Arc<ArchiveCommand>>,
+    compress: bool,                        // gzip для архивов
+    maintenance: Option<Arc<Maintenance>>, // None — всё обслуживание в потоке записи
+    current_link: bool,                    // ссылка app.log на активный файл при Timestamped
+    source_location: bool,                 // писать file:line записи
+    thread_format: ThreadFormat,
+    min_level: LogLevel,   // записи ниже уровня в этот файл не пишутся
+    route: Option<String>, // шаблон категорий, которые уходят только сюда
+    filter: Option<RecordFilter>,
+    timestamp: TimestampMode,
+    sequence_numbers: bool,                // писать SEQ:n
+    context: String,                       // хост, процесс и статические поля, вычисляются один раз
+    context_fields: Vec<(String, String)>, // то же парами, для JSON
+    format: LineFormat,
+    min_free_space: Option<u64>, // порог свободного места для режима деградации
+    flush_policies: Vec<FlushPolicy>, // пусто — EveryRecord
+    buffer_capacity: Option<usize>, // None — выбрать по политике сброса
+    background: Option<usize>,   // очередь фонового писателя, записей; None — пишет вызывающий
+    header: Option<String>,      // шаблон заголовка нового файла; None — без заголовка
+    header_on_open: bool,        // заголовок и при запуске, не только после ротации
+    line_ending: LineEnding,
+    bom: bool, // BOM в начале каждого нового файла
+    #[cfg(feature = "encoding")]
+    encoding: Option<String>, // кодовая страница файла; None — UTF-8
+    clock: Arc<dyn Clock>,
+    #[cfg(feature = "test-util")]
+    faults: Option<Arc<test::FaultInjector>>, // сбои для тестов обработки ошибок
+}
+
+impl FileConfig {
+    fn new(dir: PathBuf, basename: String, max_size: u64, max_files: usize) -> Self {
+        FileConfig {
+            dir,
+            basename,
+            max_size,
+            max_files,
+            mode: None,
+            restrict_acl: false,
+            multi_process: false,
+            shared_append: None,
+            naming: RotationNaming::Numbered,
+            rotation: RotationPolicy::Size,
+            rotate_on_open: false,
+            open_mode: OpenMode::Append,
+            retry: RetryPolicy::default(),
+            durability: Durability::None,
+            io_uring: false,
+            archive_dir: None,
+            on_archive: Vec::new(),
+            archive_command: None,
+            compress: false,
+            maintenance: None,
+            current_link: true,
+            source_location: false,
+            thread_format: ThreadFormat::NameOrId,
+            min_level: LogLevel::Trace,
+            route: None,
+            filter: None,
+            timestamp: TimestampMode::WallClock,
+            sequence_numbers: false,
+            context: String::new(),
+            context_fields: Vec::new(),
+            format: LineFormat::Text,
+            min_free_space: None,
+            flush_policies: Vec::new(),
+            buffer_capacity: None,
+            background: None,
+            header: Some(RotatingWriter::DEFAULT_HEADER.to_owned()),
+            header_on_open: false,
+            line_ending: LineEnding::Lf,
+            bom: false,
+            #[cfg(feature = "encoding")]
+            encoding: None,
+            clock: system_clock(),
+            #[cfg(feature = "test-util")]
+            faults: None,
+        }
+    }
+}
+
+// Файл не открылся: записи ждут в памяти, попытки открыть его — с растущей паузой
+struct Outage {
+    lines: VecDeque<Vec<u8>>,
+    bytes: usize,
+    dropped: u64,
+    failures: u32,
+    error: (io::ErrorKind, String), // последняя ошибка открытия
+    delay: Duration,
+    next_attempt: Instant,
+}
+
+impl Outage {
+    fn new(error: &io::Error, policy: &RetryPolicy) -> Self {
+        let now = Instant::now();
+        let mut outage = Outage {
+            lines: VecDeque::new(),
+            bytes: 0,
+            dropped: 0,
+            failures: 0,
+            error: (error.kind(), String::new()),
+            delay: policy.initial_delay,
+            next_attempt: now,
+        };
+        outage.failed(error, now, policy);
+        outage
+    }
+
+    fn failed(&mut self, error: &io::Error, now: Instant, policy: &RetryPolicy) {
+        self.failures += 1;
+        self.error = (error.kind(), error.to_string());
+        self.next_attempt = now + self.delay;
+        self.delay = (self.delay * 2).min(policy.max_delay);
+    }
+}
+
+struct RotatingWriter {
+    dir: PathBuf,
+    archive: Arc<ArchivePlan>, // что делать с файлом после ротации
+    staged: AtomicU64,         // счётчик для имён закрытых файлов
+    basename: String,
+    max_size: u64,
+    config: FileConfig,
+    active_name: Mutex<String>, // имя активного файла (при Numbered всегда basename)
+    file: Arc<Mutex<Option<LogFile>>>,
+    started: Duration, // Clock::monotonic при создании, для TimestampMode::Elapsed
+    sequence: AtomicU64, // номер следующей записи
+    size: AtomicU64,   // размер активного файла вместе с буфером
+    records: AtomicU64, // записей в активном файле, для RotationPolicy::Lines
+    flush_state: Arc<FlushState>,
+    rotation_lock: Option<RotationLock>,
+    rotating: Mutex<()>, // ротация в процессе — по одному потоку; берётся до file
+    queue: Option<Arc<WriteQueue>>, // строки для фонового писателя
+    generation: AtomicU64, // сколько раз открывался активный файл
+    degraded: AtomicBool, // мало места на диске: Trace/Debug отбрасываются
+    outage: Mutex<Option<Outage>>, // файл не открылся; берётся после file
+    last_moved_check: Mutex<Instant>,
+    file_id: Mutex<Option<FileId>>, // какой файл открыт, для AppendRole::Writer
+    #[cfg_attr(not(all(feature = "io_uring", target_os = "linux")), allow(dead_code))]
+    uring_failed: AtomicBool, // io_uring недоступен, пишем обычным write()
+    last_space_check: Mutex<Option<Instant>>,
+    app_info: String,
+    #[cfg(feature = "encoding")]
+    transcoder: Option<encoding::Transcoder>,
+    system_logger: Option<SystemLogger>, // для логов об ошибках
+    stats: Arc<StatsCounters>,
+}
+
+// Длина сообщения в байтах, без промежуточной String
+struct MessageLength(usize);
+
+impl fmt::Write for MessageLength {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+// Первые `keep` байт сообщения (по границе символа) и пометка об обрезке
+struct TruncatedMessage<'a> {
+    args: fmt::Arguments<'a>,
+    keep: usize,
+}
+
+impl fmt::Display for TruncatedMessage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Prefix<'a, 'b> {
+            out: &'a mut fmt::Formatter<'b>,
+            left: usize,
+        }
+        impl fmt::Write for Prefix<'_, '_> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                let mut end = s.len().min(self.left);
+                while !s.is_char_boundary(end) {
+                    end -= 1;
+                }
+                self.left = if end < s.len() { 0 } else { self.left - end };
+                self.out.write_str(&s[..end])
+            }
+        }
+        fmt::write(
+            &mut Prefix {
+                out: f,
+                left: self.keep,
+            },
+            self.args,
+        )?;
+        f.write_str(RotatingWriter::TRUNCATED_MARK)
+    }
+}
+
+impl RotatingWriter {
+    const MIN_SIZE: u64 = 256; // минимальный размер, чтобы вместить заголовок + пару строк
+    const MAX_NUMBERED_FILES: usize = 10_000;
+    const BUFFER_SIZE: usize = 8 * 1024; // буфер, если сброс не после каждой записи
+    const BACKGROUND_BUFFER_SIZE: usize = 256 * 1024;
+    const DEFAULT_HEADER: &'static str = "[ROTATION] Logger restarted — {app}";
+
+    fn new(
+        config: FileConfig,
+        app_info: &str,
+        system_logger: Option<SystemLogger>,
+        stats: Arc<StatsCounters>,
+    ) -> io::Result<Self> {
+        Self::validate(&config)?;
+
+        fs::create_dir_all(&config.dir)?;
+        let archive_dir = match config.archive_dir {
+            Some(ref archive_dir) => {
+                let archive_dir = config.dir.join(archive_dir);
+                fs::create_dir_all(&archive_dir)?;
+                archive_dir
+            }
+            None => config.dir.clone(),
+        };
+
+        // При Timestamped продолжаем писать в самый свежий файл, как и при Numbered
+        let active_name = match config.naming {
+            RotationNaming::Numbered => config.basename.clone(),
+            RotationNaming::Timestamped => list_timestamped_files(&config.dir, &config.basename)
+                .pop()
+                .unwrap_or_else(|| timestamped_file_name(&config.basename, &*config.clock)),
+        };
+        let path = config.dir.join(&active_name);
+
+        let file = Self::open_at_start(&path, &config)?;
+        let size = file.metadata()?.len();
+        let file_id = Some(FileId::of(&file)?);
+        let records = existing_records(&path, config.rotation)?;
+
+        let rotation_lock = if config.multi_process {
+            let lock_path = config.dir.join(format!("{}.lock", config.basename));
+            Some(RotationLock::open(&lock_path)?)
+        } else {
+            None
+        };
+
+        let file = BufWriter::with_capacity(
+            Self::buffer_capacity(&config),
+            OutputFile::new(file, Arc::clone(&stats)),
+        );
+        let file = Arc::new(Mutex::new(Some(file)));
+        let flush_state = Arc::new(FlushState {
+            unflushed: AtomicU32::new(0),
+            last_flush: Mutex::new(Instant::now()),
+        });
+        for policy in &config.flush_policies {
+            if let FlushPolicy::Interval(interval) = *policy {
+                spawn_interval_flusher(&file, &flush_state, interval, false);
+            }
+        }
+        if let Durability::FsyncInterval(interval) = config.durability {
+            spawn_interval_flusher(&file, &flush_state, interval, true);
+        }
+
+        #[cfg(feature = "encoding")]
+        let transcoder = match config.encoding {
+            Some(ref label) => encoding::Transcoder::new(label)?,
+            None => None,
+        };
+        let queue = config.background.map(|capacity| {
+            Arc::new(WriteQueue {
+                batch: Mutex::new(Batch::default()),
+                ready: Condvar::new(),
+                space: Condvar::new(),
+                draining: Mutex::new(()),
+                capacity: capacity.max(1),
+            })
+        });
+
+        let writer = RotatingWriter {
+            dir: config.dir.clone(),
+            archive: Arc::new(ArchivePlan {
+                archive_dir,
+                basename: config.basename.clone(),
+                max_files: config.max_files,
+                naming: config.naming,
+                compress: config.compress,
+                on_archive: config.on_archive.clone(),
+                command: config.archive_command.clone(),
+            }),
+            staged: AtomicU64::new(0),
+            basename: config.basename.clone(),
+            max_size: config.max_size,
+            started: config.clock.monotonic(),
+            config,
+            active_name: Mutex::new(active_name),
+            file,
+            size: AtomicU64::new(size),
+            records: AtomicU64::new(records),
+            sequence: AtomicU64::new(1),
+            flush_state,
+            rotation_lock,
+            rotating: Mutex::new(()),
+            queue,
+            generation: AtomicU64::new(0),
+            degraded: AtomicBool::new(false),
+            outage: Mutex::new(None),
+            last_moved_check: Mutex::new(Instant::now()),
+            file_id: Mutex::new(file_id),
+            uring_failed: AtomicBool::new(false),
+            last_space_check: Mutex::new(None),
+            app_info: app_info.to_owned(),
+            #[cfg(feature = "encoding")]
+            transcoder,
+            system_logger,
+            stats,
+        };
+        // Кольцо создаётся, когда уже есть куда сообщить о его недоступности
+        #[cfg(all(feature = "io_uring", target_os = "linux"))]
+        if writer.config.io_uring {
+            let mut file_lock = writer.file.lock().unwrap();
+            if let Some(file) = file_lock.take() {
+                let file = file.into_inner().map_err(|e| e.into_error())?.file;
+                *file_lock = Some(writer.wrap(file));
+            }
+        }
+        writer.update_current_link();
+        writer.resume_maintenance();
+        if writer.config.rotate_on_open && writer.size.load(Ordering::Relaxed) > 0 {
+            writer.rotate_on_open()?; // заголовок пишет ротация
+        } else if writer.config.header_on_open {
+            if let Some(ref mut file) = *writer.file.lock().unwrap() {
+                writer.write_header(file);
+            }
+        }
+        Ok(writer)
+    }
+
+    // При запуске ждём файл до startup_timeout; CreateNew не повторяется
+    fn open_at_start(path: &Path, config: &FileConfig) -> io::Result<File> {
+        let deadline = Instant::now() + config.retry.startup_timeout;
+        let mut delay = config.retry.initial_delay;
+        loop {
+            match open_log_file(path, config, config.open_mode) {
+                Err(e) if e.kind() != io::ErrorKind::AlreadyExists && Instant::now() < deadline => {
+                    std::thread::sleep(
+                        delay.min(deadline.saturating_duration_since(Instant::now())),
+                    );
+                    delay = (delay * 2).min(config.retry.max_delay);
+                    let _ = fs::create_dir_all(&config.dir);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn validate(config: &FileConfig) -> io::Result<()> {
+        let invalid = |msg: String| {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{}: {}", config.basename, msg),
+            ))
+        };
+        if config.basename.is_empty() {
+            return invalid("file name is empty".to_owned());
+        }
+        if config.max_size < Self::MIN_SIZE {
+            return invalid(format!(
+                "max_size must be at least {} bytes, got {}",
+                Self::MIN_SIZE,
+                config.max_size
+            ));
+        }
+        if config.rotation == RotationPolicy::Lines(0) {
+            return invalid("RotationPolicy::Lines needs at least one record per file".to_owned());
+        }
+        // Numbered на каждой ротации проверяет все номера до max_files
+        if config.naming == RotationNaming::Numbered && config.max_files > Self::MAX_NUMBERED_FILES
+        {
+            return invalid(format!(
+                "max_files {} is too large for Numbered naming (at most {}); use \
+                 RotationNaming::Timestamped or 0 to keep all archives",
+                config.max_files,
+                Self::MAX_NUMBERED_FILES
+            ));
+        }
+        if config.open_mode != OpenMode::Append && config.rotate_on_open {
+            return invalid(format!(
+                "OpenMode::{:?} conflicts with rotate_on_open, which archives the previous file",
+                config.open_mode
+            ));
+        }
+        // Очистка при запуске стёрла бы записи других процессов
+        if config.open_mode == OpenMode::Truncate && config.multi_process {
+            return invalid("OpenMode::Truncate cannot be used with multi_process".to_owned());
+        }
+        // У каждого процесса своё имя активного файла: после чужой ротации остальные
+        // ротировали бы к себе и расходились по разным файлам
+        if config.multi_process && config.naming == RotationNaming::Timestamped {
+            return invalid("multi_process needs RotationNaming::Numbered".to_owned());
+        }
+        if let Some(role) = config.shared_append {
+            if config.multi_process {
+                return invalid("shared_append and multi_process are alternatives".to_owned());
+            }
+            if config.open_mode == OpenMode::Truncate {
+                return invalid("OpenMode::Truncate cannot be used with shared_append".to_owned());
+            }
+            // Writer находит новый файл по имени, а считать чужие записи без блокировки нельзя
+            if config.naming != RotationNaming::Numbered {
+                return invalid("shared_append needs RotationNaming::Numbered".to_owned());
+            }
+            if let RotationPolicy::Lines(_) = config.rotation {
+                return invalid("shared_append rotates by size only".to_owned());
+            }
+            if role == AppendRole::Writer && config.rotate_on_open {
+                return invalid("only AppendRole::Rotator can rotate_on_open".to_owned());
+            }
+            // Общему файлу нужен отдельный write() на каждую запись
+            if config.background.is_some() {
+                return invalid("shared_append cannot be used with background_writer".to_owned());
+            }
+        }
+        // Кольцо пишет по своим смещениям, а общему файлу нужен O_APPEND
+        if config.io_uring && (config.multi_process || config.shared_append.is_some()) {
+            return invalid(
+                "io_uring cannot be used with multi_process or shared_append".to_owned(),
+            );
+        }
+        #[cfg(feature = "encoding")]
+        if let Some(ref label) = config.encoding {
+            if encoding::Transcoder::new(label)?.is_some() && config.bom {
+                return invalid(
+                    "UTF-8 BOM cannot be used with a legacy output encoding".to_owned(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    // Файл прошлого запуска уходит в архив; при ошибке продолжаем писать в него
+    fn rotate_on_open(&self) -> io::Result<()> {
+        let _rotating = self.rotating.lock().unwrap();
+        let mut file_lock = self.file.lock().unwrap();
+        self.sync_before_close(&mut file_lock);
+        *file_lock = None; // закрываем до переименования
+        let _rotation_guard = self.lock_rotation();
+        match self.rotate() {
+            Ok(()) => StatsCounters::inc(&self.stats.rotations),
+            Err(e) => self.log_to_system(
+                LogLevel::Error,
+                &format!("Failed to rotate log on open: {}", e),
+            ),
+        }
+        *file_lock = self.reopen_with_header()?.map(|f| self.wrap(f));
+        Ok(())
+    }
+
+    // Ротация по требованию (Logger::rotate), независимо от размера файла
+    fn rotate_now(&self) -> io::Result<()> {
+        if self.config.shared_append == Some(AppendRole::Writer) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the shared log file is rotated by the AppendRole::Rotator process",
+            ));
+        }
+        // Принятые до ротации записи — в старый файл
+        let _ = self.drain_queue();
+        let _rotating = self.rotating.lock().unwrap();
+        let mut file_lock = self.file.lock().unwrap();
+        if file_lock.is_none() {
+            return Err(Self::not_open());
+        }
+        self.sync_before_close(&mut file_lock);
+        *file_lock = None; // закрываем до переименования
+        let _rotation_guard = self.lock_rotation();
+        let rotated = self.rotate();
+        match rotated {
+            Ok(()) => StatsCounters::inc(&self.stats.rotations),
+            Err(ref e) => {
+                self.log_to_system(LogLevel::Error, &format!("Failed to rotate log: {}", e))
+            }
+        }
+        if let Err(e) = self.switch_file(&mut file_lock, rotated.is_ok()) {
+            // Дальше как при неудачной ротации по размеру: записи копятся до открытия файла
+            self.outage
+                .lock()
+                .unwrap()
+                .get_or_insert_with(|| Outage::new(&e, &self.config.retry));
+            return Err(e);
+        }
+        rotated
+    }
+
+    // Буфер над только что открытым активным файлом и кольцо io_uring, если оно
+    // включено и создаётся. О первой неудаче сообщаем, дальше пишем обычным write()
+    fn wrap(&self, file: File) -> LogFile {
+        let capacity = Self::buffer_capacity(&self.config);
+        #[cfg(all(feature = "io_uring", target_os = "linux"))]
+        if self.config.io_uring && !self.uring_failed.load(Ordering::Relaxed) {
+            match uring::Ring::new(&self.active_path(), &file) {
+                Ok(ring) => {
+                    let mut output = OutputFile::new(file, Arc::clone(&self.stats));
+                    output.ring = Some(ring);
+                    return BufWriter::with_capacity(capacity, output);
+                }
+                Err(e) => {
+                    self.uring_failed.store(true, Ordering::Relaxed);
+                    self.log_to_system(
+                        LogLevel::Warning,
+                        &format!("io_uring is unavailable ({}), using regular writes", e),
+                    );
+                }
+            }
+        }
+        BufWriter::with_capacity(capacity, OutputFile::new(file, Arc::clone(&self.stats)))
+    }
+
+    // Без буфера BufWriter пишет каждую строку сразу в файл
+    fn buffer_capacity(config: &FileConfig) -> usize {
+        if config.shared_append.is_some() {
+            return 0; // каждая запись — отдельный write()
+        }
+        if let Some(capacity) = config.buffer_capacity {
+            return capacity;
+        }
+        if config.background.is_some() {
+            return Self::BACKGROUND_BUFFER_SIZE; // пачка из очереди — одним write()
+        }
+        let every_record = config.flush_policies.is_empty()
+            || config.flush_policies.contains(&FlushPolicy::EveryRecord);
+        if every_record {
+            0
+        } else {
+            Self::BUFFER_SIZE
+        }
+    }
+
+    // records — сколько записей добавилось с прошлой проверки; level — старший их уровень
+    fn should_flush(&self, level: LogLevel, records: u32) -> bool {
+        let pending = self
+            .flush_state
+            .unflushed
+            .fetch_add(records, Ordering::Relaxed)
+            + records;
+        if self.config.flush_policies.is_empty()
+            || matches!(
+                self.config.durability,
+                Durability::FlushOnly | Durability::FsyncEveryRecord
+            )
+        {
+            return true;
+        }
+        self.config
+            .flush_policies
+            .iter()
+            .any(|policy| match *policy {
+                FlushPolicy::EveryRecord => true,
+                FlushPolicy::EveryN(n) => pending >= n.max(1),
+                FlushPolicy::Interval(interval) => {
+                    self.flush_state.last_flush.lock().unwrap().elapsed() >= interval
+                }
+                FlushPolicy::OnLevel(min) => (level as usize) >= (min as usize),
+            })
+    }
+
+    // Строка осталась в буфере и уйдёт в файл вместе со следующими; пустой буфер
+    // значит, что она уже записана сама по себе
+    fn count_buffered(file: &mut LogFile) {
+        if !file.buffer().is_empty() {
+            file.get_mut().pending += 1;
+        }
+    }
+
+    fn flush_file(&self, file: &mut LogFile) -> io::Result<()> {
+        file.flush()?;
+        self.flush_state.flushed();
+        Ok(())
+    }
+
+    // При fsync-надёжности файл, который сейчас переименуют или закроют, целиком на диске
+    fn sync_before_close(&self, file: &mut Option<LogFile>) {
+        if !self.config.durability.syncs() {
+            return;
+        }
+        if let Some(ref mut file) = *file {
+            if let Err(e) = self
+                .flush_file(file)
+                .and_then(|()| file.get_ref().sync_data())
+            {
+                self.log_to_system(
+                    LogLevel::Error,
+                    &format!("Failed to sync log file before closing: {}", e),
+                );
+            }
+        }
+    }
+
+    fn routes(&self, target: &str) -> bool {
+        match self.config.route {
+            Some(ref pattern) => target_matches(pattern, target),
+            None => false,
+        }
+    }
+
+    fn active_path(&self) -> PathBuf {
+        self.dir.join(&*self.active_name.lock().unwrap())
+    }
+
+    // Ok(true) — запись попала в файл. Глобальный уровень проверяет Logger.
+    fn write(&self, record: &Record) -> io::Result<bool> {
+        let level = record.level;
+        if level < self.config.min_level {
+            return Ok(false);
+        }
+        if let Some(ref filter) = self.config.filter {
+            if !filter(record) {
+                return Ok(false);
+            }
+        }
+
+        if self.config.min_free_space.is_some() {
+            self.check_disk_space(false);
+            if self.degraded.load(Ordering::Relaxed) && (level as usize) < (LogLevel::Info as usize)
+            {
+                StatsCounters::inc(&self.stats.dropped);
+                return Ok(false);
+            }
+        }
+
+        if let Some(ref queue) = self.queue {
+            // Ошибки файла фоновый писатель учитывает в статистике
+            return self.enqueue(queue, record).map(|()| true);
+        }
+        self.record_written(self.write_record(Entry::Record(record)))
+            .map(|()| true)
+    }
+
+    fn record_written(&self, written: io::Result<usize>) -> io::Result<()> {
+        match written {
+            Ok(bytes) => {
+                self.stats.add_bytes(bytes);
+                StatsCounters::set_outcome(&self.stats.last_file_write, true);
+                Ok(())
+            }
+            Err(e) => {
+                StatsCounters::inc(&self.stats.file_errors);
+                StatsCounters::set_outcome(&self.stats.last_file_write, false);
+                self.stats
+                    .set_last_error(format!("file write failed: {}", e));
+                Err(e)
+            }
+        }
+    }
+
+    // Строка форматируется здесь же, под блокировкой очереди, — номера записей идут
+    // в файле по порядку. Полная очередь задерживает запись, пока поток её не разберёт
+    fn enqueue(&self, queue: &WriteQueue, record: &Record) -> io::Result<()> {
+        let mut batch = queue.batch.lock().unwrap();
+        while batch.lines.len() >= queue.capacity {
+            batch = queue.space.wait(batch).unwrap();
+        }
+        let start = batch.data.len();
+        if let Err(e) = self.format_line(&mut batch.data, record, self.next_sequence()) {
+            batch.data.truncate(start);
+            return Err(e);
+        }
+        let end = batch.data.len();
+        batch.lines.push((record.level, end));
+        queue.ready.notify_one();
+        Ok(())
+    }
+
+    // Пишет накопленную пачку: строки идут через обычный путь с ротацией, а в файл
+    // уходят из буфера одним write() при сбросе в конце
+    fn drain_queue(&self) -> io::Result<()> {
+        let Some(ref queue) = self.queue else {
+            return Ok(());
+        };
+        let _draining = queue.draining.lock().unwrap();
+        let batch = std::mem::take(&mut *queue.batch.lock().unwrap());
+        queue.space.notify_all();
+        if batch.lines.is_empty() {
+            return Ok(());
+        }
+        let mut result = Ok(());
+        let mut start = 0;
+        let mut top = LogLevel::Trace;
+        for &(level, end) in &batch.lines {
+            let line = &batch.data[start..end];
+            start = end;
+            top = top.max(level);
+            let written = self.record_written(self.write_record(Entry::Line(level, line)));
+            if result.is_ok() {
+                result = written;
+            }
+        }
+        if self.should_flush(top, batch.lines.len() as u32) {
+            if let Some(ref mut file) = *self.file.lock().unwrap() {
+                let synced = self.sync_written(file);
+                if result.is_ok() {
+                    result = self.record_written(synced.map(|()| 0));
+                }
+            }
+        }
+        result
+    }
+
+    fn write_record(&self, entry: Entry) -> io::Result<usize> {
+        let mut file_lock = self.file.lock().unwrap();
+        if file_lock.is_none() {
+            if let Err(e) = self.recover(&mut file_lock, false) {
+                return Err(self.hold(entry, e));
+            }
+        } else if self.config.shared_append == Some(AppendRole::Writer) {
+            // Ротирует другой процесс: следуем за ним, как только по пути новый файл
+            if self.file_replaced() {
+                if let Err(e) = self.switch_file(&mut file_lock, false) {
+                    return Err(self.hold(entry, e));
+                }
+            }
+        } else if self.moved_externally() {
+            self.log_to_system(
+                LogLevel::Warning,
+                &format!(
+                    "Log file {} was moved or deleted, starting a new one",
+                    self.active_path().display()
+                ),
+            );
+            if let Err(e) = self.switch_file(&mut file_lock, true) {
+                return Err(self.hold(entry, e));
+            }
+        }
+
+        // Проверяем размер. В многопроцессном режиме в файл пишут и другие процессы,
+        // поэтому размер берём у самого файла (seek, чтобы не сбрасывать буфер)
+        let need_rotate = match (self.config.rotation, &mut *file_lock) {
+            (_, None) => false,
+            _ if self.config.shared_append == Some(AppendRole::Writer) => false,
+            (RotationPolicy::Lines(max), Some(_)) => {
+                self.records.load(Ordering::Relaxed) >= max.max(1)
+            }
+            (RotationPolicy::Size, Some(file)) if self.shares_file() => {
+                let pos = file.get_mut().seek(SeekFrom::End(0)).unwrap_or(0);
+                pos + file.buffer().len() as u64 >= self.max_size
+            }
+            (RotationPolicy::Size, Some(_)) => self.size.load(Ordering::Relaxed) >= self.max_size,
+        };
+
+        if need_rotate {
+            let generation = self.generation.load(Ordering::Relaxed);
+            self.sync_before_close(&mut file_lock);
+            drop(file_lock); // освобождаем
+
+            if self.config.min_free_space.is_some() {
+                self.check_disk_space(true);
+            }
+
+            // Ротирует один поток; остальные, дождавшись его, пишут в уже новый файл
+            let rotating = self.rotating.lock().unwrap();
+            let mut file_lock = if self.generation.load(Ordering::Relaxed) != generation {
+                drop(rotating);
+                self.file.lock().unwrap()
+            } else {
+                // В многопроцессном режиме ротацию выполняет только один процесс за раз
+                let _rotation_guard = self.lock_rotation();
+                let rotated = self.rotation_still_needed();
+
+                if rotated {
+                    match self.rotate() {
+                        Ok(()) => StatsCounters::inc(&self.stats.rotations),
+                        Err(e) => self.log_to_system(
+                            LogLevel::Error,
+                            &format!("Failed to rotate log: {}", e),
+                        ),
+                    }
+                }
+
+                let mut file_lock = self.file.lock().unwrap();
+                // Если ротацию уже сделал другой процесс — просто переходим на новый активный файл
+                let reopened = if rotated {
+                    self.reopen_with_header()
+                } else {
+                    self.reopen()
+                };
+                *file_lock = match reopened {
+                    Ok(f) => f.map(|f| self.wrap(f)),
+                    Err(e) => {
+                        self.log_to_system(
+                            LogLevel::Error,
+                            &format!(
+                                "Failed to reopen log: {}; keeping records in memory until it opens",
+                                e
+                            ),
+                        );
+                        *file_lock = None;
+                        return Err(self.hold(entry, e));
+                    }
+                };
+                file_lock
+            };
+
+            // Пишем в новый файл
+            match *file_lock {
+                Some(ref mut file) => self.write_buffered(file, entry),
+                None => Err(Self::not_open()),
+            }
+        } else {
+            // Пишем в текущий файл
+            match *file_lock {
+                Some(ref mut file) => self.write_buffered(file, entry),
+                None => Err(Self::not_open()),
+            }
+        }
+    }
+
+    fn write_buffered(&self, file: &mut LogFile, entry: Entry) -> io::Result<usize> {
+        #[cfg(feature = "test-util")]
+        if let Some(ref faults) = self.config.faults {
+            faults.before_write()?;
+        }
+        let bytes = match entry {
+            Entry::Record(record) => self.write_line(file, record, self.next_sequence())?,
+            Entry::Line(_, line) => {
+                file.write_all(line)?;
+                line.len()
+            }
+        };
+        Self::count_buffered(file);
+        self.size.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.records.fetch_add(1, Ordering::Relaxed);
+        // Из очереди сбрасывается вся пачка разом (drain_queue)
+        if self.queue.is_none() && self.should_flush(entry.level(), 1) {
+            self.sync_written(file)?;
+        }
+        Ok(bytes)
+    }
+
+    fn sync_written(&self, file: &mut LogFile) -> io::Result<()> {
+        self.flush_file(file)?;
+        if self.config.durability == Durability::FsyncEveryRecord {
+            file.get_ref().sync_data()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let drained = self.drain_queue();
+        let mut file_lock = self.file.lock().unwrap();
+        if file_lock.is_none() {
+            self.recover(&mut file_lock, false)?;
+        }
+        let flushed = match *file_lock {
+            Some(ref mut file) => self.flush_file(file),
+            None => Err(Self::not_open()),
+        };
+        drained.and(flushed)
+    }
+
+    // Файл недоступен: запись остаётся в памяти до удачной попытки открыть его
+    fn hold(&self, entry: Entry, error: io::Error) -> io::Error {
+        let limit = self.config.retry.buffer_size;
+        let mut outage = self.outage.lock().unwrap();
+        let outage = outage.get_or_insert_with(|| Outage::new(&error, &self.config.retry));
+
+        let mut line = Vec::new();
+        let kept = limit > 0
+            && match entry {
+                Entry::Record(record) => self
+                    .format_line(&mut line, record, self.next_sequence())
+                    .is_ok(),
+                Entry::Line(_, formatted) => {
+                    line.extend_from_slice(formatted);
+                    true
+                }
+            };
+        if kept {
+            outage.bytes += line.len();
+            outage.lines.push_back(line);
+            StatsCounters::inc(&self.stats.buffered_records);
+        } else {
+            outage.dropped += 1;
+            StatsCounters::inc(&self.stats.dropped);
+        }
+        while outage.bytes > limit {
+            let Some(oldest) = outage.lines.pop_front() else {
+                break;
+            };
+            outage.bytes -= oldest.len();
+            outage.dropped += 1;
+            StatsCounters::inc(&self.stats.dropped);
+            self.stats.buffered_records.fetch_sub(1, Ordering::Relaxed);
+        }
+
+        let what = if kept { "kept in memory" } else { "dropped" };
+        io::Error::new(
+            error.kind(),
+            format!("log file is unavailable, record {}: {}", what, error),
+        )
+    }
+
+    // Снова открывает недоступный файл и дописывает накопленные записи. Попытки — не
+    // чаще расписания (force — сейчас же). Ok и без файла, если сбоя не было
+    fn recover(&self, file: &mut Option<LogFile>, force: bool) -> io::Result<()> {
+        let mut guard = self.outage.lock().unwrap();
+        let Some(mut outage) = guard.take() else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        if !force && now < outage.next_attempt {
+            let error = io::Error::new(outage.error.0, outage.error.1.clone());
+            *guard = Some(outage);
+            return Err(error);
+        }
+
+        let _ = fs::create_dir_all(&self.dir);
+        let reopened = match self
+            .reopen_with_header()
+            .and_then(|f| f.ok_or_else(Self::not_open))
+        {
+            Ok(f) => f,
+            Err(e) => {
+                outage.failed(&e, now, &self.config.retry);
+                *guard = Some(outage);
+                return Err(e);
+            }
+        };
+
+        let mut reopened = self.wrap(reopened);
+        let count = outage.lines.len() as u64;
+        let written = outage
+            .lines
+            .iter()
+            .try_for_each(|line| {
+                reopened.write_all(line)?;
+                Self::count_buffered(&mut reopened);
+                Ok(())
+            })
+            .and_then(|()| self.flush_file(&mut reopened));
+        self.stats
+            .buffered_records
+            .fetch_sub(count, Ordering::Relaxed);
+        self.size.fetch_add(outage.bytes as u64, Ordering::Relaxed);
+        self.records.fetch_add(count, Ordering::Relaxed);
+        self.stats.add_bytes(outage.bytes);
+        *file = Some(reopened);
+
+        self.log_to_system(
+            LogLevel::Warning,
+            &format!(
+                "Log file reopened after {} failed attempts: {} records written from memory, {} dropped",
+                outage.failures, count, outage.dropped
+            ),
+        );
+        written
+    }
+
+    // Сбрасывает и закрывает файл; дальнейшие записи вернут ошибку "log file is not open"
+    fn shutdown(&self, timeout: Duration) -> io::Result<()> {
+        let deadline = Instant::now() + timeout;
+        let _ = self.drain_queue(); // ошибки уже в статистике
+        let mut file_lock = loop {
+            match self.file.try_lock() {
+                Ok(guard) => break guard,
+                Err(std::sync::TryLockError::Poisoned(e)) => break e.into_inner(),
+                Err(std::sync::TryLockError::WouldBlock) => {
+                    if Instant::now() >= deadline {
+                        return Err(io::Error::new(
+                            io::ErrorKind::TimedOut,
+                            "timed out waiting for the log writer to finish",
+                        ));
+                    }
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            }
+        };
+        // Последняя попытка дописать то, что ждало недоступного файла
+        if file_lock.is_none() {
+            if let Err(e) = self.recover(&mut file_lock, true) {
+                let lost = self.outage.lock().unwrap().take();
+                let count = lost.map_or(0, |outage| outage.lines.len() as u64);
+                self.stats
+                    .buffered_records
+                    .fetch_sub(count, Ordering::Relaxed);
+                self.stats.dropped.fetch_add(count, Ordering::Relaxed);
+                self.log_to_system(
+                    LogLevel::Error,
+                    &format!(
+                        "Log file is still unavailable at shutdown, {} records kept in memory are lost: {}",
+                        count, e
+                    ),
+                );
+                return Err(e);
+            }
+        }
+        match file_lock.take() {
+            Some(mut file) if self.config.durability.syncs() => {
+                file.flush()?;
+                file.get_ref().sync_data()
+            }
+            Some(mut file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+
+    const MOVED_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+    // В файл пишут и другие процессы
+    fn shares_file(&self) -> bool {
+        self.config.multi_process || self.config.shared_append.is_some()
+    }
+
+    // Закрывает текущий файл и открывает активный заново
+    fn switch_file(&self, file: &mut Option<LogFile>, header: bool) -> io::Result<()> {
+        let reopened = if header {
+            self.reopen_with_header()
+        } else {
+            self.reopen()
+        };
+        match reopened {
+            Ok(f) => {
+                *file = f.map(|f| self.wrap(f));
+                Ok(())
+            }
+            Err(e) => {
+                *file = None;
+                Err(e)
+            }
+        }
+    }
+
+    // По пути уже другой файл (или его нет): Rotator переименовал наш. Проверяется перед
+    // каждой записью, чтобы записи не уходили в архив
+    fn file_replaced(&self) -> bool {
+        match FileId::at(&self.active_path()) {
+            Ok(id) => self.file_id.lock().unwrap().as_ref() != Some(&id),
+            Err(e) => e.kind() == io::ErrorKind::NotFound,
+        }
+    }
+
+    // Активный файл забрали снаружи (переместили или удалили, пока служба пишет) — тогда
+    // продолжаем в новом файле с тем же именем. Проверка не чаще раза в секунду
+    fn moved_externally(&self) -> bool {
+        {
+            let mut last = self.last_moved_check.lock().unwrap();
+            if last.elapsed() < Self::MOVED_CHECK_INTERVAL {
+                return false;
+            }
+            *last = Instant::now();
+        }
+        matches!(fs::symlink_metadata(self.active_path()), Err(e) if e.kind() == io::ErrorKind::NotFound)
+    }
+
+    fn not_open() -> io::Error {
+        io::Error::new(io::ErrorKind::NotConnected, "log file is not open")
+    }
+
+    const SPACE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+    // Проверка свободного места не чаще раза в секунду (перед ротацией — всегда).
+    // Ниже порога: удаляем старые архивы и переходим в режим деградации.
+    fn check_disk_space(&self, force: bool) {
+        let threshold = match self.config.min_free_space {
+            Some(t) => t,
+            None => return,
+        };
+
+        {
+            let mut last = self.last_space_check.lock().unwrap();
+            if !force && last.is_some_and(|t| t.elapsed() < Self::SPACE_CHECK_INTERVAL) {
+                return;
+            }
+            *last = Some(Instant::now());
+        }
+
+        let mut free = match free_disk_space(&self.dir) {
+            Ok(free) => free,
+            Err(_) => return,
+        };
+
+        if free >= threshold {
+            if self.degraded.swap(false, Ordering::Relaxed) {
+                self.log_to_system(
+                    LogLevel::Info,
+                    &format!(
+                        "Disk space on log volume recovered ({} bytes free), leaving degrade mode",
+                        free
+                    ),
+                );
+            }
+            return;
+        }
+
+        let entering = !self.degraded.swap(true, Ordering::Relaxed);
+
+        // Освобождаем место за счёт самых старых архивов, если они на этом же томе
+        let archives = if self.archive.archive_dir == self.dir {
+            self.archive_files()
+        } else {
+            Vec::new()
+        };
+        for archive in archives {
+            if free >= threshold {
+                break;
+            }
+            if fs::remove_file(&archive).is_ok() {
+                free = free_disk_space(&self.dir).unwrap_or(free);
+            }
+        }
+
+        if entering {
+            self.log_to_system(
+                LogLevel::Warning,
+                &format!(
+                    "Low disk space on log volume: {} bytes free (threshold {}), dropping Trace/Debug records",
+                    free, threshold
+                ),
+            );
+        }
+    }
+
+    // Архивы (без активного файла), от самых старых к новым
+    fn archive_files(&self) -> Vec<PathBuf> {
+        match self.config.naming {
+            RotationNaming::Numbered => (1..=self.archive.last_index())
+                .rev()
+                .flat_map(|i| {
+                    let path = self.archive.numbered(i);
+                    let compressed = compressed_path(&path);
+                    [path, compressed]
+                })
+                .filter(|path| path.exists())
+                .collect(),
+            RotationNaming::Timestamped => {
+                let active = self.active_path();
+                let archive_dir = &self.archive.archive_dir;
+                list_archived_files(archive_dir, &self.basename)
+                    .into_iter()
+                    .map(|name| archive_dir.join(name))
+                    .filter(|path| *path != active)
+                    .collect()
+            }
+        }
+    }
+
+    // Строка собирается в буфере и уходит одним write(): с O_APPEND записи разных
+    // процессов не перемешиваются
+    fn write_line<W: Write>(
+        &self,
+        file: &mut W,
+        record: &Record,
+        seq: Option<u64>,
+    ) -> io::Result<usize> {
+        with_format_buffer(|line| {
+            self.format_line(line, record, seq)?;
+            file.write_all(line)?;
+            Ok(line.len())
+        })
+    }
+
+    // Готовая к записи строка: с номером, кодовой страницей файла и концом строки
+    fn format_line(&self, line: &mut Vec<u8>, record: &Record, seq: Option<u64>) -> io::Result<()> {
+        self.format_log_line(&mut *line, record, seq, true)?;
+        let ending = self.config.line_ending.as_bytes();
+        if self.config.shared_append.is_some()
+            && line.len() + ending.len() > Self::ATOMIC_WRITE_LIMIT
+        {
+            self.shorten_line(line, record, seq)?;
+        }
+        self.finish_line(line);
+        Ok(())
+    }
+
+    // Кодовая страница файла (фича encoding) и конец строки
+    fn finish_line(&self, line: &mut Vec<u8>) {
+        #[cfg(feature = "encoding")]
+        if let Some(ref transcoder) = self.transcoder {
+            transcoder.transcode(line);
+        }
+        line.extend_from_slice(self.config.line_ending.as_bytes());
+    }
+
+    // Сколько ядро гарантированно дописывает одним куском (PIPE_BUF)
+    const ATOMIC_WRITE_LIMIT: usize = 4096;
+    const TRUNCATED_MARK: &'static str = "... [truncated]";
+
+    // В shared_append длинное сообщение укорачивается, чтобы строка с переводом строки
+    // уложилась в ATOMIC_WRITE_LIMIT и ушла одним write(); номер записи остаётся прежним
+    fn shorten_line(
+        &self,
+        line: &mut Vec<u8>,
+        record: &Record,
+        seq: Option<u64>,
+    ) -> io::Result<()> {
+        let limit = Self::ATOMIC_WRITE_LIMIT - self.config.line_ending.as_bytes().len();
+        let mut length = MessageLength(0);
+        let _ = fmt::write(&mut length, record.args);
+        // Сначала укорачиваем сообщение; если поля и контекст сами по себе длиннее предела,
+        // отбрасываем их целиком (обрезанный JSON не разобрать) и укорачиваем снова
+        for fields in [true, false] {
+            if line.len() <= limit {
+                break;
+            }
+            if !fields {
+                line.clear();
+                self.format_log_line(&mut *line, record, seq, false)?;
+            }
+            let mut keep = length.0;
+            while line.len() > limit && keep > 0 {
+                let excess = line.len() - limit + Self::TRUNCATED_MARK.len();
+                keep = keep.saturating_sub(excess);
+                line.clear();
+                let message = TruncatedMessage {
+                    args: record.args,
+                    keep,
+                };
+                let args = format_args!("{}", message);
+                self.format_log_line(&mut *line, &Record { args, ..*record }, seq, fields)?;
+            }
+        }
+        // Остальное режем по границе символа, чтобы строка осталась UTF-8
+        if line.len() > limit {
+            let mut end = limit;
+            while end > 0 && line[end] & 0xC0 == 0x80 {
+                end -= 1;
+            }
+            line.truncate(end);
+        }
+        Ok(())
+    }
+
+    fn lock_rotation(&self) -> Option<RotationLockGuard<'_>> {
+        let lock = self.rotation_lock.as_ref()?;
+        match lock.acquire() {
+            Ok(guard) => Some(guard),
+            Err(e) => {
+                self.log_to_system(
+                    LogLevel::Error,
+                    &format!("Failed to acquire rotation lock: {}", e),
+                );
+                None
+            }
+        }
+    }
+
+    // Под блокировкой перепроверяем размер по пути: другой процесс мог уже сдвинуть файлы
+    fn rotation_still_needed(&self) -> bool {
+        if self.rotation_lock.is_none() {
+            return true;
+        }
+        match self.config.rotation {
+            RotationPolicy::Size => fs::metadata(self.active_path())
+                .map(|m| m.len() >= self.max_size)
+                .unwrap_or(false),
+            RotationPolicy::Lines(max) => {
+                existing_records(&self.active_path(), self.config.rotation)
+                    .is_ok_and(|records| records >= max.max(1))
+            }
+        }
+    }
+
+    // Номер следующей записи (SEQ:n). Берётся под блокировкой файла, поэтому идёт в файле
+    // по порядку, и один раз на запись, даже если строку приходится форматировать заново
+    fn next_sequence(&self) -> Option<u64> {
+        self.config
+            .sequence_numbers
+            .then(|| self.sequence.fetch_add(1, Ordering::Relaxed))
+    }
+
+    // fields = false — без контекста и полей записи (строка не уложилась в предел)
+    fn format_log_line<W: Write>(
+        &self,
+        out: &mut W,
+        record: &Record,
+        seq: Option<u64>,
+        fields: bool,
+    ) -> io::Result<()> {
+        match self.config.format {
+            LineFormat::Text => self.format_text_line(out, record, seq, fields),
+            LineFormat::Json => self.format_json_line(out, record, seq, fields),
+            LineFormat::Ecs => self.format_ecs_line(out, record, seq, fields),
+            LineFormat::Message => write!(out, "{}", record.args),
+        }
+    }
+
+    fn format_json_line<W: Write>(
+        &self,
+        out: &mut W,
+        record: &Record,
+        seq: Option<u64>,
+        fields: bool,
+    ) -> io::Result<()> {
+        out.write_all(b"{")?;
+        let mut comma = "";
+        if self.config.timestamp != TimestampMode::Elapsed {
+            let now = self.now(record);
+            write!(
+                out,
+                "\"time\":\"{}\"",
+                now.to_rfc3339_opts(chrono::SecondsFormat::Millis, false)
+            )?;
+            comma = ",";
+        }
+        if self.config.timestamp != TimestampMode::WallClock {
+            write!(
+                out,
+                "{}\"elapsed\":{:.3}",
+                comma,
+                self.elapsed().as_secs_f64()
+            )?;
+        }
+        write!(out, ",\"level\":\"{}\"", record.level.as_str())?;
+        if let Some(seq) = seq {
+            write!(out, ",\"seq\":{}", seq)?;
+        }
+        write!(out, ",\"pid\":{}", process::id())?;
+        write_json_thread_field(out, self.config.thread_format)?;
+        out.write_all(b",\"target\":")?;
+        write_json_str(out, record.target())?;
+        if self.config.source_location {
+            if let Some(location) = record.location {
+                out.write_all(b",\"file\":")?;
+                write_json_str(out, location.file)?;
+                write!(out, ",\"line\":{}", location.line)?;
+            }
+        }
+        out.write_all(b",\"message\":")?;
+        write_json_display(out, &record.args)?;
+        if fields {
+            let context = self
+                .config
+                .context_fields
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()));
+            write_json_fields(out, "fields", context.chain(record.fields.iter().copied()))?;
+        }
+        out.write_all(b"}")
+    }
+
+    // Время всегда в UTC: @timestamp обязателен в ECS, прошедшее время в схеме не предусмотрено
+    fn format_ecs_line<W: Write>(
+        &self,
+        out: &mut W,
+        record: &Record,
+        seq: Option<u64>,
+        fields: bool,
+    ) -> io::Result<()> {
+        let now = self.now(record).with_timezone(&chrono::Utc);
+        write!(
+            out,
+            "{{\"@timestamp\":\"{}\",\"log.level\":\"{}\"",
+            now.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            ecs_level(record.level)
+        )?;
+        out.write_all(b",\"message\":")?;
+        write_json_display(out, &record.args)?;
+        write!(out, ",\"ecs.version\":\"{}\"", ECS_VERSION)?;
+        if let Some(seq) = seq {
+            write!(out, ",\"event.sequence\":{}", seq)?;
+        }
+        write!(out, ",\"process.pid\":{}", process::id())?;
+        THREAD_TAG.with(|tag| match (self.config.thread_format, &tag.name) {
+            (ThreadFormat::NameOrId, Some(name)) => {
+                out.write_all(b",\"process.thread.name\":")?;
+                write_json_str(out, name)
+            }
+            (ThreadFormat::NameOrId, None) | (ThreadFormat::Id, _) => {
+                write!(out, ",\"process.thread.id\":{}", tag.id)
+            }
+            (ThreadFormat::Off, _) => Ok(()),
+        })?;
+        out.write_all(b",\"log.logger\":")?;
+        write_json_str(out, record.target())?;
+        if self.config.source_location {
+            if let Some(location) = record.location {
+                out.write_all(b",\"log.origin.file.name\":")?;
+                write_json_str(out, location.file)?;
+                write!(out, ",\"log.origin.file.line\":{}", location.line)?;
+            }
+        }
+        if !fields {
+            return out.write_all(b"}");
+        }
+        write_ecs_trace(out, record.fields)?;
+        // Хост и имя процесса — поля схемы, остальной контекст и поля записи — labels
+        let mut labels = Vec::new();
+        for (key, value) in &self.config.context_fields {
+            match key.as_str() {
+                "host" => {
+                    out.write_all(b",\"host.name\":")?;
+                    write_json_str(out, value)?;
+                }
+                "process" => {
+                    out.write_all(b",\"process.name\":")?;
+                    write_json_str(out, value)?;
+                }
+                _ => labels.push((key.as_str(), value.as_str())),
+            }
+        }
+        write_json_fields(
+            out,
+            "labels",
+            labels.into_iter().chain(ecs_labels(record.fields)),
+        )?;
+        out.write_all(b"}")
+    }
+
+    // Время записи; у служебных строк писателя его нет
+    fn now(&self, record: &Record) -> DateTime<Local> {
+        record.time.unwrap_or_else(|| self.config.clock.now())
+    }
+
+    fn format_text_line<W: Write>(
+        &self,
+        out: &mut W,
+        record: &Record,
+        seq: Option<u64>,
+        fields: bool,
+    ) -> io::Result<()> {
+        let now = self.now(record);
+        let pid = process::id();
+        match self.config.timestamp {
+            TimestampMode::WallClock => write!(out, "[{}]", now.format("%Y-%m-%d %H:%M:%S%.3f"))?,
+            TimestampMode::Elapsed => write_elapsed(out, self.elapsed())?,
+            TimestampMode::Both => {
+                write!(out, "[{}] ", now.format("%Y-%m-%d %H:%M:%S%.3f"))?;
+                write_elapsed(out, self.elapsed())?;
+            }
+        }
+        write!(out, " {}", record.level.as_str())?;
+        if let Some(seq) = seq {
+            write!(out, " SEQ:{}", seq)?;
+        }
+        write!(out, " PID:{}", pid)?;
+        write_thread_field(out, self.config.thread_format)?;
+        if fields {
+            out.write_all(self.config.context.as_bytes())?;
+            for (key, value) in record.fields {
+                write!(out, " {}={}", key, value)?;
+            }
+        }
+        out.write_all(b" ")?;
+        if self.config.source_location {
+            if let Some(location) = record.location {
+                write!(out, "{}:{} ", location.file, location.line)?;
+            }
+        }
+        write!(out, "{}", record.args)
+    }
+
+    fn reopen(&self) -> io::Result<Option<File>> {
+        #[cfg(feature = "test-util")]
+        if let Some(ref faults) = self.config.faults {
+            faults.before_open()?;
+        }
+        let path = self.active_path();
+        let file = open_log_file(&path, &self.config, OpenMode::Append)?;
+        let metadata = file.metadata()?;
+        self.generation.fetch_add(1, Ordering::Relaxed);
+        self.size.store(metadata.len(), Ordering::Relaxed);
+        *self.file_id.lock().unwrap() = Some(FileId::of(&file)?);
+        self.records.store(
+            existing_records(&path, self.config.rotation)?,
+            Ordering::Relaxed,
+        );
+        self.update_current_link();
+        Ok(Some(file))
+    }
+
+    // app.log → app.<метка>.log, чтобы `tail -F app.log` работал при любой схеме имён
+    fn update_current_link(&self) {
+        if self.config.naming != RotationNaming::Timestamped || !self.config.current_link {
+            return;
+        }
+        let active = self.active_name.lock().unwrap().clone();
+        if let Err(e) = point_current_link(&self.dir, &self.basename, &active) {
+            self.log_to_system(
+                LogLevel::Error,
+                &format!("Failed to update current log link: {}", e),
+            );
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.config.clock.monotonic().saturating_sub(self.started)
+    }
+
+    fn reopen_with_header(&self) -> io::Result<Option<File>> {
+        let mut file = self.reopen()?;
+        if let Some(ref mut f) = file {
+            self.write_header(f);
+        }
+        Ok(file)
+    }
+
+    // Заголовок по шаблону: {app} — приложение и сборка, {file} — имя файла. Пишется
+    // мимо уровней и фильтров файла, уровнем Info
+    fn write_header<W: Write>(&self, file: &mut W) {
+        let Some(ref template) = self.config.header else {
+            return;
+        };
+        if let RotationPolicy::Lines(_) = self.config.rotation {
+            return; // заголовок нарушил бы число записей в файле
+        }
+        let name = self.active_name.lock().unwrap().clone();
+        let text = template
+            .replace("{app}", &self.app_info)
+            .replace("{file}", &name);
+        // Запрос, во время которого случилась ротация, — чтобы связать её с ним
+        let correlation = CorrelationId::current();
+        let fields = correlation
+            .as_ref()
+            .map(|id| [(CorrelationId::FIELD, id.as_str())]);
+        let header = format_args!("{}", text);
+        let header = Record::new(LogLevel::Info, header)
+            .with_fields(fields.as_ref().map_or(&[][..], |fields| &fields[..]));
+        // Записям в очереди номера уже выданы: заголовок между ними идёт без номера
+        let seq = match self.queue {
+            Some(_) => None,
+            None => self.next_sequence(),
+        };
+        if let Ok(bytes) = self.write_line(file, &header, seq) {
+            self.size.fetch_add(bytes as u64, Ordering::Relaxed);
+            self.stats
+                .bytes_written
+                .fetch_add(bytes as u64, Ordering::Relaxed);
+        }
+    }
+
+    // В потоке записи только закрываем активный файл под другим именем; сдвиг номеров,
+    // сжатие, выгрузку и удаление лишних делает поток обслуживания. Numbered в
+    // многопроцессном режиме ротируется целиком здесь, под межпроцессной блокировкой.
+    fn rotate(&self) -> io::Result<()> {
+        #[cfg(feature = "test-util")]
+        if let Some(ref faults) = self.config.faults {
+            faults.before_rename()?;
+        }
+        let closed = match self.config.naming {
+            RotationNaming::Numbered => self.dir.join(&self.basename),
+            RotationNaming::Timestamped => {
+                let next = timestamped_file_name(&self.basename, &*self.config.clock);
+                let mut active = self.active_name.lock().unwrap();
+                if *active == next {
+                    // Та же миллисекунда — новый файл совпал бы со старым
+                    return Ok(());
+                }
+                let previous = std::mem::replace(&mut *active, next);
+                self.dir.join(previous)
+            }
+        };
+        if !closed.exists() {
+            return Ok(());
+        }
+
+        match self.maintenance() {
+            Some(maintenance) => {
+                let closed = match self.config.naming {
+                    RotationNaming::Numbered => {
+                        let staged = self.staging_path();
+                        fs::rename(&closed, &staged)?;
+                        staged
+                    }
+                    RotationNaming::Timestamped => closed,
+                };
+                maintenance.submit(Arc::clone(&self.archive), closed);
+            }
+            None => {
+                let stored = self.archive.store(&closed)?;
+                self.archive
+                    .archived(&stored, &self.system_logger, &self.stats);
+            }
+        }
+        Ok(())
+    }
+
+    fn maintenance(&self) -> Option<&Maintenance> {
+        let inline = self.rotation_lock.is_some() && self.config.naming == RotationNaming::Numbered;
+        self.config.maintenance.as_deref().filter(|_| !inline)
+    }
+
+    // app.log.rotating.20250405-143022-123-000001: закрытый файл, ждущий обслуживания
+    fn staging_path(&self) -> PathBuf {
+        let seq = self.staged.fetch_add(1, Ordering::Relaxed);
+        let stamp = Local::now().format(TIMESTAMP_NAME_FORMAT);
+        self.dir.join(format!(
+            "{}{}{}-{:06}",
+            self.basename, STAGING_INFIX, stamp, seq
+        ))
+    }
+
+    // Файлы, закрытые ротацией, но не обслуженные до завершения прошлого запуска
+    fn resume_maintenance(&self) {
+        let Some(maintenance) = self.maintenance() else {
+            return;
+        };
+        let prefix = format!("{}{}", self.basename, STAGING_INFIX);
+        let mut left: Vec<PathBuf> = match self.config.naming {
+            RotationNaming::Numbered => fs::read_dir(&self.dir)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .filter(|entry| {
+                    entry
+                        .file_name()
+                        .to_str()
+                        .is_some_and(|name| name.starts_with(&prefix))
+                })
+                .map(|entry| entry.path())
+                .collect(),
+            RotationNaming::Timestamped if self.archive.archive_dir != self.dir => {
+                let active = self.active_path();
+                list_timestamped_files(&self.dir, &self.basename)
+                    .into_iter()
+                    .map(|name| self.dir.join(name))
+                    .filter(|path| *path != active)
+                    .collect()
+            }
+            RotationNaming::Timestamped => Vec::new(),
+        };
+        left.sort();
+        for closed in left {
+            maintenance.submit(Arc::clone(&self.archive), closed);
+        }
+    }
+
+    fn log_to_system(&self, level: LogLevel, msg: &str) {
+        Self::report(&self.system_logger, &self.stats, level, msg);
+    }
+
+    // То же для фоновых потоков, у которых нет ссылки на писателя
+    fn report(
+        system_logger: &Option<SystemLogger>,
+        stats: &StatsCounters,
+        level: LogLevel,
+        msg: &str,
+    ) {
+        if let Some(ref logger) = *system_logger {
+            let ok = Self::log_to_system_impl(logger, level, msg);
+            StatsCounters::set_outcome(&stats.last_system_write, ok);
+            if !ok {
+                StatsCounters::inc(&stats.system_log_errors);
+                stats.set_last_error("system log write failed".to_owned());
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    fn log_to_system_impl(logger: &SystemLogger, level: LogLevel, msg: &str) -> bool {
+        write_syslog(logger, level, msg, &[])
+    }
+
+    #[cfg(target_os = "windows")]
+    fn log_to_system_impl(logger: &SystemLogger, level: LogLevel, msg: &str) -> bool {
+        logger
+            .try_report_result(level.to_winlog_level(), msg)
+            .is_ok()
+    }
+}
+
+// Запись для файла: ещё не отформатированная или готовая строка из очереди
+#[derive(Clone, Copy)]
+enum Entry<'a> {
+    Record(&'a Record<'a>),
+    Line(LogLevel, &'a [u8]),
+}
+
+impl Entry<'_> {
+    fn level(&self) -> LogLevel {
+        match *self {
+            Entry::Record(record) => record.level,
+            Entry::Line(level, _) => level,
+        }
+    }
+}
+
+// Очередь фонового писателя (LoggerBuilder::background_writer)
+struct WriteQueue {
+    batch: Mutex<Batch>,
+    ready: Condvar,      // в очереди появились строки
+    space: Condvar,      // поток забрал пачку
+    draining: Mutex<()>, // пачки пишутся по одной, по порядку
+    capacity: usize,     // записей
+}
+
+// Строки подряд в одном буфере; у каждой — уровень и конец в data
+#[derive(Default)]
+struct Batch {
+    data: Vec<u8>,
+    lines: Vec<(LogLevel, usize)>,
+}
+
+// Разбирает очередь писателя; завершается вместе с ним (остаток пишет Drop)
+fn spawn_background_writer(writer: &Arc<RotatingWriter>) {
+    let Some(ref queue) = writer.queue else {
+        return;
+    };
+    let queue = Arc::clone(queue);
+    let writer = Arc::downgrade(writer);
+    let _ = std::thread::Builder::new()
+        .name("loglib-writer".to_owned())
+        .spawn(move || loop {
+            let batch = queue.batch.lock().unwrap();
+            let (batch, _) = queue
+                .ready
+                .wait_timeout_while(batch, Duration::from_millis(100), |batch| {
+                    batch.lines.is_empty()
+                })
+                .unwrap();
+            drop(batch);
+            match writer.upgrade() {
+                Some(writer) => {
+                    let _ = writer.drain_queue(); // ошибки — в статистике
+                }
+                None => break,
+            }
+        });
+}
+
+impl Drop for RotatingWriter {
+    fn drop(&mut self) {
+        let _ = self.drain_queue();
+    }
+}
+
+// Учёт сбросов писателя; общий с фоновым потоком сброса
+struct FlushState {
+    unflushed: AtomicU32, // записей с последнего сброса
+    last_flush: Mutex<Instant>,
+}
+
+impl FlushState {
+    fn flushed(&self) {
+        self.unflushed.store(0, Ordering::Relaxed);
+        *self.last_flush.lock().unwrap() = Instant::now();
+    }
+}
+
+// Сбрасывает буфер раз в интервал, даже если новых записей нет (sync — и делает fsync);
+// завершается вместе с писателем
+fn spawn_interval_flusher(
+    file: &Arc<Mutex<Option<LogFile>>>,
+    state: &Arc<FlushState>,
+    interval: Duration,
+    sync: bool,
+) {
+    let file = Arc::downgrade(file);
+    let state = Arc::clone(state);
+    let _ = std::thread::Builder::new()
+        .name("loglib-flush".to_owned())
+        .spawn(move || loop {
+            std::thread::sleep(interval);
+            match file.upgrade() {
+                Some(file) => {
+                    if let Some(ref mut f) = *file.lock().unwrap() {
+                        if f.flush().is_ok() {
+                            state.flushed();
+                            if sync {
+                                let _ = f.get_ref().sync_data();
+                            }
+                        }
+                    }
+                }
+                None => break,
+            }
+        });
+}
+
+// ===== Heartbeat =====
+
+// Раз в интервал пишет строку со статистикой логгера; завершается вместе с логгером
+fn spawn_heartbeat(
+    stats: &Arc<StatsCounters>,
+    writer: Option<&Arc<RotatingWriter>>,
+    system_logger: Option<SystemLogger>,
+    interval: Duration,
+) {
+    let stats = Arc::downgrade(stats);
+    let writer = writer.map(Arc::downgrade);
+    let _ = std::thread::Builder::new()
+        .name("loglib-heartbeat".to_owned())
+        .spawn(move || loop {
+            std::thread::sleep(interval);
+            let Some(stats) = stats.upgrade() else {
+                break;
+            };
+            let writer = writer.as_ref().and_then(Weak::upgrade);
+            let file_size = writer.as_ref().map(|w| w.size.load(Ordering::Relaxed));
+            let message = heartbeat_message(&stats.snapshot(), file_size);
+            if let Some(writer) = writer {
+                let _ = writer.write(
+                    &Record::new(LogLevel::Info, format_args!("{}", message))
+                        .with_target("loglib::heartbeat"),
+                );
+            }
+            if let Some(ref logger) = system_logger {
+                let ok = write_system_log(logger, LogLevel::Info, &message);
+                StatsCounters::set_outcome(&stats.last_system_write, ok);
+                if !ok {
+                    StatsCounters::inc(&stats.system_log_errors);
+                }
+            }
+        });
+}
+
+fn heartbeat_message(stats: &LogStats, file_size: Option<u64>) -> String {
+    format!("HEARTBEAT {}", stats_summary(stats, file_size))
+}
+
+// Статистика одной строкой key=value: для heartbeat и команды stats канала управления
+fn stats_summary(stats: &LogStats, file_size: Option<u64>) -> String {
+    let mut message = format!(
+        "records={} dropped={} bytes={} rotations={} file_errors={} system_log_errors={}",
+        stats.total_records(),
+        stats.dropped,
+        stats.bytes_written,
+        stats.rotations,
+        stats.file_errors,
+        stats.system_log_errors
+    );
+    if let Some(size) = file_size {
+        message.push_str(&format!(" file_size={}", size));
+    }
+    match stats.last_error {
+        Some(ref error) => message.push_str(&format!(" last_error=\"{}\"", error)),
+        None => message.push_str(" last_error=none"),
+    }
+    message
+}
+
+#[cfg(unix)]
+fn write_system_log(logger: &SystemLogger, level: LogLevel, msg: &str) -> bool {
+    write_syslog(logger, level, msg, &[])
+}
+
+#[cfg(target_os = "windows")]
+fn write_system_log(logger: &SystemLogger, level: LogLevel, msg: &str) -> bool {
+    logger
+        .try_report_result(level.to_winlog_level(), msg)
+        .is_ok()
+}
+
+// ===== Имена файлов с меткой времени =====
+
+const TIMESTAMP_NAME_FORMAT: &str = "%Y%m%d-%H%M%S-%3f";
+const TIMESTAMP_NAME_LEN: usize = 19; // 20250405-143022-123
+
+fn timestamped_file_name(basename: &str, clock: &dyn Clock) -> String {
+    let stamp = clock.now().format(TIMESTAMP_NAME_FORMAT).to_string();
+    instance_file_name(basename, &stamp)
+}
+
+const COMPRESSED_SUFFIX: &str = ".gz";
+const STAGING_INFIX: &str = ".rotating.";
+
+fn compressed_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(COMPRESSED_SUFFIX);
+    PathBuf::from(name)
+}
+
+// Переименование, а между томами (отдельный archive_dir) — копирование и удаление
+pub(crate) fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(rename_error) => {
+            if fs::copy(from, to).is_err() {
+                let _ = fs::remove_file(to);
+                return Err(rename_error);
+            }
+            fs::remove_file(from)
+        }
+    }
+}
+
+// Признак «тот же файл»: после ротации по пути лежит другой файл
+#[derive(PartialEq, Eq)]
+pub(crate) struct FileId(
+    #[cfg(unix)] (u64, u64),
+    // Серийный номер тома и индекс файла. Время создания не годится: из-за туннелирования
+    // файловой системы файл, созданный под только что переименованным именем, получает
+    // время создания прежнего — ровно то, что делает ротация
+    #[cfg(windows)] (u32, u64),
+    #[cfg(not(any(unix, windows)))] Option<std::time::SystemTime>,
+);
+
+impl FileId {
+    // Файл, который уже открыт
+    #[cfg(unix)]
+    pub(crate) fn of(file: &File) -> io::Result<Self> {
+        use std::os::unix::fs::MetadataExt;
+        let meta = file.metadata()?;
+        Ok(FileId((meta.dev(), meta.ino())))
+    }
+
+    #[cfg(windows)]
+    pub(crate) fn of(file: &File) -> io::Result<Self> {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::Storage::FileSystem::{
+            GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION,
+        };
+
+        let mut info: BY_HANDLE_FILE_INFORMATION = unsafe { std::mem::zeroed() };
+        if unsafe { GetFileInformationByHandle(file.as_raw_handle() as _, &mut info) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let index = (u64::from(info.nFileIndexHigh) << 32) | u64::from(info.nFileIndexLow);
+        Ok(FileId((info.dwVolumeSerialNumber, index)))
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    pub(crate) fn of(file: &File) -> io::Result<Self> {
+        Ok(FileId(file.metadata()?.created().ok()))
+    }
+
+    // Файл, который сейчас лежит по пути
+    #[cfg(unix)]
+    pub(crate) fn at(path: &Path) -> io::Result<Self> {
+        use std::os::unix::fs::MetadataExt;
+        let meta = fs::metadata(path)?;
+        Ok(FileId((meta.dev(), meta.ino())))
+    }
+
+    // Открываем без прав на чтение и запись и ничего не запрещая другим: только чтобы
+    // спросить индекс
+    #[cfg(windows)]
+    pub(crate) fn at(path: &Path) -> io::Result<Self> {
+        use std::os::windows::fs::OpenOptionsExt;
+        let file = OpenOptions::new()
+            .access_mode(0)
+            .share_mode(SHARE_ALL)
+            .open(path)?;
+        Self::of(&file)
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    pub(crate) fn at(path: &Path) -> io::Result<Self> {
+        Ok(FileId(fs::metadata(path)?.created().ok()))
+    }
+}
+
+// Строки уже записанного файла — начальный счётчик для RotationPolicy::Lines
+fn existing_records(path: &Path, policy: RotationPolicy) -> io::Result<u64> {
+    if policy == RotationPolicy::Size {
+        return Ok(0);
+    }
+    let mut reader = io::BufReader::new(open_for_reading(path)?);
+    let mut count = 0;
+    loop {
+        let buf = io::BufRead::fill_buf(&mut reader)?;
+        if buf.is_empty() {
+            return Ok(count);
+        }
+        count += buf.iter().filter(|&&b| b == b'\n').count() as u64;
+        let len = buf.len();
+        io::BufRead::consume(&mut reader, len);
+    }
+}
+
+// Файлы вида app.<метка>.log в каталоге, от старых к новым (метка сортируется как строка)
+fn list_timestamped_files(dir: &Path, basename: &str) -> Vec<String> {
+    list_timestamped(dir, basename, false)
+}
+
+// То же вместе со сжатыми архивами (app.<метка>.log.gz)
+fn list_archived_files(dir: &Path, basename: &str) -> Vec<String> {
+    list_timestamped(dir, basename, cfg!(feature = "gzip"))
+}
+
+fn list_timestamped(dir: &Path, basename: &str, compressed: bool) -> Vec<String> {
+    let (stem, ext) = split_extension(basename);
+    let prefix = format!("{}.", stem);
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| {
+            let name = match name.strip_suffix(COMPRESSED_SUFFIX) {
+                Some(plain) if compressed => plain,
+                _ => name.as_str(),
+            };
+            name.strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_suffix(ext))
+                .map(|stamp| {
+                    stamp.len() == TIMESTAMP_NAME_LEN
+                        && stamp.bytes().all(|b| b.is_ascii_digit() || b == b'-')
+                })
+                .unwrap_or(false)
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+#[cfg(unix)]
+fn point_current_link(dir: &Path, basename: &str, active: &str) -> io::Result<()> {
+    let link = dir.join(basename);
+    // Обычный файл с таким именем (например, оставшийся от Numbered) не трогаем
+    if let Ok(meta) = fs::symlink_metadata(&link) {
+        if !meta.file_type().is_symlink() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a symlink", link.display()),
+            ));
+        }
+    }
+    // Ссылку создаём рядом и переименовываем поверх старой — замена атомарна.
+    // Цель относительная, чтобы каталог с логами можно было перенести.
+    let tmp = dir.join(format!("{}.link-tmp", basename));
+    let _ = fs::remove_file(&tmp);
+    std::os::unix::fs::symlink(active, &tmp)?;
+    fs::rename(&tmp, &link)
+}
+
+// Символические ссылки на Windows требуют привилегий, поэтому при неудаче
+// рядом пишется файл-указатель <имя>.latest с именем активного файла
+#[cfg(not(unix))]
+fn point_current_link(dir: &Path, basename: &str, active: &str) -> io::Result<()> {
+    #[cfg(windows)]
+    {
+        let link = dir.join(basename);
+        let replaceable = match fs::symlink_metadata(&link) {
+            Ok(meta) => meta.file_type().is_symlink() && fs::remove_file(&link).is_ok(),
+            Err(_) => true,
+        };
+        if replaceable && std::os::windows::fs::symlink_file(active, &link).is_ok() {
+            return Ok(());
+        }
+    }
+    fs::write(dir.join(format!("{}.latest", basename)), active)
+}
+
+// ===== Свободное место на диске =====
+
+#[cfg(unix)]
+fn free_disk_space(dir: &Path) -> io::Result<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let c_path = std::ffi::CString::new(dir.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(windows)]
+fn free_disk_space(dir: &Path) -> io::Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = dir.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available: u64 = 0;
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(available)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn free_disk_space(_dir: &Path) -> io::Result<u64> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "free space check is not supported on this platform",
+    ))
+}
+
+// ===== Сведения о хосте и процессе =====
+
+/// Сведения о сборке приложения: попадают в каждую запись полями `version`, `git_hash`,
+/// `build_date` и в заголовок ротации. Обычно создаётся макросом `with_build_info!()`
+/// в крейте приложения.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BuildInfo {
+    /// Имя пакета; в заголовке ротации его заменяет `app_name`, если оно задано
+    pub name: String,
+    pub version: String,
+    pub git_hash: Option<String>,
+    pub build_date: Option<String>,
+}
+
+impl BuildInfo {
+    // Поля записи; имя пакета в них не входит — оно уже в app_name или PROC
+    fn fields(&self) -> impl Iterator<Item = (String, String)> + '_ {
+        [
+            Some(("version", &self.version)),
+            self.git_hash.as_ref().map(|hash| ("git_hash", hash)),
+            self.build_date.as_ref().map(|date| ("build_date", date)),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|(key, value)| (key.to_owned(), value.clone()))
+    }
+}
+
+/// `billing v1.4.2 (git 3f9c2ab, built 2026-10-16)`
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} v{}", self.name, self.version)?;
+        match (&self.git_hash, &self.build_date) {
+            (Some(hash), Some(date)) => write!(f, " (git {}, built {})", hash, date),
+            (Some(hash), None) => write!(f, " (git {})", hash),
+            (None, Some(date)) => write!(f, " (built {})", date),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn hostname() -> String {
+    let mut buf = [0u8; 256];
+    if unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) } != 0 {
+        return "unknown".to_owned();
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+#[cfg(not(unix))]
+fn hostname() -> String {
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| "unknown".to_owned())
+}
+
+// Имя исполняемого файла без расширения
+fn process_name() -> String {
+    std::env::current_exe()
+        .ok()
+        .and_then(|path| path.file_stem().map(|s| s.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "unknown".to_owned())
+}
+
+// Постоянная часть строки: " HOST:web-01 PROC:app env=prod"
+fn static_context(hostname: bool, process_name: bool, fields: &[(String, String)]) -> String {
+    let mut context = String::new();
+    if hostname {
+        context.push_str(&format!(" HOST:{}", self::hostname()));
+    }
+    if process_name {
+        context.push_str(&format!(" PROC:{}", self::process_name()));
+    }
+    for (key, value) in fields {
+        context.push_str(&format!(" {}={}", key, value));
+    }
+    context
+}
+
+// Те же данные парами: host, process и статические поля
+fn static_context_fields(
+    hostname: bool,
+    process_name: bool,
+    fields: &[(String, String)],
+) -> Vec<(String, String)> {
+    let mut context = Vec::new();
+    if hostname {
+        context.push(("host".to_owned(), self::hostname()));
+    }
+    if process_name {
+        context.push(("process".to_owned(), self::process_name()));
+    }
+    context.extend(fields.iter().cloned());
+    context
+}
+
+// ===== Межпроцессная блокировка ротации =====
+
+// Файл-блокировка рядом с логом. Mutex нужен потому, что flock не различает
+// потоки одного процесса: без него второй поток «получил» бы уже взятую блокировку.
+struct RotationLock {
+    file: Mutex<File>,
+}
+
+struct RotationLockGuard<'a> {
+    file: std::sync::MutexGuard<'a, File>,
+}
+
+impl RotationLock {
+    fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(path)?;
+        Ok(RotationLock {
+            file: Mutex::new(file),
+        })
+    }
+
+    fn acquire(&self) -> io::Result<RotationLockGuard<'_>> {
+        let file = self.file.lock().unwrap();
+        lock_file_exclusive(&file)?;
+        Ok(RotationLockGuard { file })
+    }
+}
+
+impl Drop for RotationLockGuard<'_> {
+    fn drop(&mut self) {
+        let _ = unlock_file(&self.file);
+    }
+}
+
+#[cfg(unix)]
+fn lock_file_exclusive(file: &File) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn unlock_file(file: &File) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_UN) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn lock_file_exclusive(file: &File) -> io::Result<()> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{LockFileEx, LOCKFILE_EXCLUSIVE_LOCK};
+    use windows_sys::Win32::System::IO::OVERLAPPED;
+
+    let mut overlapped: OVERLAPPED = unsafe { std::mem::zeroed() };
+    let ok = unsafe {
+        LockFileEx(
+            file.as_raw_handle() as _,
+            LOCKFILE_EXCLUSIVE_LOCK,
+            0,
+            u32::MAX,
+            u32::MAX,
+            &mut overlapped,
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn unlock_file(file: &File) -> io::Result<()> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::UnlockFileEx;
+    use windows_sys::Win32::System::IO::OVERLAPPED;
+
+    let mut overlapped: OVERLAPPED = unsafe { std::mem::zeroed() };
+    let ok = unsafe {
+        UnlockFileEx(
+            file.as_raw_handle() as _,
+            0,
+            u32::MAX,
+            u32::MAX,
+            &mut overlapped,
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn lock_file_exclusive(_file: &File) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn unlock_file(_file: &File) -> io::Result<()> {
+    Ok(())
+}
+
+// ===== Открытие файла и права доступа =====
+
+fn open_log_file(path: &Path, config: &FileConfig, open_mode: OpenMode) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.append(true);
+    match open_mode {
+        OpenMode::CreateNew => options.create_new(true),
+        OpenMode::Append | OpenMode::Truncate => options.create(true),
+    };
+
+    // Права задаём сразу при создании, чтобы файл ни на миг не был доступен всем
+    #[cfg(unix)]
+    if let Some(mode) = config.mode {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(mode);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        options.share_mode(SHARE_ALL);
+    }
+
+    let file = options.open(path).map_err(|e| match e.kind() {
+        io::ErrorKind::AlreadyExists => io::Error::new(
+            e.kind(),
+            format!("{} already exists (OpenMode::CreateNew)", path.display()),
+        ),
+        _ => e,
+    })?;
+    apply_file_permissions(&file, path, config)?;
+    // O_APPEND сохраняем: с ним несколько дескрипторов не затирают записи друг друга
+    if open_mode == OpenMode::Truncate {
+        file.set_len(0)?;
+    }
+    if config.bom && file.metadata()?.len() == 0 {
+        (&file).write_all(UTF8_BOM)?;
+    }
+    Ok(file)
+}
+
+// Активный файл можно читать (BareTail, скрипты поддержки), перемещать и удалять,
+// пока служба пишет в него; FILE_SHARE_WRITE — для multi_process
+#[cfg(windows)]
+const SHARE_ALL: u32 = windows_sys::Win32::Storage::FileSystem::FILE_SHARE_READ
+    | windows_sys::Win32::Storage::FileSystem::FILE_SHARE_WRITE
+    | windows_sys::Win32::Storage::FileSystem::FILE_SHARE_DELETE;
+
+// Файл лога для чтения; на Windows не мешает логгеру писать в него и ротировать
+pub(crate) fn open_for_reading(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.read(true);
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        options.share_mode(SHARE_ALL);
+    }
+    options.open(path)
+}
+
+// mode при создании урезается umask'ом и не трогает уже существующий файл — выставляем явно
+#[cfg(unix)]
+fn apply_file_permissions(file: &File, _path: &Path, config: &FileConfig) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    if let Some(mode) = config.mode {
+        file.set_permissions(fs::Permissions::from_mode(mode))?;
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn apply_file_permissions(_file: &File, path: &Path, config: &FileConfig) -> io::Result<()> {
+    if config.restrict_acl {
+        restrict_file_acl(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn apply_file_permissions(_file: &File, _path: &Path, _config: &FileConfig) -> io::Result<()> {
+    Ok(())
+}
+
+// Защищённый DACL (без наследования): полный доступ только SYSTEM, Administrators
+// и владельцу файла — иначе процесс не под админом не сможет открыть файл после ротации
+#[cfg(windows)]
+fn restrict_file_acl(path: &Path) -> io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Foundation::LocalFree;
+    use windows_sys::Win32::Security::Authorization::{
+        ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+    };
+    use windows_sys::Win32::Security::{
+        SetFileSecurityW, DACL_SECURITY_INFORMATION, PROTECTED_DACL_SECURITY_INFORMATION,
+        PSECURITY_DESCRIPTOR,
+    };
+
+    let sddl: Vec<u16> = "D:P(A;;FA;;;SY)(A;;FA;;;BA)(A;;FA;;;OW)"
+        .encode_utf16()
+        .chain(Some(0))
+        .collect();
+    let wide_path: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+
+    let mut descriptor: PSECURITY_DESCRIPTOR = std::ptr::null_mut();
+    let converted = unsafe {
+        ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            sddl.as_ptr(),
+            SDDL_REVISION_1,
+            &mut descriptor,
+            std::ptr::null_mut(),
+        )
+    };
+    if converted == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let applied = unsafe {
+        SetFileSecurityW(
+            wide_path.as_ptr(),
+            DACL_SECURITY_INFORMATION | PROTECTED_DACL_SECURITY_INFORMATION,
+            descriptor,
+        )
+    };
+    let result = if applied == 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    };
+
+    unsafe { LocalFree(descriptor as _) };
+    result
+}
+
+// ===== Основной логгер =====
+
+pub struct Logger {
+    rotating_writer: Option<Arc<RotatingWriter>>,
+    extra_writers: Vec<Arc<RotatingWriter>>, // дополнительные файлы (например, errors.log)
+    sinks: Vec<Arc<dyn Sink>>,               // почта, webhook и прочие приёмники
+    attached: Arc<AttachedSinks>,            // подключённые на ходу (add_sink)
+    has_attached: AtomicBool,                // чтобы без них не брать блокировку
+    next_sink_id: AtomicU64,
+    enrichers: RwLock<Vec<Enricher>>,
+    has_enrichers: AtomicBool,
+    system_logger: Option<SystemLogger>,
+    app_name: String,
+    levels: Arc<Levels>,
+    stats: Arc<StatsCounters>,
+    settings: LoggerConfig, // настройки построителя, для reconfigure_global
+    system_log_format: LineFormat,
+    interceptors: Vec<Interceptor>,
+    trace_source: Option<trace::TraceSource>,
+    clock: Arc<dyn Clock>, // время записей
+    started: Duration,     // Clock::monotonic при создании, для итога работы
+    shutdown_summary: bool,
+    summary_written: AtomicBool,
+    #[allow(dead_code)] // держит канал открытым, пока жив логгер
+    control: Option<control::Server>,
+    #[cfg(feature = "http_admin")]
+    http_admin: Option<admin::HttpServer>,
+}
+
+impl Logger {
+    pub fn system_only(app_name: &str) -> std::io::Result<Self> {
+        Logger::builder()
+            .app_name(app_name)
+            .system_log(true)
+            .build()
+    }
+
+    pub fn file_only<P: AsRef<Path>>(
+        directory: P,
+        filename: &str,
+        max_size_bytes: u64,
+        max_files: usize,
+    ) -> std::io::Result<Self> {
+        Logger::builder()
+            .file(directory, filename, max_size_bytes, max_files)
+            .build()
+    }
+
+    pub fn file_and_system<P: AsRef<Path>>(
+        app_name: &str,
+        directory: P,
+        filename: &str,
+        max_size_bytes: u64,
+        max_files: usize,
+    ) -> std::io::Result<Self> {
+        Logger::builder()
+            .app_name(app_name)
+            .file(directory, filename, max_size_bytes, max_files)
+            .system_log(true)
+            .build()
+    }
+
+    /// Логгер без файлов, системного лога и приёмников: записи сразу отбрасываются.
+    /// Для библиотек, которые принимают `Arc<Logger>` всегда, и для тестов и бенчмарков.
+    pub fn disabled() -> Self {
+        Logger {
+            rotating_writer: None,
+            extra_writers: Vec::new(),
+            sinks: Vec::new(),
+            attached: Arc::default(),
+            has_attached: AtomicBool::new(false),
+            next_sink_id: AtomicU64::new(1),
+            enrichers: RwLock::new(Vec::new()),
+            has_enrichers: AtomicBool::new(false),
+            system_logger: None,
+            app_name: "disabled".to_owned(),
+            levels: Levels::new(LogLevel::Trace, None),
+            stats: Arc::new(StatsCounters::default()),
+            settings: LoggerConfig::default(),
+            system_log_format: LineFormat::Text,
+            interceptors: Vec::new(),
+            trace_source: None,
+            clock: system_clock(),
+            started: Duration::ZERO,
+            shutdown_summary: false,
+            summary_written: AtomicBool::new(false),
+            control: None,
+            #[cfg(feature = "http_admin")]
+            http_admin: None,
+        }
+    }
+
+    pub fn builder() -> LoggerBuilder {
+        LoggerBuilder::new()
+    }
+
+    pub fn app_name(&self) -> &str {
+        &self.app_name
+    }
+
+    fn write_startup_banner(&self) {
+        let fields = startup::fields();
+        let fields: Vec<(&str, &str)> = fields.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        self.write_lifecycle("STARTUP", "loglib::startup", &fields);
+    }
+
+    // Итог работы; при повторном shutdown не повторяется
+    fn write_shutdown_summary(&self) {
+        if !self.shutdown_summary || self.summary_written.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let stats = self.stats.snapshot();
+        let uptime = self.clock.monotonic().saturating_sub(self.started);
+        let mut fields = vec![
+            ("uptime", format!("{:.3}s", uptime.as_secs_f64())),
+            ("records", stats.total_records().to_string()),
+        ];
+        for (level, count) in LogLevel::ALL.iter().zip(stats.records_by_level) {
+            fields.push((ecs_level(*level), count.to_string()));
+        }
+        fields.push(("rotations", stats.rotations.to_string()));
+        fields.push(("dropped", stats.dropped.to_string()));
+        let fields: Vec<(&str, &str)> = fields.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        self.write_lifecycle("SHUTDOWN", "loglib::shutdown", &fields);
+    }
+
+    // Стартовая и итоговая записи идут мимо уровней: их ищут как раз тогда, когда
+    // уровень Warning
+    fn write_lifecycle(&self, message: &str, target: &str, fields: &[(&str, &str)]) {
+        if let Some(ref writer) = self.rotating_writer {
+            let _ = writer.write(
+                &Record::new(LogLevel::Info, format_args!("{}", message))
+                    .with_target(target)
+                    .with_fields(fields),
+            );
+        } else if let Some(ref logger) = self.system_logger {
+            let mut message = message.to_owned();
+            for (key, value) in fields {
+                message.push_str(&format!(" {}={}", key, value));
+            }
+            let ok = write_system_log(logger, LogLevel::Info, &message);
+            StatsCounters::set_outcome(&self.stats.last_system_write, ok);
+            if !ok {
+                StatsCounters::inc(&self.stats.system_log_errors);
+            }
+        }
+    }
+
+    /// Настройки, с которыми собран логгер. Приёмники, перехватчики, дополнительные
+    /// файлы и часы в них не входят.
+    pub fn config(&self) -> &LoggerConfig {
+        &self.settings
+    }
+
+    #[cfg(unix)]
+    fn init_system_logger(
+        app_name: &str,
+        options: &SyslogOptions,
+        static_fields: &[(String, String)],
+    ) -> std::io::Result<Option<SystemLogger>> {
+        let ident = match options.ident {
+            Some(ref ident) => ident.clone(),
+            None if app_name == "unnamed" => process_name(),
+            None => app_name.to_owned(),
+        };
+        let formatter = SyslogFormatter {
+            facility: options.facility.to_syslog(),
+            ident,
+            pid: options.include_pid.then(std::process::id),
+            format: options.format,
+            hostname: hostname(),
+            static_fields: static_fields.to_vec(),
+        };
+        match syslog::unix(formatter) {
+            Ok(logger) => Ok(Some(Arc::new(Mutex::new(logger)))),
+            Err(_) => Ok(None),
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    fn init_system_logger(
+        app_name: &str,
+        _options: &SyslogOptions,
+        _static_fields: &[(String, String)],
+    ) -> std::io::Result<Option<SystemLogger>> {
+        Ok(Some(winlog_rs::WinEventLogger::new(app_name)))
+    }
+
+    /// Меняет глобальный уровень — для всех логгеров процесса, как `set_global_log_level`.
+    /// Уровень только этого логгера — `set_min_level` и `set_level_override`.
+    pub fn set_log_level(&self, level: LogLevel) {
+        set_global_log_level(level);
+    }
+
+    /// Порог этого логгера поверх глобального уровня: запись должна пройти оба.
+    /// Начальное значение — из `LoggerBuilder::verbosity`, иначе Trace.
+    pub fn set_min_level(&self, level: LogLevel) {
+        self.levels.set_min(level);
+    }
+
+    pub fn min_level(&self) -> LogLevel {
+        self.levels.min()
+    }
+
+    /// Уровень этого логгера вместо глобального (`None` — снова глобальный). Так логгер
+    /// аудита пишет Info, даже когда приложение подняло глобальный уровень до Error.
+    /// `min_level` действует и при замене.
+    pub fn set_level_override(&self, level: Option<LogLevel>) {
+        self.levels.set_overridden(level);
+    }
+
+    pub fn level_override(&self) -> Option<LogLevel> {
+        self.levels.overridden()
+    }
+
+    /// Пишет запись с уровнем, известным только во время выполнения.
+    pub fn log(&self, level: LogLevel, args: std::fmt::Arguments) {
+        self.write_record(&Record::new(level, args));
+    }
+
+    pub fn platform_log(&self, level: LogLevel, message: &str) {
+        self.platform_log_record(&Record::new(level, format_args!("{}", message)));
+    }
+
+    /// Как `platform_log`, но с полями записи (в RFC 5424 они уходят в structured data).
+    pub fn platform_log_record(&self, record: &Record) {
+        if self.system_logger.is_none() {
+            return;
+        }
+        let level = record.level;
+        if self.accepts(level) {
+            if let Some(ref logger) = self.system_logger {
+                let written = self.prepared(record, |record| {
+                    with_format_buffer(|message| {
+                        let fields = match self.system_log_format {
+                            LineFormat::Json => {
+                                system_log_json(message, record);
+                                &[][..]
+                            }
+                            LineFormat::Ecs => {
+                                system_log_ecs(message, record);
+                                &[][..]
+                            }
+                            LineFormat::Text | LineFormat::Message => {
+                                let _ = write!(message, "{}", record.args);
+                                record.fields
+                            }
+                        };
+                        // Display и экранирование JSON дают только UTF-8
+                        let text = std::str::from_utf8(message).unwrap_or_default();
+                        let ok = self.log_to_system(logger, record.level, text, fields);
+                        (ok, message.len())
+                    })
+                });
+                let Some((ok, bytes)) = written else {
+                    StatsCounters::inc(&self.stats.dropped);
+                    return;
+                };
+                StatsCounters::set_outcome(&self.stats.last_system_write, ok);
+                if ok {
+                    self.stats.record(level, bytes);
+                } else {
+                    StatsCounters::inc(&self.stats.system_log_errors);
+                    self.stats
+                        .set_last_error("system log write failed".to_owned());
+                }
+            }
+        } else {
+            StatsCounters::inc(&self.stats.dropped);
+        }
+    }
+
+    /// Пройдёт ли запись уровня `level` фильтр (с учётом фич `max_level_*`).
+    /// Макросы проверяют это до форматирования.
+    pub fn enabled(&self, level: LogLevel) -> bool {
+        (level as usize) >= __STATIC_MIN_LEVEL && self.accepts(level)
+    }
+
+    // Глобальный уровень (или замена его для этого логгера) и уровень логгера;
+    // область with_level_scope может пропустить и то, что они отсекли бы
+    fn accepts(&self, level: LogLevel) -> bool {
+        let threshold = match self.level_override() {
+            Some(threshold) => level != LogLevel::Off && level >= threshold,
+            None => should_log(level),
+        };
+        (threshold && level >= self.min_level()) || self.scope_accepts(level)
+    }
+
+    fn scope_accepts(&self, level: LogLevel) -> bool {
+        if LEVEL_SCOPES_ACTIVE.load(Ordering::Relaxed) == 0 || level == LogLevel::Off {
+            return false;
+        }
+        let key = self.scope_key();
+        let thread = LEVEL_SCOPES
+            .try_with(|scopes| innermost_level(&scopes.borrow(), key))
+            .ok()
+            .flatten();
+        let passes = |scoped: Option<LogLevel>| scoped.is_some_and(|scoped| level >= scoped);
+        #[cfg(feature = "tokio")]
+        if passes(context::scoped_level(key)) {
+            return true;
+        }
+        passes(thread)
+    }
+
+    // Логгер не может переместиться, пока открыта его область: она держит &self
+    fn scope_key(&self) -> usize {
+        self as *const Logger as usize
+    }
+
+    /// Выполняет `f`, пропуская в этом потоке записи этого логгера от `level` и выше —
+    /// даже если глобальный уровень или `min_level` их отсекли бы. Так можно снять
+    /// подробности одной операции, не включая Trace для всего сервиса. Пороги файлов
+    /// и приёмников действуют как прежде; другие потоки области не видят.
+    pub fn with_level_scope<R>(&self, level: LogLevel, f: impl FnOnce() -> R) -> R {
+        let _scope = ThreadLevelScope {
+            _active: LevelScopeActive::new(),
+        };
+        LEVEL_SCOPES.with(|scopes| scopes.borrow_mut().push((self.scope_key(), level)));
+        f()
+    }
+
+    /// Как `with_level_scope`, но для задачи tokio: область действует, на каком бы
+    /// потоке ни выполнялась `future`. В задачи из `tokio::spawn` она не переходит.
+    #[cfg(feature = "tokio")]
+    pub async fn with_level_scope_async<F: std::future::Future>(
+        &self,
+        level: LogLevel,
+        future: F,
+    ) -> F::Output {
+        let _active = LevelScopeActive::new();
+        context::level_scope(self.scope_key(), level, future).await
+    }
+
+    #[doc(hidden)] // для макросов: учитывает запись, отброшенную до форматирования
+    pub fn __count_filtered(&self) {
+        if self.has_outputs() {
+            StatsCounters::inc(&self.stats.dropped);
+        }
+    }
+
+    /// Снимок счётчиков: записи по уровням, байты, ротации, отброшенные записи и ошибки.
+    pub fn stats(&self) -> LogStats {
+        self.stats.snapshot()
+    }
+
+    /// Исправен ли логгер: для `/healthz` и readiness-проб.
+    pub fn health(&self) -> LogHealth {
+        let mut pending_records = 0;
+        let mut since_last_flush = None;
+        for writer in self.writers() {
+            let state = &writer.flush_state;
+            pending_records += state.unflushed.load(Ordering::Relaxed) as u64;
+            let elapsed = state.last_flush.lock().unwrap().elapsed();
+            since_last_flush = since_last_flush.max(Some(elapsed));
+        }
+        LogHealth {
+            file_ok: StatsCounters::outcome(&self.stats.last_file_write),
+            system_log_ok: StatsCounters::outcome(&self.stats.last_system_write),
+            pending_records,
+            since_last_flush,
+        }
+    }
+
+    pub fn write_to_file(&self, level: LogLevel, message: &str) {
+        let _ = self.try_write(level, message);
+    }
+
+    /// Как `write_to_file`, но возвращает ошибку записи в файл вместо того, чтобы её проглотить.
+    /// Без файлового лога и для отфильтрованных по уровню записей возвращает `Ok(())`.
+    pub fn try_write(&self, level: LogLevel, message: &str) -> io::Result<()> {
+        self.try_write_record(&Record::new(level, format_args!("{}", message)))
+    }
+
+    /// Пишет запись в файл; сообщение форматируется сразу в буфер файла.
+    /// Этим методом пользуются макросы `trace!`..`fatal!`.
+    pub fn write_record(&self, record: &Record) {
+        let _ = self.try_write_record(record);
+    }
+
+    /// Пишет запись во все файлы логгера. Ошибка одного файла не мешает записи в остальные;
+    /// возвращается первая из ошибок.
+    pub fn try_write_record(&self, record: &Record) -> io::Result<()> {
+        if !self.has_outputs() {
+            return Ok(());
+        }
+        if !self.accepts(record.level) {
+            StatsCounters::inc(&self.stats.dropped);
+            return Ok(());
+        }
+        let written = self.prepared(record, |record| self.write_outputs(record));
+        written.unwrap_or_else(|| {
+            StatsCounters::inc(&self.stats.dropped);
+            Ok(())
+        })
+    }
+
+    /// Добавляет обогатитель: он вызывается для каждой записи, прошедшей уровень, и
+    /// дописывает к ней поля — текущего арендатора, ревизию конфигурации, память
+    /// процесса, — не оборачивая каждый вызов макроса. Запись только заимствует свои
+    /// данные, поэтому новые поля кладутся в переданный список; они идут после полей
+    /// контекста и самой записи. Перехватчики видят уже дополненную запись.
+    pub fn add_enricher<F>(&self, enricher: F)
+    where
+        F: Fn(&Record, &mut Vec<(String, String)>) + Send + Sync + 'static,
+    {
+        self.enrichers.write().unwrap().push(Arc::new(enricher));
+        self.has_enrichers.store(true, Ordering::Release);
+    }
+
+    // Время, поля контекста, correlation_id, trace_id/span_id, обогатители и
+    // перехватчики — всё, что добавляется к записи до выходов; None — перехватчик её отбросил
+    fn prepared<R>(&self, record: &Record, write: impl FnOnce(&Record) -> R) -> Option<R> {
+        with_context(&record.stamped(&*self.clock), |record| {
+            correlation::with_correlation(record, |record| {
+                trace::with_trace(record, self.trace_source.as_ref(), |record| {
+                    self.enriched(record, |record| self.intercepted(record, write))
+                })
+            })
+        })
+    }
+
+    fn enriched<R>(&self, record: &Record, write: impl FnOnce(&Record) -> R) -> R {
+        if !self.has_enrichers.load(Ordering::Acquire) {
+            return write(record);
+        }
+        let mut added = Vec::new();
+        for enricher in self.enrichers.read().unwrap().iter() {
+            enricher(record, &mut added);
+        }
+        if added.is_empty() {
+            return write(record);
+        }
+        let fields: Vec<(&str, &str)> = record
+            .fields
+            .iter()
+            .copied()
+            .chain(added.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+            .collect();
+        write(&Record {
+            fields: &fields,
+            ..*record
+        })
+    }
+
+    // Пропускает запись через перехватчики; None — запись отброшена
+    fn intercepted<R>(&self, record: &Record, write: impl FnOnce(&Record) -> R) -> Option<R> {
+        if self.interceptors.is_empty() {
+            return Some(write(record));
+        }
+        let mut owned = OwnedRecord::from_record(record);
+        for interceptor in &self.interceptors {
+            owned = interceptor(owned)?;
+        }
+        // Перехватчик мог понизить уровень
+        if !self.accepts(owned.level) {
+            return None;
+        }
+        let fields: Vec<(&str, &str)> = owned
+            .fields
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        Some(write(&Record {
+            level: owned.level,
+            args: format_args!("{}", owned.message),
+            location: owned.location,
+            target: Some(&owned.target),
+            fields: &fields,
+            ..*record
+        }))
+    }
+
+    fn write_outputs(&self, record: &Record) -> io::Result<()> {
+        // Категории с маршрутом не попадают в основной файл
+        let target = record.target();
+        let routed = self.extra_writers.iter().any(|w| w.routes(target));
+        let main = self.rotating_writer.iter().filter(|_| !routed);
+        let extra = self.extra_writers.iter().filter(|w| match w.config.route {
+            Some(_) => w.routes(target),
+            None => true,
+        });
+
+        let mut written = false;
+        let mut result = Ok(());
+        for writer in main.chain(extra) {
+            match writer.write(record) {
+                Ok(ok) => written |= ok,
+                Err(e) => {
+                    if result.is_ok() {
+                        result = Err(e);
+                    }
+                }
+            }
+        }
+        let mut write_sink = |sink: &Arc<dyn Sink>| match sink.write(record) {
+            Ok(()) => written = true,
+            Err(e) => {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        };
+        self.sinks.iter().for_each(&mut write_sink);
+        if self.has_attached.load(Ordering::Acquire) {
+            let attached = self.attached.read().unwrap();
+            attached.iter().for_each(|(_, sink)| write_sink(sink));
+        }
+        if written {
+            self.stats.record(record.level, 0);
+        }
+        result
+    }
+
+    fn writers(&self) -> impl Iterator<Item = &Arc<RotatingWriter>> {
+        self.rotating_writer.iter().chain(self.extra_writers.iter())
+    }
+
+    fn has_outputs(&self) -> bool {
+        self.rotating_writer.is_some()
+            || !self.extra_writers.is_empty()
+            || !self.sinks.is_empty()
+            || self.has_attached.load(Ordering::Acquire)
+    }
+
+    /// Обработчик HTTP-управления этим логгером — чтобы встроить `/level`, `/rotate`,
+    /// `/stats` в свой сервер (axum, hyper) вместо `LoggerBuilder::http_admin`.
+    #[cfg(feature = "http_admin")]
+    pub fn admin_handler(&self) -> admin::AdminHandler {
+        admin::AdminHandler::new(self.control_target())
+    }
+
+    /// Адрес сервера из `LoggerBuilder::http_admin` — с настоящим портом, если задан 0.
+    #[cfg(feature = "http_admin")]
+    pub fn http_admin_addr(&self) -> Option<std::net::SocketAddr> {
+        self.http_admin.as_ref().map(admin::HttpServer::local_addr)
+    }
+
+    // Слабые ссылки на части логгера для канала управления
+    fn control_target(&self) -> control::Target {
+        control::Target {
+            levels: Arc::downgrade(&self.levels),
+            writers: self.writers().map(Arc::downgrade).collect(),
+            sinks: self.sinks.iter().map(Arc::downgrade).collect(),
+            attached: Arc::downgrade(&self.attached),
+            stats: Arc::downgrade(&self.stats),
+        }
+    }
+
+    // Приёмники построителя и подключённые на ходу
+    fn all_sinks(&self) -> Vec<Arc<dyn Sink>> {
+        let attached = self.attached.read().unwrap();
+        let attached = attached.iter().map(|(_, sink)| Arc::clone(sink));
+        self.sinks.iter().cloned().chain(attached).collect()
+    }
+
+    /// Подключает приёмник к работающему логгеру — например, чтобы диагностическая утилита
+    /// на время получала записи сервиса. Записи, которые пишутся в этот момент, могут
+    /// пройти мимо нового приёмника. Отключается через `remove_sink`.
+    pub fn add_sink(&self, sink: Box<dyn Sink>) -> SinkId {
+        let id = SinkId(self.next_sink_id.fetch_add(1, Ordering::Relaxed));
+        let mut attached = self.attached.write().unwrap();
+        attached.push((id, Arc::from(sink)));
+        self.has_attached.store(true, Ordering::Release);
+        id
+    }
+
+    /// Отключает приёмник из `add_sink`: дожидается записей, которые он сейчас получает,
+    /// и сбрасывает его (ошибка сброса возвращается). Неизвестный `id` — `NotFound`.
+    pub fn remove_sink(&self, id: SinkId) -> io::Result<()> {
+        let removed = {
+            let mut attached = self.attached.write().unwrap();
+            let index = attached
+                .iter()
+                .position(|(sink_id, _)| *sink_id == id)
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, format!("no sink with {:?}", id))
+                })?;
+            let (_, sink) = attached.remove(index);
+            self.has_attached
+                .store(!attached.is_empty(), Ordering::Release);
+            sink
+        };
+        removed.flush()
+    }
+
+    /// Сразу отправляет файлы логгера в архив и начинает новые, не дожидаясь
+    /// `max_size`, — например, перед тем как забрать лог для разбора. Возвращает первую
+    /// ошибку; файл, который не удалось переименовать, пишется дальше. При
+    /// `AppendRole::Writer` — `Unsupported`: общий файл ротирует другой процесс.
+    pub fn rotate(&self) -> io::Result<()> {
+        let mut result = Ok(());
+        for writer in self.writers() {
+            if let Err(e) = writer.rotate_now() {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+
+    /// Сбрасывает файлы лога на диск и возвращает ошибку, если это не удалось.
+    /// Приёмники дожидаются отправки уже принятых записей.
+    pub fn try_flush(&self) -> io::Result<()> {
+        flush_outputs(self.writers(), &self.all_sinks())
+    }
+
+    pub fn flush(&self) {
+        let _ = self.try_flush();
+    }
+
+    /// Дожидается окончания текущей записи (не дольше `timeout`), сбрасывает и закрывает файл.
+    /// После этого запись в файл через этот логгер больше невозможна.
+    pub fn shutdown(&self, timeout: Duration) -> io::Result<()> {
+        self.write_shutdown_summary();
+        shutdown_outputs(self.writers(), &self.all_sinks(), timeout)
+    }
+
+    /// Как `try_flush`, но не блокирует поток runtime: сброс выполняется в `spawn_blocking`.
+    /// Вызывать внутри runtime tokio.
+    #[cfg(feature = "tokio")]
+    pub async fn flush_async(&self) -> io::Result<()> {
+        let writers: Vec<_> = self.writers().cloned().collect();
+        let sinks = self.all_sinks();
+        run_blocking(move || flush_outputs(writers.iter(), &sinks)).await
+    }
+
+    /// Как `shutdown`, но не блокирует поток runtime — для обработчиков graceful shutdown.
+    #[cfg(feature = "tokio")]
+    pub async fn shutdown_async(&self, timeout: Duration) -> io::Result<()> {
+        self.write_shutdown_summary();
+        let writers: Vec<_> = self.writers().cloned().collect();
+        let sinks = self.all_sinks();
+        run_blocking(move || shutdown_outputs(writers.iter(), &sinks, timeout)).await
+    }
+
+    #[cfg(unix)]
+    fn log_to_system(
+        &self,
+        logger: &SystemLogger,
+        level: LogLevel,
+        msg: &str,
+        fields: &[(&str, &str)],
+    ) -> bool {
+        write_syslog(logger, level, msg, fields)
+    }
+
+    #[cfg(target_os = "windows")]
+    fn log_to_system(
+        &self,
+        logger: &SystemLogger,
+        level: LogLevel,
+        msg: &str,
+        fields: &[(&str, &str)],
+    ) -> bool {
+        use std::fmt::Write as _;
+
+        with_format_string(|text| {
+            text.push_str(msg);
+            for (key, value) in fields {
+                let _ = write!(text, " {}={}", key, value);
+            }
+            logger
+                .try_report_result(level.to_winlog_level(), text)
+                .is_ok()
+        })
+    }
+}
+
+// Сбрасывает все файлы и приёмники; возвращается первая ошибка
+fn flush_outputs<'a>(
+    writers: impl Iterator<Item = &'a Arc<RotatingWriter>>,
+    sinks: &[Arc<dyn Sink>],
+) -> io::Result<()> {
+    let mut result = Ok(());
+    for writer in writers {
+        if let Err(e) = writer.flush() {
+            if result.is_ok() {
+                result = Err(e);
+            }
+        }
+    }
+    for sink in sinks {
+        if let Err(e) = sink.flush() {
+            if result.is_ok() {
+                result = Err(e);
+            }
+        }
+    }
+    result
+}
+
+fn shutdown_outputs<'a>(
+    writers: impl Iterator<Item = &'a Arc<RotatingWriter>>,
+    sinks: &[Arc<dyn Sink>],
+    timeout: Duration,
+) -> io::Result<()> {
+    let deadline = Instant::now() + timeout;
+    let mut result = Ok(());
+    for writer in writers {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if let Err(e) = writer.shutdown(left) {
+            if result.is_ok() {
+                result = Err(e);
+            }
+        }
+    }
+    for sink in sinks {
+        if let Err(e) = sink.flush() {
+            if result.is_ok() {
+                result = Err(e);
+            }
+        }
+    }
+    result
+}
+
+#[cfg(feature = "tokio")]
+async fn run_blocking<F>(f: F) -> io::Result<()>
+where
+    F: FnOnce() -> io::Result<()> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .unwrap_or_else(|e| Err(io::Error::other(e)))
+}
+
+// ===== Приёмники =====
+
+/// Приёмник, подключённый к работающему логгеру (см. `Logger::add_sink`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SinkId(u64);
+
+type AttachedSinks = RwLock<Vec<(SinkId, Arc<dyn Sink>)>>;
+
+/// Получатель записей помимо файлов и системного лога: почта, webhook, память в тестах.
+/// Вызывается для каждой записи, прошедшей глобальный уровень; свои фильтры приёмник
+/// применяет сам. Запись живёт только во время вызова — для отложенной отправки её
+/// нужно отформатировать.
+pub trait Sink: Send + Sync {
+    fn write(&self, record: &Record) -> io::Result<()>;
+
+    /// Дождаться отправки уже принятых записей
+    fn flush(&self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct FilteredSink<S> {
+    inner: S,
+    filter: Box<dyn Fn(&Record) -> bool + Send + Sync>,
+}
+
+impl<S: Sink> Sink for FilteredSink<S> {
+    fn write(&self, record: &Record) -> io::Result<()> {
+        if (self.filter)(record) {
+            self.inner.write(record)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// ===== Дополнительные файлы =====
+
+/// Ещё один ротируемый файл логгера со своими настройками: каталог, формат строки, уровень.
+/// Добавляется через `LoggerBuilder::add_file`; настройки основного файла на него не влияют.
+/// Хост, процесс, статические поля и `instance_id` берутся от логгера.
+pub struct FileSink {
+    config: FileConfig,
+}
+
+impl FileSink {
+    /// `max_files` — число архивов, 0 — хранить все.
+    pub fn new<P: AsRef<Path>>(
+        directory: P,
+        filename: &str,
+        max_size_bytes: u64,
+        max_files: usize,
+    ) -> Self {
+        FileSink {
+            config: FileConfig::new(
+                directory.as_ref().to_path_buf(),
+                filename.to_owned(),
+                max_size_bytes,
+                max_files,
+            ),
+        }
+    }
+
+    /// Писать в этот файл только записи не ниже `level`.
+    pub fn min_level(mut self, level: LogLevel) -> Self {
+        self.config.min_level = level;
+        self
+    }
+
+    /// Писать в этот файл только записи, для которых `filter` вернул true. Проверяется
+    /// после глобального уровня и `min_level`:
+    /// ```ignore
+    /// FileSink::new("/var/log/app", "audit.log", 10 << 20, 5)
+    ///     .filter(|r| r.target().starts_with("audit"))
+    /// ```
+    pub fn filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&Record) -> bool + Send + Sync + 'static,
+    {
+        self.config.filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Формат строк этого файла, например JSON для сборщика рядом с текстовым основным логом.
+    pub fn line_format(mut self, format: LineFormat) -> Self {
+        self.config.format = format;
+        self
+    }
+
+    pub fn file_mode(mut self, mode: u32) -> Self {
+        self.config.mode = Some(mode);
+        self
+    }
+
+    pub fn restrict_file_acl(mut self, restrict: bool) -> Self {
+        self.config.restrict_acl = restrict;
+        self
+    }
+
+    pub fn multi_process(mut self, enabled: bool) -> Self {
+        self.config.multi_process = enabled;
+        self
+    }
+
+    pub fn shared_append(mut self, role: AppendRole) -> Self {
+        self.config.shared_append = Some(role);
+        self
+    }
+
+    pub fn rotation_naming(mut self, naming: RotationNaming) -> Self {
+        self.config.naming = naming;
+        self
+    }
+
+    pub fn rotation_policy(mut self, policy: RotationPolicy) -> Self {
+        self.config.rotation = policy;
+        self
+    }
+
+    pub fn current_link(mut self, enabled: bool) -> Self {
+        self.config.current_link = enabled;
+        self
+    }
+
+    pub fn rotate_on_open(mut self, enabled: bool) -> Self {
+        self.config.rotate_on_open = enabled;
+        self
+    }
+
+    pub fn open_mode(mut self, mode: OpenMode) -> Self {
+        self.config.open_mode = mode;
+        self
+    }
+
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.config.retry = policy;
+        self
+    }
+
+    pub fn durability(mut self, durability: Durability) -> Self {
+        self.config.durability = durability;
+        self
+    }
+
+    #[cfg(feature = "io_uring")]
+    pub fn io_uring(mut self, enabled: bool) -> Self {
+        self.config.io_uring = enabled;
+        self
+    }
+
+    pub fn archive_dir<P: AsRef<Path>>(mut self, directory: P) -> Self {
+        self.config.archive_dir = Some(directory.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn on_archive<F: Fn(&Path) + Send + Sync + 'static>(mut self, hook: F) -> Self {
+        self.config.on_archive.push(Arc::new(hook));
+        self
+    }
+
+    #[cfg(feature = "s3")]
+    pub fn upload(self, uploader: s3::S3Uploader) -> Self {
+        uploader.start();
+        self.on_archive(move |path| uploader.enqueue(path))
+    }
+
+    pub fn archive_command<S: AsRef<str>>(mut self, command: &[S], timeout: Duration) -> Self {
+        self.config.archive_command = ArchiveCommand::new(command, timeout).map(Arc::new);
+        self
+    }
+
+    #[cfg(feature = "gzip")]
+    pub fn compress(mut self, enabled: bool) -> Self {
+        self.config.compress = enabled;
+        self
+    }
+
+    pub fn source_location(mut self, enabled: bool) -> Self {
+        self.config.source_location = enabled;
+        self
+    }
+
+    pub fn thread_format(mut self, format: ThreadFormat) -> Self {
+        self.config.thread_format = format;
+        self
+    }
+
+    pub fn timestamp_mode(mut self, mode: TimestampMode) -> Self {
+        self.config.timestamp = mode;
+        self
+    }
+
+    pub fn sequence_numbers(mut self, enabled: bool) -> Self {
+        self.config.sequence_numbers = enabled;
+        self
+    }
+
+    pub fn rotation_header(mut self, template: Option<&str>) -> Self {
+        self.config.header = template.map(str::to_owned);
+        self
+    }
+
+    pub fn header_on_open(mut self, enabled: bool) -> Self {
+        self.config.header_on_open = enabled;
+        self
+    }
+
+    pub fn line_ending(mut self, ending: LineEnding) -> Self {
+        self.config.line_ending = ending;
+        self
+    }
+
+    pub fn utf8_bom(mut self, enabled: bool) -> Self {
+        self.config.bom = enabled;
+        self
+    }
+
+    #[cfg(feature = "encoding")]
+    pub fn output_encoding(mut self, label: &str) -> Self {
+        self.config.encoding = Some(label.to_owned());
+        self
+    }
+
+    pub fn min_free_space(mut self, bytes: u64) -> Self {
+        self.config.min_free_space = Some(bytes);
+        self
+    }
+
+    pub fn flush_policy(mut self, policy: FlushPolicy) -> Self {
+        self.config.flush_policies.push(policy);
+        self
+    }
+
+    pub fn buffer_capacity(mut self, bytes: usize) -> Self {
+        self.config.buffer_capacity = Some(bytes);
+        self
+    }
+
+    pub fn background_writer(mut self, queue: usize) -> Self {
+        self.config.background = Some(queue);
+        self
+    }
+}
+
+// ===== Построитель логгера =====
+
+pub struct LoggerBuilder {
+    app_name: Option<String>,
+    build_info: Option<BuildInfo>,
+    file: Option<(PathBuf, String, u64, usize)>,
+    system_log: bool,
+    syslog: SyslogOptions,
+    file_mode: Option<u32>,
+    restrict_file_acl: bool,
+    multi_process: bool,
+    shared_append: Option<AppendRole>,
+    instance_id: Option<String>,
+    naming: RotationNaming,
+    rotation_policy: RotationPolicy,
+    rotate_on_open: bool,
+    open_mode: OpenMode,
+    retry_policy: RetryPolicy,
+    durability: Durability,
+    io_uring: bool,
+    archive_dir: Option<PathBuf>,
+    on_archive: Vec<ArchiveHook>,
+    archive_command: Option<ArchiveCommand>,
+    compress: bool,
+    current_link: bool,
+    source_location: bool,
+    thread_format: ThreadFormat,
+    timestamp: TimestampMode,
+    sequence_numbers: bool,
+    rotation_header: Option<String>,
+    header_on_open: bool,
+    line_ending: LineEnding,
+    utf8_bom: bool,
+    #[cfg(feature = "encoding")]
+    output_encoding: Option<String>,
+    error_file: Option<String>,
+    sinks: Vec<FileSink>,
+    custom_sinks: Vec<Arc<dyn Sink>>,
+    include_hostname: bool,
+    include_process_name: bool,
+    static_fields: Vec<(String, String)>,
+    min_free_space: Option<u64>,
+    flush_policies: Vec<FlushPolicy>,
+    buffer_capacity: Option<usize>,
+    background_writer: Option<usize>,
+    heartbeat: Option<Duration>,
+    heartbeat_system_log: bool,
+    startup_banner: bool,
+    shutdown_summary: bool,
+    control_socket: Option<PathBuf>,
+    #[cfg(feature = "http_admin")]
+    http_admin: Option<std::net::SocketAddr>,
+    verbosity_signals: bool,
+    control_events: Option<String>,
+    clock: Arc<dyn Clock>,
+    #[cfg(feature = "test-util")]
+    faults: Option<Arc<test::FaultInjector>>,
+    verbosity: Option<Verbosity>,
+    level_override: Option<LogLevel>,
+    line_format: LineFormat,
+    system_log_format: LineFormat,
+    interceptors: Vec<Interceptor>,
+    trace_source: Option<trace::TraceSource>,
+    #[cfg(feature = "regex")]
+    suppress: Vec<SuppressRule>,
+}
+
+impl Default for LoggerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LoggerBuilder {
+    pub fn new() -> Self {
+        LoggerBuilder {
+            app_name: None,
+            build_info: None,
+            file: None,
+            system_log: false,
+            syslog: SyslogOptions {
+                facility: SyslogFacility::User,
+                ident: None,
+                include_pid: true,
+                format: SyslogFormat::Rfc3164,
+            },
+            file_mode: None,
+            restrict_file_acl: false,
+            multi_process: false,
+            shared_append: None,
+            instance_id: None,
+            naming: RotationNaming::Numbered,
+            rotation_policy: RotationPolicy::Size,
+            rotate_on_open: false,
+            open_mode: OpenMode::Append,
+            retry_policy: RetryPolicy::default(),
+            durability: Durability::None,
+            io_uring: false,
+            archive_dir: None,
+            on_archive: Vec::new(),
+            archive_command: None,
+            compress: false,
+            current_link: true,
+            source_location: false,
+            thread_format: ThreadFormat::NameOrId,
+            timestamp: TimestampMode::WallClock,
+            sequence_numbers: false,
+            rotation_header: Some(RotatingWriter::DEFAULT_HEADER.to_owned()),
+            header_on_open: false,
+            line_ending: LineEnding::Lf,
+            utf8_bom: false,
+            #[cfg(feature = "encoding")]
+            output_encoding: None,
+            error_file: None,
+            sinks: Vec::new(),
+            custom_sinks: Vec::new(),
+            include_hostname: false,
+            include_process_name: false,
+            static_fields: Vec::new(),
+            min_free_space: None,
+            flush_policies: Vec::new(),
+            buffer_capacity: None,
+            background_writer: None,
+            heartbeat: None,
+            heartbeat_system_log: false,
+            startup_banner: false,
+            shutdown_summary: false,
+            control_socket: None,
+            #[cfg(feature = "http_admin")]
+            http_admin: None,
+            verbosity_signals: false,
+            control_events: None,
+            clock: system_clock(),
+            #[cfg(feature = "test-util")]
+            faults: None,
+            verbosity: None,
+            level_override: None,
+            line_format: LineFormat::Text,
+            system_log_format: LineFormat::Text,
+            interceptors: Vec::new(),
+            trace_source: None,
+            #[cfg(feature = "regex")]
+            suppress: Vec::new(),
+        }
+    }
+
+    pub fn app_name(mut self, app_name: &str) -> Self {
+        self.app_name = Some(app_name.to_owned());
+        self
+    }
+
+    /// Версия, git-хеш и дата сборки в каждой записи и в заголовке ротации.
+    /// Макрос берёт `CARGO_PKG_*` крейта, где он вызван, а хеш и дату — из переменных
+    /// `GIT_HASH` и `BUILD_DATE` при компиляции (их обычно задаёт build.rs или CI):
+    /// ```ignore
+    /// .build_info(loglib::with_build_info!())
+    /// // ... INFO PID:12345 TID:main version=1.4.2 git_hash=3f9c2ab build_date=2026-10-16 started
+    /// ```
+    pub fn build_info(mut self, info: BuildInfo) -> Self {
+        self.build_info = Some(info);
+        self
+    }
+
+    /// Основной файл: ротация при `max_size_bytes`, хранится `max_files` архивов
+    /// (0 — хранить все, удаляет их только `min_free_space`).
+    pub fn file<P: AsRef<Path>>(
+        mut self,
+        directory: P,
+        filename: &str,
+        max_size_bytes: u64,
+        max_files: usize,
+    ) -> Self {
+        self.file = Some((
+            directory.as_ref().to_path_buf(),
+            filename.to_owned(),
+            max_size_bytes,
+            max_files,
+        ));
+        self
+    }
+
+    pub fn system_log(mut self, enabled: bool) -> Self {
+        self.system_log = enabled;
+        self
+    }
+
+    /// Facility для syslog (по умолчанию `User`). Только unix.
+    pub fn syslog_facility(mut self, facility: SyslogFacility) -> Self {
+        self.syslog.facility = facility;
+        self
+    }
+
+    /// Ident в syslog. По умолчанию — `app_name`, а если он не задан, имя исполняемого файла.
+    pub fn syslog_ident(mut self, ident: &str) -> Self {
+        self.syslog.ident = Some(ident.to_owned());
+        self
+    }
+
+    /// Добавлять PID к ident: `app[1234]:` (по умолчанию да).
+    pub fn syslog_pid(mut self, enabled: bool) -> Self {
+        self.syslog.include_pid = enabled;
+        self
+    }
+
+    /// Формат syslog (по умолчанию `Rfc3164`). В `Rfc5424` поля записи и статические
+    /// поля передаются как structured data, а не текстом.
+    pub fn syslog_format(mut self, format: SyslogFormat) -> Self {
+        self.syslog.format = format;
+        self
+    }
+
+    /// Права на файлы лога в unix (например, `0o600`): и на текущий, и на каждый новый после ротации.
+    /// На других платформах игнорируется.
+    pub fn file_mode(mut self, mode: u32) -> Self {
+        self.file_mode = Some(mode);
+        self
+    }
+
+    /// На Windows оставляет в DACL файлов лога только SYSTEM, Administrators и владельца.
+    /// На других платформах игнорируется.
+    pub fn restrict_file_acl(mut self, restrict: bool) -> Self {
+        self.restrict_file_acl = restrict;
+        self
+    }
+
+    /// Режим для нескольких процессов, пишущих в один файл: ротация сериализуется
+    /// через файл-блокировку `<имя>.lock` (flock / LockFileEx), строки пишутся в режиме append.
+    /// Только с `RotationNaming::Numbered`.
+    pub fn multi_process(mut self, enabled: bool) -> Self {
+        self.multi_process = enabled;
+        self
+    }
+
+    /// Несколько процессов дописывают один файл без блокировок: каждая запись уходит
+    /// одним `write()` в файл, открытый на дозапись, и не длиннее 4 КиБ (длинное
+    /// сообщение обрезается). Ротирует только процесс с `AppendRole::Rotator`, остальные
+    /// (`Writer`) перед каждой записью проверяют, не заменён ли файл, и переходят на новый.
+    /// Только `Numbered` и ротация по размеру; `multi_process` не нужен.
+    pub fn shared_append(mut self, role: AppendRole) -> Self {
+        self.shared_append = Some(role);
+        self
+    }
+
+    /// Отдельный файл на каждый процесс: к имени добавляется PID (`app.log` → `app.1234.log`).
+    /// Ротация тоже идёт по файлам своего экземпляра: `app.1234.log.1`, `app.1234.log.2`, ...
+    pub fn per_process_file(mut self, enabled: bool) -> Self {
+        self.instance_id = if enabled {
+            Some(process::id().to_string())
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Как `per_process_file`, но вместо PID используется заданный идентификатор
+    /// (`instance_id("worker-3")` → `app.worker-3.log`).
+    pub fn instance_id(mut self, id: &str) -> Self {
+        self.instance_id = Some(id.to_owned());
+        self
+    }
+
+    /// Схема имён файлов: `Numbered` (app.log, app.log.1, ...) или
+    /// `Timestamped` (app.20250405-143022-123.log, ...).
+    pub fn rotation_naming(mut self, naming: RotationNaming) -> Self {
+        self.naming = naming;
+        self
+    }
+
+    /// Когда ротировать основной файл: по размеру (по умолчанию) или по числу записей —
+    /// `RotationPolicy::Lines(10_000)` для разборщиков, которым нужны файлы
+    /// фиксированной длины. В режиме `multi_process` записи считаются каждым процессом
+    /// отдельно, а перед ротацией — по строкам самого файла.
+    pub fn rotation_policy(mut self, policy: RotationPolicy) -> Self {
+        self.rotation_policy = policy;
+        self
+    }
+
+    /// При `Timestamped` поддерживать ссылку `app.log` на активный файл (по умолчанию включено).
+    /// Если создать символическую ссылку нельзя (Windows без привилегий), пишется
+    /// файл-указатель `app.log.latest` с именем активного файла.
+    pub fn current_link(mut self, enabled: bool) -> Self {
+        self.current_link = enabled;
+        self
+    }
+
+    /// Начинать новый файл при каждом запуске: непустой файл прошлого запуска сразу
+    /// уходит в архив, и один файл соответствует одному запуску сервиса.
+    /// Не сочетайте с `multi_process`: каждый процесс отправит в архив файл остальных.
+    pub fn rotate_on_open(mut self, enabled: bool) -> Self {
+        self.rotate_on_open = enabled;
+        self
+    }
+
+    /// Что делать с активным файлом, оставшимся от прошлого запуска: дописывать
+    /// (`OpenMode::Append`, по умолчанию), очистить (`Truncate`) или отказаться
+    /// запускаться (`CreateNew`). Действует только при запуске: после ротации новый
+    /// файл всегда пуст. Архивы не затрагиваются.
+    pub fn open_mode(mut self, mode: OpenMode) -> Self {
+        self.open_mode = mode;
+        self
+    }
+
+    /// Что делать, если файл не открывается: при ротации записи копятся в памяти
+    /// (`buffer_size`, по умолчанию 1 МиБ) и дописываются, когда файл откроется; попытки —
+    /// при записях, с паузой от `initial_delay` до `max_delay`. `startup_timeout` — сколько
+    /// `build()` ждёт файл при запуске. Накопленное видно в `LogStats::buffered_records`.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Надёжность записи для журналов аудита: `FsyncEveryRecord` — каждая запись на
+    /// диске до возврата из вызова, `FsyncInterval` — fsync раз в интервал,
+    /// `FlushOnly` — без fsync, но каждая запись сразу уходит в ОС. При fsync-режимах
+    /// файл синхронизируется и перед ротацией, и при `shutdown`.
+    pub fn durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Писать файл через io_uring (фича `io_uring`, Linux): сброшенный буфер уходит в
+    /// ядро без ожидания записи, завершения собираются позже, а `flush` ждёт все.
+    /// Выигрыш — при буферизованной политике сброса (`EveryN`, `Interval`) и большом
+    /// потоке. Если ядро не поддерживает io_uring или он запрещён, логгер сообщает в
+    /// системный лог и пишет обычным способом. Несовместимо с `multi_process` и
+    /// `shared_append`.
+    #[cfg(feature = "io_uring")]
+    pub fn io_uring(mut self, enabled: bool) -> Self {
+        self.io_uring = enabled;
+        self
+    }
+
+    /// Каталог для архивов: активный файл остаётся в каталоге лога, а при ротации
+    /// уезжает сюда (на другой том — копированием). Относительный путь отсчитывается
+    /// от каталога лога: `.archive_dir("archive")` → `logs/archive/`.
+    /// Читать такие логи — `reader::LogReader::open_archived`.
+    pub fn archive_dir<P: AsRef<Path>>(mut self, directory: P) -> Self {
+        self.archive_dir = Some(directory.as_ref().to_path_buf());
+        self
+    }
+
+    /// Вызывать `hook` с путём каждого архива основного файла и файла ошибок, когда
+    /// архив уже на месте (и сжат, если включено `compress`). Хуки вызываются по порядку
+    /// добавления в фоновом потоке обслуживания и задерживают только следующие архивы.
+    pub fn on_archive<F: Fn(&Path) + Send + Sync + 'static>(mut self, hook: F) -> Self {
+        self.on_archive.push(Arc::new(hook));
+        self
+    }
+
+    /// Отправлять архивы в S3-совместимое хранилище (фича `s3`). Архив сразу
+    /// переносится в очередь выгрузчика и в ротации больше не участвует.
+    #[cfg(feature = "s3")]
+    pub fn upload(self, uploader: s3::S3Uploader) -> Self {
+        uploader.start();
+        self.on_archive(move |path| uploader.enqueue(path))
+    }
+
+    /// Запускать команду после каждой ротации, передав путь архива последним
+    /// аргументом: `.archive_command(&["/usr/local/bin/ship-log", "--gzip"], Duration::from_secs(120))`.
+    /// Команда работает в потоке обслуживания и не задерживает запись; не уложившийся
+    /// в `timeout` процесс завершается. Ошибка запуска, ненулевой код выхода и таймаут
+    /// пишутся в системный лог и в `LogStats::last_error`. Пустой список отключает команду.
+    pub fn archive_command<S: AsRef<str>>(mut self, command: &[S], timeout: Duration) -> Self {
+        self.archive_command = ArchiveCommand::new(command, timeout);
+        self
+    }
+
+    /// Сжимать архивы gzip (фича `gzip`): `app.log.1.gz`, `app.<метка>.log.gz`.
+    /// Сжатие, как и сдвиг номеров, перенос в `archive_dir`, выгрузка и команда
+    /// после ротации, выполняется фоновым потоком обслуживания, а не потоком записи;
+    /// очередь видна в `LogStats::maintenance_backlog`. `LogReader` читает сжатые
+    /// архивы, если фича включена.
+    #[cfg(feature = "gzip")]
+    pub fn compress(mut self, enabled: bool) -> Self {
+        self.compress = enabled;
+        self
+    }
+
+    /// Следить за свободным местом на томе с логами. Если свободно меньше `bytes`,
+    /// логгер удаляет самые старые архивы, перестаёт писать Trace/Debug и один раз
+    /// сообщает об этом в системный лог. Режим снимается, когда место освободится.
+    pub fn min_free_space(mut self, bytes: u64) -> Self {
+        self.min_free_space = Some(bytes);
+        self
+    }
+
+    /// Писать в строку место вызова макроса: `... TID:.. src/net/client.rs:142 сообщение`.
+    pub fn source_location(mut self, enabled: bool) -> Self {
+        self.source_location = enabled;
+        self
+    }
+
+    /// Формат поля потока: имя или номер (по умолчанию), только номер, либо без поля.
+    pub fn thread_format(mut self, format: ThreadFormat) -> Self {
+        self.thread_format = format;
+        self
+    }
+
+    /// Дополнительный файл только для Error и Fatal (например, `errors.log`) рядом с основным.
+    /// Основной лог по-прежнему получает все записи; размер и число архивов — как у основного.
+    pub fn error_file(mut self, filename: &str) -> Self {
+        self.error_file = Some(filename.to_owned());
+        self
+    }
+
+    /// Ещё один файл со своими настройками, например JSON-лог в каталоге сборщика
+    /// рядом с обычным. Каждая запись пишется во все файлы логгера.
+    pub fn add_file(mut self, sink: FileSink) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Маршрут по категории: записи с подходящей категорией (`"audit.*"`, `"net::*"`)
+    /// пишутся в этот файл вместо основного. Категория — `Record::target()`, по умолчанию
+    /// модуль вызова макроса. Остальные записи идут в основной файл как обычно.
+    pub fn route(mut self, pattern: &str, sink: FileSink) -> Self {
+        let mut sink = sink;
+        sink.config.route = Some(pattern.to_owned());
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Перехватчик записей: вызывается до файлов, системного лога и приёмников.
+    /// Может дополнить запись полями, изменить её (например, понизить шумную ошибку
+    /// до Warning) или отбросить, вернув `None`. Перехватчики выполняются по порядку
+    /// добавления; после них уровень записи проверяется ещё раз.
+    /// ```ignore
+    /// .intercept(|r| Some(r.with_field("region", &region())))
+    /// .intercept(|mut r| {
+    ///     if r.target.starts_with("hyper") && r.level == LogLevel::Error {
+    ///         r.level = LogLevel::Warning;
+    ///     }
+    ///     Some(r)
+    /// })
+    /// ```
+    pub fn intercept<F>(mut self, interceptor: F) -> Self
+    where
+        F: Fn(OwnedRecord) -> Option<OwnedRecord> + Send + Sync + 'static,
+    {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    /// Источник активного спана для полей `trace_id` и `span_id` (в ECS — `trace.id` и
+    /// `span.id`). Вызывается для каждой записи, прошедшей уровень; `None` — спана нет,
+    /// тогда берётся область `TraceContext::enter`. С OpenTelemetry:
+    /// ```ignore
+    /// .trace_context(|| {
+    ///     let context = opentelemetry::Context::current();
+    ///     let span = context.span();
+    ///     let span = span.span_context();
+    ///     span.is_valid()
+    ///         .then(|| TraceContext::new(span.trace_id().to_bytes(), span.span_id().to_bytes()))
+    /// })
+    /// ```
+    pub fn trace_context<F>(mut self, source: F) -> Self
+    where
+        F: Fn() -> Option<TraceContext> + Send + Sync + 'static,
+    {
+        self.trace_source = Some(Arc::new(source));
+        self
+    }
+
+    /// Отбрасывать или понижать записи, сообщение которых совпало с регулярным
+    /// выражением (фича `regex`). Правила проверяются по порядку до перехватчиков;
+    /// неверный шаблон — ошибка `build()`.
+    #[cfg(feature = "regex")]
+    pub fn suppress(mut self, rule: SuppressRule) -> Self {
+        self.suppress.push(rule);
+        self
+    }
+
+    /// Ещё один приёмник записей, например `smtp::SmtpSink`.
+    pub fn add_sink<S: Sink + 'static>(mut self, sink: S) -> Self {
+        self.custom_sinks.push(Arc::new(sink));
+        self
+    }
+
+    /// Приёмник, получающий только записи, для которых `filter` вернул true.
+    /// Фильтр проверяется после глобального уровня.
+    pub fn add_sink_filtered<S, F>(mut self, sink: S, filter: F) -> Self
+    where
+        S: Sink + 'static,
+        F: Fn(&Record) -> bool + Send + Sync + 'static,
+    {
+        self.custom_sinks.push(Arc::new(FilteredSink {
+            inner: sink,
+            filter: Box::new(filter),
+        }));
+        self
+    }
+
+    /// Формат строк основного файла и файла ошибок: текст (по умолчанию), JSON или
+    /// только сообщение. У файлов из `add_file` — свой формат (`FileSink::line_format`).
+    pub fn line_format(mut self, format: LineFormat) -> Self {
+        self.line_format = format;
+        self
+    }
+
+    /// Тело сообщения в системном логе. `Text` (по умолчанию) и `Message` — текст сообщения,
+    /// поля передаются по правилам `syslog_format`; `Json` — запись целиком одним JSON-объектом.
+    /// Время и PID системный лог добавляет сам.
+    pub fn system_log_format(mut self, format: LineFormat) -> Self {
+        self.system_log_format = format;
+        self
+    }
+
+    /// Время в начале строки: местное (по умолчанию), прошедшее с создания логгера или оба.
+    /// `Elapsed` удобен для профилирования запуска и на устройствах без синхронизации часов.
+    pub fn timestamp_mode(mut self, mode: TimestampMode) -> Self {
+        self.timestamp = mode;
+        self
+    }
+
+    /// Нумеровать записи логгера по порядку (`SEQ:42`), чтобы находить пропуски и перестановки.
+    pub fn sequence_numbers(mut self, enabled: bool) -> Self {
+        self.sequence_numbers = enabled;
+        self
+    }
+
+    /// Шаблон строки, которой начинается файл после ротации; `None` — без неё.
+    /// `{app}` — приложение и сборка, `{file}` — имя файла. По умолчанию
+    /// `[ROTATION] Logger restarted — {app}`. Строка пишется уровнем Info при любом
+    /// уровне логгера; при `RotationPolicy::Lines` её нет.
+    /// ```ignore
+    /// .rotation_header(Some("=== {file}: {app} ==="))
+    /// ```
+    pub fn rotation_header(mut self, template: Option<&str>) -> Self {
+        self.rotation_header = template.map(str::to_owned);
+        self
+    }
+
+    /// Писать заголовок и при запуске, в файл, открытый первым.
+    pub fn header_on_open(mut self, enabled: bool) -> Self {
+        self.header_on_open = enabled;
+        self
     }
 
-    fn format_log_line(&self, level: LogLevel, message: &str) -> String {
-        let now = Local::now();
-        let pid = process::id();
-        let thread_id = format!("{:?}", std::thread::current().id());
-        format!(
-            "[{}] {} PID:{} TID:{} {}",
-            now.format("%Y-%m-%d %H:%M:%S%.3f"),
-            level.as_str(),
-            pid,
-            thread_id,
-            message
-        )
+    /// Конец строки в файлах: `LineEnding::CrLf` — для Блокнота и разборщиков, которые
+    /// ждут `\r\n`. По умолчанию `\n`.
+    pub fn line_ending(mut self, ending: LineEnding) -> Self {
+        self.line_ending = ending;
+        self
     }
 
-    fn reopen(&self) -> io::Result<Option<File>> {
-        let path = self.dir.join(&self.basename);
-        OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&path)
-            .map(Some)
-            .map_err(|e| {
-                self.log_to_system(
-                    LogLevel::Error,
-                    &format!("Failed to reopen log file: {}", e),
-                );
-                e
-            })
+    /// Писать UTF-8 BOM в начало каждого нового файла — при создании, после ротации и
+    /// `OpenMode::Truncate` (в непустой файл BOM не добавляется). Так старые программы
+    /// Windows не принимают кириллицу за ANSI. `LogReader` BOM пропускает; для JSON-строк,
+    /// которые читают сборщики логов, он обычно не нужен.
+    pub fn utf8_bom(mut self, enabled: bool) -> Self {
+        self.utf8_bom = enabled;
+        self
     }
 
-    fn reopen_with_header(&self) -> io::Result<Option<File>> {
-        let mut file = self.reopen()?; // <-- добавили mut
+    /// Кодовая страница файлов (фича `encoding`) для программ, которые не читают UTF-8:
+    /// `"windows-1251"`, `"shift_jis"` (cp932) и другие имена WHATWG. Символы, которых нет
+    /// в кодовой странице, заменяются на `?`. Неизвестное имя — ошибка `build()`; с
+    /// `utf8_bom` не сочетается. `LogReader` такие файлы не читает — он ждёт UTF-8.
+    #[cfg(feature = "encoding")]
+    pub fn output_encoding(mut self, label: &str) -> Self {
+        self.output_encoding = Some(label.to_owned());
+        self
+    }
 
-        if let Some(ref mut f) = file {
-            let header = format!("[ROTATION] Logger restarted — {}", self.app_info);
-            let line = self.format_log_line(LogLevel::Debug, &header);
-            let _ = writeln!(f, "{}", line);
-            let _ = f.flush();
-        }
+    /// Писать в каждую строку имя хоста: `HOST:web-01`.
+    pub fn hostname(mut self, enabled: bool) -> Self {
+        self.include_hostname = enabled;
+        self
+    }
 
-        Ok(file)
+    /// Писать в каждую строку имя исполняемого файла: `PROC:server`.
+    pub fn process_name(mut self, enabled: bool) -> Self {
+        self.include_process_name = enabled;
+        self
     }
 
-    fn rotate(&self) -> io::Result<()> {
-        // Удаляем самый старый
-        let old = self
-            .dir
-            .join(format!("{}.{}", self.basename, self.max_files));
-        let _ = fs::remove_file(&old);
-
-        // Сдвигаем файлы: .3 → .4, .2 → .3, ..., .1 → .2
-        for i in (1..self.max_files).rev() {
-            let src = self.dir.join(format!("{}.{}", self.basename, i));
-            if src.exists() {
-                let dst = self.dir.join(format!("{}.{}", self.basename, i + 1));
-                let _ = fs::remove_file(&dst);
-                if let Err(e) = fs::rename(&src, &dst) {
-                    return Err(e);
-                }
-            }
-        }
+    /// Постоянное поле `key=value` в каждой строке, например `("env", "prod")`.
+    pub fn with_static_field(mut self, key: &str, value: &str) -> Self {
+        self.static_fields.push((key.to_owned(), value.to_owned()));
+        self
+    }
 
-        // Текущий файл → становится .1
-        let current = self.dir.join(&self.basename);
-        if current.exists() {
-            let dst = self.dir.join(format!("{}.1", self.basename));
-            let _ = fs::remove_file(&dst);
-            if let Err(e) = fs::rename(&current, &dst) {
-                return Err(e);
-            }
-        }
+    /// Когда сбрасывать файл на диск (по умолчанию `EveryRecord`). Повторные вызовы
+    /// добавляют условия: `.flush_policy(Interval(500 мс)).flush_policy(OnLevel(Error))` —
+    /// сброс раз в 500 мс или сразу после ошибки, остальное копится в буфере.
+    pub fn flush_policy(mut self, policy: FlushPolicy) -> Self {
+        self.flush_policies.push(policy);
+        self
+    }
 
-        Ok(())
+    /// Размер буфера файла в байтах. По умолчанию 8 КиБ, если политика сброса не
+    /// `EveryRecord`, иначе буфер не используется. Заполненный буфер сбрасывается
+    /// независимо от политики; 0 — писать каждую строку сразу.
+    pub fn buffer_capacity(mut self, bytes: usize) -> Self {
+        self.buffer_capacity = Some(bytes);
+        self
     }
 
-    fn log_to_system(&self, level: LogLevel, msg: &str) {
-        if let Some(ref logger) = self.system_logger {
-            self.log_to_system_impl(logger, level, msg);
-        }
+    /// Писать файлы в фоновом потоке: запись форматируется в потоке вызова и встаёт в
+    /// очередь, а поток `loglib-writer` забирает накопившиеся строки пачкой и отдаёт их
+    /// в файл одним `write()` — при 100 тыс. сообщений в секунду это в сотни раз меньше
+    /// системных вызовов. Политика сброса действует на пачку: при `EveryRecord` каждая
+    /// пачка сразу уходит в файл. В очереди не больше `queue` записей; полная очередь
+    /// задерживает запись. Ошибки файла видны в `stats()`, а не в `try_write`;
+    /// `flush`, `rotate` и `shutdown` сначала дописывают очередь. Не сочетается с
+    /// `shared_append`.
+    pub fn background_writer(mut self, queue: usize) -> Self {
+        self.background_writer = Some(queue);
+        self
     }
 
-    #[cfg(target_os = "linux")]
-    fn log_to_system_impl(&self, logger: &SystemLogger, level: LogLevel, msg: &str) {
-        let severity = level.to_syslog_level();
-        let _ = syslog::write(logger, severity, msg);
+    /// Раз в `interval` писать в основной файл строку Info со статистикой логгера:
+    /// записи, отброшенные записи, ошибки, размер файла и последняя ошибка.
+    /// По ней видно, что сам логгер жив, даже если приложение молчит.
+    pub fn heartbeat(mut self, interval: Duration) -> Self {
+        self.heartbeat = Some(interval);
+        self
     }
 
-    #[cfg(target_os = "windows")]
-    fn log_to_system_impl(&self, logger: &SystemLogger, level: LogLevel, msg: &str) {
-        logger.report(level.to_winlog_level(), msg);
+    /// Источник времени для всех файлов логгера. В тестах — `test::ManualClock`,
+    /// чтобы метки времени и имена файлов были предсказуемыми.
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
     }
-}
 
-// ===== Основной логгер =====
+    /// Искусственные сбои записи и ротации во всех файлах логгера, чтобы проверить
+    /// обработку ошибок и запасные пути. Только для тестов (фича `test-util`).
+    #[cfg(feature = "test-util")]
+    pub fn fault_injection(mut self, faults: Arc<test::FaultInjector>) -> Self {
+        self.faults = Some(faults);
+        self
+    }
 
-pub struct Logger {
-    rotating_writer: Option<Arc<RotatingWriter>>,
-    system_logger: Option<SystemLogger>,
-    app_name: String,
-}
+    /// Уровень логгера по числу флагов `-v`: 0 — Warning, 1 — Info, 2 — Debug, 3 и больше — Trace.
+    /// Глобальный уровень по-прежнему действует: для Trace нужен и `set_global_log_level(Trace)`.
+    /// Без вызова логгер пропускает всё, что прошло глобальный уровень.
+    pub fn verbosity(mut self, count: u8) -> Self {
+        self.verbosity
+            .get_or_insert_with(Verbosity::default)
+            .verbose = count;
+        self
+    }
 
-impl Logger {
-    pub fn system_only(app_name: &str) -> std::io::Result<Self> {
-        let system_logger = Self::init_system_logger(app_name)?;
-        Ok(Logger {
-            rotating_writer: None,
-            system_logger,
-            app_name: app_name.to_owned(),
-        })
+    /// Уровень этого логгера вместо глобального (см. `Logger::set_level_override`):
+    /// `set_global_log_level` на такой логгер не действует.
+    pub fn level_override(mut self, level: LogLevel) -> Self {
+        self.level_override = Some(level);
+        self
     }
 
-    pub fn file_only<P: AsRef<Path>>(
-        directory: P,
-        filename: &str,
-        max_size_bytes: u64,
-        max_files: usize,
-    ) -> std::io::Result<Self> {
-        let writer = Arc::new(RotatingWriter::new(
-            directory,
-            filename,
-            max_size_bytes,
-            max_files,
-            "UnknownApp",
-            None,
-        )?);
-        Ok(Logger {
-            rotating_writer: Some(writer),
-            system_logger: None,
-            app_name: "unnamed".to_owned(),
-        })
+    /// Флаг `-q`: только Error и Fatal, независимо от `verbosity`.
+    pub fn quiet(mut self, enabled: bool) -> Self {
+        self.verbosity.get_or_insert_with(Verbosity::default).quiet = enabled;
+        self
     }
 
-    pub fn file_and_system<P: AsRef<Path>>(
-        app_name: &str,
-        directory: P,
-        filename: &str,
-        max_size_bytes: u64,
-        max_files: usize,
-    ) -> std::io::Result<Self> {
-        let version = option_env!("CARGO_PKG_VERSION").unwrap_or("dev");
-        let app_info = format!("{} v{}", app_name, version);
+    /// `verbosity` и `quiet` из аргументов командной строки (см. `Verbosity::from_args`).
+    pub fn verbosity_from_args(mut self) -> Self {
+        self.verbosity = Some(Verbosity::from_args());
+        self
+    }
 
-        let system_logger = Self::init_system_logger(app_name)?;
-        let writer = Arc::new(RotatingWriter::new(
-            directory,
-            filename,
-            max_size_bytes,
-            max_files,
-            &app_info,
-            system_logger.clone(),
-        )?);
+    /// Дублировать heartbeat в системный лог (нужен `.system_log(true)`).
+    pub fn heartbeat_system_log(mut self, enabled: bool) -> Self {
+        self.heartbeat_system_log = enabled;
+        self
+    }
 
-        Ok(Logger {
-            rotating_writer: Some(writer),
-            system_logger,
-            app_name: app_name.to_owned(),
-        })
+    /// Одна запись `STARTUP` при создании логгера: ОС, хост, число процессоров, память,
+    /// путь к исполняемому файлу, командная строка и локаль. Пишется в основной файл
+    /// (без файла — в системный лог) при любом уровне. Командная строка попадает в лог
+    /// целиком — не включайте, если в аргументах бывают пароли.
+    pub fn startup_banner(mut self, enabled: bool) -> Self {
+        self.startup_banner = enabled;
+        self
     }
 
-    #[cfg(target_os = "linux")]
-    fn init_system_logger(app_name: &str) -> std::io::Result<Option<SystemLogger>> {
-        match syslog::unix(syslog::Facility::LOG_USER) {
-            Ok(logger) => Ok(Some(logger)),
-            Err(_) => Ok(None),
-        }
+    /// Итоговая запись `SHUTDOWN` при `shutdown()` (и при drop `LoggerGuard`): время
+    /// работы, число записей по уровням, ротаций и отброшенных записей. Пишется туда же
+    /// и так же, как `startup_banner`, один раз за жизнь логгера.
+    pub fn shutdown_summary(mut self, enabled: bool) -> Self {
+        self.shutdown_summary = enabled;
+        self
     }
 
-    #[cfg(target_os = "windows")]
-    fn init_system_logger(app_name: &str) -> std::io::Result<Option<SystemLogger>> {
-        Ok(Some(winlog_rs::WinEventLogger::new(app_name)))
+    /// Канал управления: Unix-сокет с правами 0600 (на Windows — именованный канал вида
+    /// `\\.\pipe\billing-log`). Команды `level debug`, `level reset`, `rotate`, `flush`,
+    /// `stats` меняют работающий логгер без перезапуска; изменения пишутся в лог.
+    /// `build()` вернёт ошибку, если канал не создаётся (например, путь занят).
+    pub fn control_socket<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.control_socket = Some(path.as_ref().to_path_buf());
+        self
     }
 
-    pub fn set_log_level(&self, level: LogLevel) {
-        set_global_log_level(level);
+    /// Встроенный HTTP-сервер управления (фича `http_admin`): `GET`/`PUT /level`,
+    /// `POST /rotate`, `POST /flush`, `GET /stats`. Аутентификации нет — слушайте
+    /// loopback или внутреннюю сеть контейнера. Порт 0 — любой свободный
+    /// (см. `Logger::http_admin_addr`).
+    #[cfg(feature = "http_admin")]
+    pub fn http_admin(mut self, addr: std::net::SocketAddr) -> Self {
+        self.http_admin = Some(addr);
+        self
     }
 
-    pub fn log(&self, args: std::fmt::Arguments) {
-        if self.rotating_writer.is_none() {
-            return;
+    /// Уровень по сигналам, как у многих демонов: `kill -USR1` — на уровень подробнее
+    /// (повторный — ещё подробнее), `kill -USR2` — уровни из настроек. Изменения пишутся
+    /// в лог. Обработчики сигналов ставятся на весь процесс и не снимаются.
+    /// На других платформах игнорируется.
+    pub fn verbosity_signals(mut self, enabled: bool) -> Self {
+        self.verbosity_signals = enabled;
+        self
+    }
+
+    /// Именованные события Windows для служб — аналог `verbosity_signals`: события
+    /// `<prefix>-rotate`, `<prefix>-debug` (на уровень подробнее) и `<prefix>-reset`
+    /// (уровни из настроек). Префикс вроде `Global\billing-log` виден из сеанса
+    /// администратора. На других платформах игнорируется.
+    pub fn control_events(mut self, prefix: &str) -> Self {
+        self.control_events = Some(prefix.to_owned());
+        self
+    }
+
+    // Настройки, выражаемые данными (см. LoggerConfig)
+    fn settings(&self) -> LoggerConfig {
+        LoggerConfig {
+            app_name: self.app_name.clone(),
+            build_info: self.build_info.clone(),
+            file: self
+                .file
+                .as_ref()
+                .map(|(directory, filename, max_size, max_files)| FileOutput {
+                    directory: directory.clone(),
+                    filename: filename.clone(),
+                    max_size: *max_size,
+                    max_files: *max_files,
+                }),
+            system_log: self.system_log,
+            syslog_facility: self.syslog.facility,
+            syslog_ident: self.syslog.ident.clone(),
+            syslog_pid: self.syslog.include_pid,
+            syslog_format: self.syslog.format,
+            file_mode: self.file_mode,
+            restrict_file_acl: self.restrict_file_acl,
+            multi_process: self.multi_process,
+            shared_append: self.shared_append,
+            per_process_file: false, // PID уже в instance_id
+            instance_id: self.instance_id.clone(),
+            rotation_naming: self.naming,
+            rotation_policy: self.rotation_policy,
+            rotate_on_open: self.rotate_on_open,
+            open_mode: self.open_mode,
+            retry_policy: self.retry_policy,
+            durability: self.durability,
+            #[cfg(feature = "io_uring")]
+            io_uring: self.io_uring,
+            archive_dir: self.archive_dir.clone(),
+            #[cfg(feature = "gzip")]
+            compress: self.compress,
+            archive_command: self
+                .archive_command
+                .as_ref()
+                .map(|command| {
+                    std::iter::once(command.program.clone())
+                        .chain(command.args.iter().cloned())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            archive_command_timeout: self
+                .archive_command
+                .as_ref()
+                .map_or(ArchiveCommand::DEFAULT_TIMEOUT, |command| command.timeout),
+            current_link: self.current_link,
+            min_free_space: self.min_free_space,
+            source_location: self.source_location,
+            thread_format: self.thread_format,
+            error_file: self.error_file.clone(),
+            timestamp_mode: self.timestamp,
+            sequence_numbers: self.sequence_numbers,
+            rotation_header: self.rotation_header.clone(),
+            header_on_open: self.header_on_open,
+            line_ending: self.line_ending,
+            utf8_bom: self.utf8_bom,
+            #[cfg(feature = "encoding")]
+            output_encoding: self.output_encoding.clone(),
+            hostname: self.include_hostname,
+            process_name: self.include_process_name,
+            static_fields: self.static_fields.iter().cloned().collect(),
+            flush_policies: self.flush_policies.clone(),
+            buffer_capacity: self.buffer_capacity,
+            background_writer: self.background_writer,
+            heartbeat: self.heartbeat,
+            heartbeat_system_log: self.heartbeat_system_log,
+            startup_banner: self.startup_banner,
+            shutdown_summary: self.shutdown_summary,
+            control_socket: self.control_socket.clone(),
+            #[cfg(feature = "http_admin")]
+            http_admin: self.http_admin,
+            verbosity_signals: self.verbosity_signals,
+            control_events: self.control_events.clone(),
+            verbosity: self.verbosity.map(|v| v.verbose),
+            quiet: self.verbosity.is_some_and(|v| v.quiet),
+            level_override: self.level_override,
+            line_format: self.line_format,
+            system_log_format: self.system_log_format,
+            #[cfg(feature = "regex")]
+            suppress: self.suppress.clone(),
         }
-        let message = format!("{}", args);
-        self.write_to_file(LogLevel::Debug, &message);
     }
 
-    pub fn platform_log(&self, level: LogLevel, message: &str) {
-        if self.system_logger.is_none() {
-            return;
+    pub fn build(self) -> io::Result<Logger> {
+        let settings = self.settings();
+        #[allow(unused_mut)]
+        let mut interceptors = self.interceptors;
+        #[cfg(feature = "regex")]
+        if !self.suppress.is_empty() {
+            interceptors.insert(0, Arc::new(suppress::interceptor(&self.suppress)?));
         }
-        if should_log(level) {
-            if let Some(ref logger) = self.system_logger {
-                self.log_to_system(logger, level, message);
+        let app_name = self.app_name.unwrap_or_else(|| "unnamed".to_owned());
+
+        let mut static_fields = self.static_fields.clone();
+        if let Some(ref info) = self.build_info {
+            static_fields.extend(info.fields());
+        }
+
+        let system_logger = if self.system_log {
+            Logger::init_system_logger(&app_name, &self.syslog, &static_fields)?
+        } else {
+            None
+        };
+
+        let stats = Arc::new(StatsCounters::default());
+        let app_info = match self.build_info {
+            Some(ref info) if app_name != "unnamed" => BuildInfo {
+                name: app_name.clone(),
+                ..info.clone()
             }
+            .to_string(),
+            Some(ref info) => info.to_string(),
+            None if app_name == "unnamed" => process_name(),
+            None => {
+                let version = option_env!("CARGO_PKG_VERSION").unwrap_or("dev");
+                format!("{} v{}", app_name, version)
+            }
+        };
+        let context = static_context(
+            self.include_hostname,
+            self.include_process_name,
+            &static_fields,
+        );
+        let context_fields = static_context_fields(
+            self.include_hostname,
+            self.include_process_name,
+            &static_fields,
+        );
+
+        if self.error_file.is_some() && self.file.is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "error_file requires a main log file",
+            ));
         }
-    }
 
-    pub fn write_to_file(&self, level: LogLevel, message: &str) {
-        if let Some(ref writer) = self.rotating_writer {
-            writer.write(level, message);
+        let maintenance = Arc::new(Maintenance::new(system_logger.clone(), stats.clone()));
+        let mut extra_writers = Vec::new();
+        let rotating_writer = match self.file {
+            Some((dir, basename, max_size, max_files)) => {
+                let basename = match self.instance_id {
+                    Some(ref id) => instance_file_name(&basename, id),
+                    None => basename,
+                };
+                let config = FileConfig {
+                    dir,
+                    basename,
+                    max_size,
+                    max_files,
+                    mode: self.file_mode,
+                    restrict_acl: self.restrict_file_acl,
+                    multi_process: self.multi_process,
+                    shared_append: self.shared_append,
+                    naming: self.naming,
+                    rotation: self.rotation_policy,
+                    rotate_on_open: self.rotate_on_open,
+                    open_mode: self.open_mode,
+                    retry: self.retry_policy,
+                    durability: self.durability,
+                    io_uring: self.io_uring,
+                    archive_dir: self.archive_dir.clone(),
+                    on_archive: self.on_archive,
+                    archive_command: self.archive_command.map(Arc::new),
+                    compress: self.compress,
+                    maintenance: Some(maintenance.clone()),
+                    current_link: self.current_link,
+                    source_location: self.source_location,
+                    thread_format: self.thread_format,
+                    min_level: LogLevel::Trace,
+                    route: None,
+                    filter: None,
+                    timestamp: self.timestamp,
+                    sequence_numbers: self.sequence_numbers,
+                    context: context.clone(),
+                    context_fields: context_fields.clone(),
+                    format: self.line_format,
+                    min_free_space: self.min_free_space,
+                    flush_policies: self.flush_policies,
+                    buffer_capacity: self.buffer_capacity,
+                    background: self.background_writer,
+                    header: self.rotation_header,
+                    header_on_open: self.header_on_open,
+                    line_ending: self.line_ending,
+                    bom: self.utf8_bom,
+                    #[cfg(feature = "encoding")]
+                    encoding: self.output_encoding.clone(),
+                    clock: self.clock.clone(),
+                    #[cfg(feature = "test-util")]
+                    faults: self.faults.clone(),
+                };
+
+                if let Some(ref error_file) = self.error_file {
+                    let mut error_config = config.clone();
+                    error_config.basename = match self.instance_id {
+                        Some(ref id) => instance_file_name(error_file, id),
+                        None => error_file.clone(),
+                    };
+                    error_config.min_level = LogLevel::Error;
+                    extra_writers.push(Arc::new(RotatingWriter::new(
+                        error_config,
+                        &app_info,
+                        system_logger.clone(),
+                        stats.clone(),
+                    )?));
+                }
+
+                Some(Arc::new(RotatingWriter::new(
+                    config,
+                    &app_info,
+                    system_logger.clone(),
+                    stats.clone(),
+                )?))
+            }
+            None => None,
+        };
+
+        for sink in self.sinks {
+            let mut config = sink.config;
+            if let Some(ref id) = self.instance_id {
+                config.basename = instance_file_name(&config.basename, id);
+            }
+            config.context = context.clone();
+            config.context_fields = context_fields.clone();
+            config.clock = self.clock.clone();
+            #[cfg(feature = "test-util")]
+            {
+                config.faults = self.faults.clone();
+            }
+            config.maintenance = Some(maintenance.clone());
+            extra_writers.push(Arc::new(RotatingWriter::new(
+                config,
+                &app_info,
+                system_logger.clone(),
+                stats.clone(),
+            )?));
         }
-    }
 
-    #[cfg(target_os = "linux")]
-    fn log_to_system(&self, logger: &SystemLogger, level: LogLevel, msg: &str) {
-        let severity = level.to_syslog_level();
-        let _ = syslog::write(logger, severity, msg);
+        for writer in rotating_writer.iter().chain(&extra_writers) {
+            spawn_background_writer(writer);
+        }
+
+        if let Some(interval) = self.heartbeat {
+            let heartbeat_system = system_logger.clone().filter(|_| self.heartbeat_system_log);
+            spawn_heartbeat(&stats, rotating_writer.as_ref(), heartbeat_system, interval);
+        }
+
+        let mut logger = Logger {
+            rotating_writer,
+            extra_writers,
+            sinks: self.custom_sinks,
+            attached: Arc::default(),
+            has_attached: AtomicBool::new(false),
+            next_sink_id: AtomicU64::new(1),
+            enrichers: RwLock::new(Vec::new()),
+            has_enrichers: AtomicBool::new(false),
+            system_logger,
+            app_name,
+            levels: Levels::new(
+                self.verbosity.map_or(LogLevel::Trace, Verbosity::level),
+                self.level_override,
+            ),
+            stats,
+            settings,
+            system_log_format: self.system_log_format,
+            interceptors,
+            trace_source: self.trace_source,
+            started: self.clock.monotonic(),
+            clock: self.clock,
+            shutdown_summary: self.shutdown_summary,
+            summary_written: AtomicBool::new(false),
+            control: None,
+            #[cfg(feature = "http_admin")]
+            http_admin: None,
+        };
+        if let Some(ref path) = self.control_socket {
+            logger.control = Some(control::Server::start(path, logger.control_target())?);
+        }
+        #[cfg(unix)]
+        if self.verbosity_signals {
+            signals::register(logger.control_target())?;
+        }
+        #[cfg(target_os = "windows")]
+        if let Some(ref prefix) = self.control_events {
+            events::watch(prefix, logger.control_target())?;
+        }
+        #[cfg(feature = "http_admin")]
+        if let Some(addr) = self.http_admin {
+            let handler = logger.admin_handler();
+            logger.http_admin = Some(admin::HttpServer::start(addr, handler)?);
+        }
+        if self.startup_banner {
+            logger.write_startup_banner();
+        }
+        Ok(logger)
     }
+}
 
-    #[cfg(target_os = "windows")]
-    fn log_to_system(&self, logger: &SystemLogger, level: LogLevel, msg: &str) {
-        logger.report(level.to_winlog_level(), msg);
+// app.log + 1234 → app.1234.log; имя без расширения: app + 1234 → app.1234
+fn instance_file_name(filename: &str, instance_id: &str) -> String {
+    let (stem, ext) = split_extension(filename);
+    format!("{}.{}{}", stem, instance_id, ext)
+}
+
+// "app.log" → ("app", ".log"); "app" → ("app", "")
+fn split_extension(filename: &str) -> (&str, &str) {
+    match filename.rfind('.') {
+        Some(pos) if pos > 0 => filename.split_at(pos),
+        _ => (filename, ""),
     }
 }
 
 // ===== Макросы =====
 
+/// `BuildInfo` крейта, где вызван макрос: `CARGO_PKG_NAME`, `CARGO_PKG_VERSION` и
+/// переменные `GIT_HASH`, `BUILD_DATE` времени компиляции (см. `LoggerBuilder::build_info`).
+#[macro_export]
+macro_rules! with_build_info {
+    () => {
+        $crate::BuildInfo {
+            name: env!("CARGO_PKG_NAME").to_owned(),
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            git_hash: option_env!("GIT_HASH").map(str::to_owned),
+            build_date: option_env!("BUILD_DATE").map(str::to_owned),
+        }
+    };
+}
+
+// Уровень проверяется до вычисления аргументов и форматирования сообщения.
+// Уровни, вырезанные фичами max_level_*, отсекаются константой и не попадают в код.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_record {
+    ($target:expr, $logger:expr, $level:expr, $($arg:tt)*) => {{
+        let level = $level;
+        if (level as usize) >= $crate::__STATIC_MIN_LEVEL {
+            let logger = &$logger;
+            if logger.enabled(level) {
+                logger.write_record(
+                    &$crate::Record::new(level, std::format_args!($($arg)*))
+                        .with_target($target)
+                        .with_location($crate::SourceLocation {
+                            file: std::file!(),
+                            line: std::line!(),
+                            module_path: std::module_path!(),
+                        }),
+                );
+            } else {
+                logger.__count_filtered();
+            }
+        }
+    }};
+}
+
+/// Пройдёт ли запись уровня `level` фильтр: `if log_enabled!(logger, LogLevel::Trace) { ... }`.
+/// Позволяет не собирать дорогие данные для отключённого уровня.
+#[macro_export]
+macro_rules! log_enabled {
+    ($logger:expr, $level:expr) => {{
+        let level = $level;
+        (level as usize) >= $crate::__STATIC_MIN_LEVEL && $logger.enabled(level)
+    }};
+}
+
+/// Запись с уровнем, вычисленным во время выполнения: `log_at!(logger, level, "...")`.
+/// Как и у остальных макросов, категорию можно задать первым аргументом:
+/// `log_at!(target: "net", logger, level, "...")`; по умолчанию это `module_path!()`.
+#[macro_export]
+macro_rules! log_at {
+    (target: $target:expr, $logger:expr, $level:expr, $($arg:tt)*) => {{
+        $crate::__log_record!($target, $logger, $level, $($arg)*);
+    }};
+    ($logger:expr, $level:expr, $($arg:tt)*) => {{
+        $crate::__log_record!(std::module_path!(), $logger, $level, $($arg)*);
+    }};
+}
+
 #[macro_export]
 macro_rules! log {
+    (target: $target:expr, $logger:expr, $($arg:tt)*) => {{
+        $crate::__log_record!($target, $logger, $crate::LogLevel::Debug, $($arg)*);
+    }};
     ($logger:expr, $($arg:tt)*) => {{
-        $logger.log(std::format_args!($($arg)*));
+        $crate::__log_record!(std::module_path!(), $logger, $crate::LogLevel::Debug, $($arg)*);
     }};
 }
 
 #[macro_export]
 macro_rules! trace {
+    (target: $target:expr, $logger:expr, $($arg:tt)*) => {{
+        $crate::__log_record!($target, $logger, $crate::LogLevel::Trace, $($arg)*);
+    }};
     ($logger:expr, $($arg:tt)*) => {{
-        $logger.write_to_file($crate::LogLevel::Trace, &format!($($arg)*));
+        $crate::__log_record!(std::module_path!(), $logger, $crate::LogLevel::Trace, $($arg)*);
     }};
 }
 #[macro_export]
 macro_rules! debug {
+    (target: $target:expr, $logger:expr, $($arg:tt)*) => {{
+        $crate::__log_record!($target, $logger, $crate::LogLevel::Debug, $($arg)*);
+    }};
     ($logger:expr, $($arg:tt)*) => {{
-        $logger.write_to_file($crate::LogLevel::Debug, &format!($($arg)*));
+        $crate::__log_record!(std::module_path!(), $logger, $crate::LogLevel::Debug, $($arg)*);
     }};
 }
 #[macro_export]
 macro_rules! info {
+    (target: $target:expr, $logger:expr, $($arg:tt)*) => {{
+        $crate::__log_record!($target, $logger, $crate::LogLevel::Info, $($arg)*);
+    }};
     ($logger:expr, $($arg:tt)*) => {{
-        $logger.write_to_file($crate::LogLevel::Info, &format!($($arg)*));
+        $crate::__log_record!(std::module_path!(), $logger, $crate::LogLevel::Info, $($arg)*);
     }};
 }
 #[macro_export]
 macro_rules! warning {
+    (target: $target:expr, $logger:expr, $($arg:tt)*) => {{
+        $crate::__log_record!($target, $logger, $crate::LogLevel::Warning, $($arg)*);
+    }};
     ($logger:expr, $($arg:tt)*) => {{
-        $logger.write_to_file($crate::LogLevel::Warning, &format!($($arg)*));
+        $crate::__log_record!(std::module_path!(), $logger, $crate::LogLevel::Warning, $($arg)*);
     }};
 }
 #[macro_export]
 macro_rules! error {
+    (target: $target:expr, $logger:expr, $($arg:tt)*) => {{
+        $crate::__log_record!($target, $logger, $crate::LogLevel::Error, $($arg)*);
+    }};
     ($logger:expr, $($arg:tt)*) => {{
-        $logger.write_to_file($crate::LogLevel::Error, &format!($($arg)*));
+        $crate::__log_record!(std::module_path!(), $logger, $crate::LogLevel::Error, $($arg)*);
     }};
 }
 #[macro_export]
 macro_rules! fatal {
+    (target: $target:expr, $logger:expr, $($arg:tt)*) => {{
+        $crate::__log_record!($target, $logger, $crate::LogLevel::Fatal, $($arg)*);
+    }};
     ($logger:expr, $($arg:tt)*) => {{
-        $logger.write_to_file($crate::LogLevel::Fatal, &format!($($arg)*));
+        $crate::__log_record!(std::module_path!(), $logger, $crate::LogLevel::Fatal, $($arg)*);
     }};
 }
 
 // ===== Глобальные макросы =====
 
+#[doc(hidden)]
 #[macro_export]
-macro_rules! glog {
-    ($($arg:tt)*) => {{
+macro_rules! __log_before_init {
+    ($target:expr, $level:expr, $($arg:tt)*) => {{
+        let level = $level;
+        if (level as usize) >= $crate::__STATIC_MIN_LEVEL && $crate::__pre_init_enabled(level) {
+            $crate::__write_before_init(
+                &$crate::Record::new(level, std::format_args!($($arg)*))
+                    .with_target($target)
+                    .with_location($crate::SourceLocation {
+                        file: std::file!(),
+                        line: std::line!(),
+                        module_path: std::module_path!(),
+                    }),
+            );
+        }
+    }};
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_global {
+    ($target:expr, $level:expr, $($arg:tt)*) => {{
         if let Some(ref logger) = *$crate::GLOBAL_LOGGER.lock().unwrap() {
-            logger.log(std::format_args!($($arg)*));
+            $crate::__log_record!($target, logger, $level, $($arg)*);
+        } else {
+            $crate::__log_before_init!($target, $level, $($arg)*);
         }
     }};
 }
+
+/// Глобальные макросы тоже принимают категорию: `ginfo!(target: "net", "...")`.
+#[macro_export]
+macro_rules! glog {
+    (target: $target:expr, $($arg:tt)*) => {{
+        $crate::__log_global!($target, $crate::LogLevel::Debug, $($arg)*);
+    }};
+    ($($arg:tt)*) => {{
+        $crate::__log_global!(std::module_path!(), $crate::LogLevel::Debug, $($arg)*);
+    }};
+}
 #[macro_export]
 macro_rules! gtrace {
+    (target: $target:expr, $($arg:tt)*) => {{
+        $crate::__log_global!($target, $crate::LogLevel::Trace, $($arg)*);
+    }};
     ($($arg:tt)*) => {{
-        if let Some(ref logger) = *$crate::GLOBAL_LOGGER.lock().unwrap() {
-            logger.write_to_file($crate::LogLevel::Trace, &format!($($arg)*));
-        }
+        $crate::__log_global!(std::module_path!(), $crate::LogLevel::Trace, $($arg)*);
     }};
 }
 #[macro_export]
 macro_rules! gdebug {
+    (target: $target:expr, $($arg:tt)*) => {{
+        $crate::__log_global!($target, $crate::LogLevel::Debug, $($arg)*);
+    }};
     ($($arg:tt)*) => {{
-        if let Some(ref logger) = *$crate::GLOBAL_LOGGER.lock().unwrap() {
-            logger.write_to_file($crate::LogLevel::Debug, &format!($($arg)*));
-        }
+        $crate::__log_global!(std::module_path!(), $crate::LogLevel::Debug, $($arg)*);
     }};
 }
 #[macro_export]
 macro_rules! ginfo {
+    (target: $target:expr, $($arg:tt)*) => {{
+        $crate::__log_global!($target, $crate::LogLevel::Info, $($arg)*);
+    }};
     ($($arg:tt)*) => {{
-        if let Some(ref logger) = *$crate::GLOBAL_LOGGER.lock().unwrap() {
-            logger.write_to_file($crate::LogLevel::Info, &format!($($arg)*));
-        }
+        $crate::__log_global!(std::module_path!(), $crate::LogLevel::Info, $($arg)*);
     }};
 }
 #[macro_export]
 macro_rules! gwarning {
+    (target: $target:expr, $($arg:tt)*) => {{
+        $crate::__log_global!($target, $crate::LogLevel::Warning, $($arg)*);
+    }};
     ($($arg:tt)*) => {{
-        if let Some(ref logger) = *$crate::GLOBAL_LOGGER.lock().unwrap() {
-            logger.write_to_file($crate::LogLevel::Warning, &format!($($arg)*));
-        }
+        $crate::__log_global!(std::module_path!(), $crate::LogLevel::Warning, $($arg)*);
     }};
 }
 #[macro_export]
 macro_rules! gerror {
+    (target: $target:expr, $($arg:tt)*) => {{
+        $crate::__log_global!($target, $crate::LogLevel::Error, $($arg)*);
+    }};
     ($($arg:tt)*) => {{
-        if let Some(ref logger) = *$crate::GLOBAL_LOGGER.lock().unwrap() {
-            logger.write_to_file($crate::LogLevel::Error, &format!($($arg)*));
-        }
+        $crate::__log_global!(std::module_path!(), $crate::LogLevel::Error, $($arg)*);
     }};
 }
 #[macro_export]
 macro_rules! gfatal {
+    (target: $target:expr, $($arg:tt)*) => {{
+        $crate::__log_global!($target, $crate::LogLevel::Fatal, $($arg)*);
+    }};
     ($($arg:tt)*) => {{
-        if let Some(ref logger) = *$crate::GLOBAL_LOGGER.lock().unwrap() {
-            logger.write_to_file($crate::LogLevel::Fatal, &format!($($arg)*));
-        }
+        $crate::__log_global!(std::module_path!(), $crate::LogLevel::Fatal, $($arg)*);
     }};
 }
 
 // ===== Глобальный логгер =====
 
-static GLOBAL_LOGGER: Lazy<std::sync::Mutex<Option<Logger>>> =
+#[doc(hidden)] // используется глобальными макросами
+pub static GLOBAL_LOGGER: Lazy<std::sync::Mutex<Option<Logger>>> =
     Lazy::new(|| std::sync::Mutex::new(None));
 
+const GLOBAL_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Номер инициализации: guard от предыдущего логгера не должен закрыть новый
+static GLOBAL_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Возвращается функциями `init_global_logger_*`. При уничтожении сбрасывает и закрывает
+/// глобальный логгер, поэтому его нужно держать до конца `main`: `let _guard = ...;`
+#[must_use = "глобальный логгер закрывается, как только guard уничтожен; сохраните его: `let _guard = ...`"]
+pub struct LoggerGuard {
+    generation: u64,
+}
+
+impl Drop for LoggerGuard {
+    fn drop(&mut self) {
+        let mut global = GLOBAL_LOGGER.lock().unwrap();
+        if GLOBAL_GENERATION.load(Ordering::SeqCst) != self.generation {
+            return;
+        }
+        if let Some(logger) = global.take() {
+            drop(global);
+            let _ = logger.shutdown(GLOBAL_SHUTDOWN_TIMEOUT);
+        }
+    }
+}
+
+// ===== Записи до инициализации =====
+
+/// Что делают глобальные макросы, пока глобальный логгер не установлен
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PreInitBehavior {
+    /// Отбрасывать записи (по умолчанию)
+    #[default]
+    Discard,
+    /// Писать в stderr
+    Stderr,
+    /// Хранить первые N записей и записать их в логгер при инициализации;
+    /// остальные отбрасываются, а их число сообщается отдельной записью
+    Buffer(usize),
+}
+
+struct PreInitState {
+    behavior: PreInitBehavior,
+    records: Vec<PreInitRecord>,
+    dropped: u64,
+}
+
+struct PreInitRecord {
+    level: LogLevel,
+    message: String,
+    target: String,
+    location: Option<SourceLocation>,
+    time: String, // когда запись сделана; в файл она попадёт позже
+}
+
+static PRE_INIT: Mutex<PreInitState> = Mutex::new(PreInitState {
+    behavior: PreInitBehavior::Discard,
+    records: Vec::new(),
+    dropped: 0,
+});
+
+// Быстрая проверка для макросов: при Discard блокировка не берётся
+static PRE_INIT_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Задаёт поведение глобальных макросов до инициализации. Вызывайте первым делом
+/// в `main`, чтобы не потерять ошибки раннего запуска. При смене режима накопленные
+/// записи отбрасываются.
+pub fn set_pre_init_behavior(behavior: PreInitBehavior) {
+    let mut state = PRE_INIT.lock().unwrap();
+    state.behavior = behavior;
+    state.records = Vec::new();
+    state.dropped = 0;
+    PRE_INIT_ACTIVE.store(behavior != PreInitBehavior::Discard, Ordering::Relaxed);
+}
+
+#[doc(hidden)] // для глобальных макросов
+pub fn __pre_init_enabled(level: LogLevel) -> bool {
+    PRE_INIT_ACTIVE.load(Ordering::Relaxed) && should_log(level)
+}
+
+#[doc(hidden)] // для глобальных макросов; вызывается под блокировкой GLOBAL_LOGGER
+pub fn __write_before_init(record: &Record) {
+    let mut state = PRE_INIT.lock().unwrap();
+    let now = Local::now().format("%Y-%m-%d %H:%M:%S%.3f");
+    match state.behavior {
+        PreInitBehavior::Discard => {}
+        PreInitBehavior::Stderr => {
+            eprintln!("[{}] {} {}", now, record.level.as_str(), record.args);
+        }
+        PreInitBehavior::Buffer(capacity) => {
+            if state.records.len() < capacity {
+                state.records.push(PreInitRecord {
+                    level: record.level,
+                    message: record.args.to_string(),
+                    target: record.target().to_owned(),
+                    location: record.location,
+                    time: now.to_string(),
+                });
+            } else {
+                state.dropped += 1;
+            }
+        }
+    }
+}
+
+// Записывает накопленное в только что установленный логгер. Вызывается под блокировкой
+// GLOBAL_LOGGER, поэтому новые записи макросов не обгоняют накопленные.
+fn replay_pre_init(logger: &Logger) {
+    let (records, dropped) = {
+        let mut state = PRE_INIT.lock().unwrap();
+        let dropped = std::mem::take(&mut state.dropped);
+        (std::mem::take(&mut state.records), dropped)
+    };
+    for buffered in &records {
+        let fields = [("logged_at", buffered.time.as_str())];
+        logger.write_record(&Record {
+            location: buffered.location,
+            ..Record::new(buffered.level, format_args!("{}", buffered.message))
+                .with_target(&buffered.target)
+                .with_fields(&fields)
+        });
+    }
+    if dropped > 0 {
+        logger.write_record(
+            &Record::new(
+                LogLevel::Warning,
+                format_args!(
+                    "{} record(s) logged before initialization were dropped: pre-init buffer is full",
+                    dropped
+                ),
+            )
+            .with_target("loglib"),
+        );
+    }
+}
+
+fn install_global_logger(logger: Logger) -> LoggerGuard {
+    let mut global = GLOBAL_LOGGER.lock().unwrap();
+    replay_pre_init(&logger);
+    *global = Some(logger);
+    let generation = GLOBAL_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    LoggerGuard { generation }
+}
+
+// Проверка и установка под одной блокировкой: из двух параллельных вызовов успешен один
+fn try_install_global_logger(logger: Logger) -> io::Result<LoggerGuard> {
+    let mut global = GLOBAL_LOGGER.lock().unwrap();
+    if global.is_some() {
+        return Err(already_initialized());
+    }
+    replay_pre_init(&logger);
+    *global = Some(logger);
+    let generation = GLOBAL_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    Ok(LoggerGuard { generation })
+}
+
+fn already_initialized() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        "global logger is already initialized",
+    )
+}
+
+// Чтобы не открывать файлы логгера, который всё равно не будет установлен
+fn ensure_not_initialized() -> io::Result<()> {
+    if is_initialized() {
+        return Err(already_initialized());
+    }
+    Ok(())
+}
+
+/// Заменяет глобальный логгер. Прежний логгер сбрасывается и закрывается до того,
+/// как новый начнёт писать: глобальные макросы на это время ждут, записи не теряются
+/// и не перемешиваются. Guard от `init_global_logger_*` закроет уже новый логгер.
+/// Возвращает ошибку закрытия прежнего логгера; новый установлен в любом случае.
+pub fn set_global_logger(logger: Logger) -> io::Result<()> {
+    let mut global = GLOBAL_LOGGER.lock().unwrap();
+    let result = match global.take() {
+        Some(previous) => previous.shutdown(GLOBAL_SHUTDOWN_TIMEOUT),
+        None => {
+            replay_pre_init(&logger);
+            Ok(())
+        }
+    };
+    *global = Some(logger);
+    result
+}
+
+/// Меняет настройки глобального логгера на ходу, например из админки сервиса:
+/// ```ignore
+/// loglib::reconfigure_global(|b| b.file("/var/log/new", "app.log", 10 << 20, 5).verbosity(2))?;
+/// ```
+/// Построитель заполнен текущими настройками (`Logger::config`); приёмники,
+/// перехватчики и дополнительные файлы нужно добавить заново. Если новый логгер собрать не удалось,
+/// остаётся прежний. Канал управления и HTTP-админку на том же адресе новый логгер забирает у прежнего.
+pub fn reconfigure_global<F>(configure: F) -> io::Result<()>
+where
+    F: FnOnce(LoggerBuilder) -> LoggerBuilder,
+{
+    let current = GLOBAL_LOGGER
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|logger| logger.settings.clone())
+        .unwrap_or_default();
+    let logger = configure(current.builder()).build()?;
+    set_global_logger(logger)
+}
+
+/// Завершает работу глобального логгера: сбрасывает файл и снимает логгер,
+/// после чего глобальные макросы ничего не пишут до новой инициализации.
+pub fn shutdown_global() -> io::Result<()> {
+    let logger = GLOBAL_LOGGER.lock().unwrap().take();
+    match logger {
+        Some(logger) => logger.shutdown(GLOBAL_SHUTDOWN_TIMEOUT),
+        None => Ok(()),
+    }
+}
+
+/// Устанавливает глобальный логгер. Уже установленный логгер заменяется без ошибки;
+/// чтобы этого не случилось, используйте `try_init_*`.
 pub fn init_global_logger_file_only(
     directory: &str,
     filename: &str,
     max_size_bytes: u64,
     max_files: usize,
-) -> std::io::Result<()> {
+) -> std::io::Result<LoggerGuard> {
     let logger = Logger::file_only(directory, filename, max_size_bytes, max_files)?;
-    *GLOBAL_LOGGER.lock().unwrap() = Some(logger);
-    Ok(())
+    Ok(install_global_logger(logger))
 }
 
-pub fn init_global_logger_system_only(app_name: &str) -> std::io::Result<()> {
+pub fn init_global_logger_system_only(app_name: &str) -> std::io::Result<LoggerGuard> {
     let logger = Logger::system_only(app_name)?;
-    *GLOBAL_LOGGER.lock().unwrap() = Some(logger);
-    Ok(())
+    Ok(install_global_logger(logger))
 }
 
 pub fn init_global_logger_file_and_system(
@@ -513,8 +6454,52 @@ pub fn init_global_logger_file_and_system(
     filename: &str,
     max_size_bytes: u64,
     max_files: usize,
-) -> std::io::Result<()> {
+) -> std::io::Result<LoggerGuard> {
     let logger = Logger::file_and_system(app_name, directory, filename, max_size_bytes, max_files)?;
-    *GLOBAL_LOGGER.lock().unwrap() = Some(logger);
-    Ok(())
+    Ok(install_global_logger(logger))
+}
+
+/// Установлен ли глобальный логгер
+pub fn is_initialized() -> bool {
+    GLOBAL_LOGGER.lock().unwrap().is_some()
+}
+
+/// Устанавливает глобальный логгер, только если он ещё не установлен; иначе
+/// ошибка `AlreadyExists`, а установленный логгер не меняется. Как `log::set_logger`:
+/// инициализацию выполняет `main`, а библиотеки только пишут через глобальные макросы.
+/// Заменить логгер намеренно можно через `set_global_logger` и `reconfigure_global`.
+pub fn try_init_global(logger: Logger) -> io::Result<LoggerGuard> {
+    try_install_global_logger(logger)
+}
+
+/// Как `init_global_logger_file_only`, но с ошибкой `AlreadyExists`, если логгер уже установлен.
+pub fn try_init_global_logger_file_only(
+    directory: &str,
+    filename: &str,
+    max_size_bytes: u64,
+    max_files: usize,
+) -> io::Result<LoggerGuard> {
+    ensure_not_initialized()?;
+    let logger = Logger::file_only(directory, filename, max_size_bytes, max_files)?;
+    try_install_global_logger(logger)
+}
+
+/// Как `init_global_logger_system_only`, но с ошибкой `AlreadyExists`, если логгер уже установлен.
+pub fn try_init_global_logger_system_only(app_name: &str) -> io::Result<LoggerGuard> {
+    ensure_not_initialized()?;
+    let logger = Logger::system_only(app_name)?;
+    try_install_global_logger(logger)
+}
+
+/// Как `init_global_logger_file_and_system`, но с ошибкой `AlreadyExists`, если логгер уже установлен.
+pub fn try_init_global_logger_file_and_system(
+    app_name: &str,
+    directory: &str,
+    filename: &str,
+    max_size_bytes: u64,
+    max_files: usize,
+) -> io::Result<LoggerGuard> {
+    ensure_not_initialized()?;
+    let logger = Logger::file_and_system(app_name, directory, filename, max_size_bytes, max_files)?;
+    try_install_global_logger(logger)
 }
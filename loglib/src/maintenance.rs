@@ -0,0 +1,261 @@
+//! Обслуживание архивов после ротации: сдвиг номеров, перенос в `archive_dir`, сжатие,
+//! удаление лишних, выгрузка и внешняя команда. В потоке записи остаётся одно
+//! переименование активного файла, остальное делает фоновый поток со своей очередью.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::{mpsc, Arc, OnceLock};
+
+#[cfg(feature = "gzip")]
+use crate::{compressed_path, COMPRESSED_SUFFIX};
+use crate::{
+    list_archived_files, move_file, ArchiveCommand, ArchiveHook, LogLevel, RotatingWriter,
+    RotationNaming, StatsCounters, SystemLogger,
+};
+
+// Что делать с закрытым файлом; одно на писатель
+pub(crate) struct ArchivePlan {
+    pub archive_dir: PathBuf,
+    pub basename: String,
+    pub max_files: usize,
+    pub naming: RotationNaming,
+    #[cfg_attr(not(feature = "gzip"), allow(dead_code))]
+    pub compress: bool,
+    pub on_archive: Vec<ArchiveHook>,
+    pub command: Option<Arc<ArchiveCommand>>,
+}
+
+impl ArchivePlan {
+    // app.log.N в каталоге архивов
+    pub fn numbered(&self, index: usize) -> PathBuf {
+        self.archive_dir
+            .join(format!("{}.{}", self.basename, index))
+    }
+
+    // Наибольший номер архива: max_files или, если хранятся все (0), последний на диске
+    pub fn last_index(&self) -> usize {
+        if self.max_files > 0 {
+            return self.max_files;
+        }
+        let prefix = format!("{}.", self.basename);
+        fs::read_dir(&self.archive_dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                let index = name.strip_prefix(&prefix)?;
+                #[cfg(feature = "gzip")]
+                let index = index.strip_suffix(COMPRESSED_SUFFIX).unwrap_or(index);
+                index.parse::<usize>().ok()
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Архив и его сжатая копия
+    fn variants(path: PathBuf) -> impl Iterator<Item = PathBuf> {
+        #[cfg(feature = "gzip")]
+        let compressed = Some(compressed_path(&path));
+        #[cfg(not(feature = "gzip"))]
+        let compressed = None;
+        std::iter::once(path).chain(compressed)
+    }
+
+    // Ставит закрытый файл на место архива; возвращает итоговый путь
+    pub fn store(&self, closed: &Path) -> io::Result<PathBuf> {
+        let stored = match self.naming {
+            RotationNaming::Numbered => {
+                let last = match self.max_files {
+                    0 => self.last_index() + 1, // хранятся все: самый старый просто сдвигается
+                    max_files => max_files,
+                };
+                // Удаляем самый старый
+                if self.max_files > 0 {
+                    for old in Self::variants(self.numbered(last)) {
+                        let _ = fs::remove_file(old);
+                    }
+                }
+                // Сдвигаем файлы: .3 → .4, .2 → .3, ..., .1 → .2
+                for i in (1..last).rev() {
+                    let sources = Self::variants(self.numbered(i));
+                    for (src, dst) in sources.zip(Self::variants(self.numbered(i + 1))) {
+                        if src.exists() {
+                            let _ = fs::remove_file(&dst);
+                            fs::rename(&src, &dst)?;
+                        }
+                    }
+                }
+                // Закрытый файл → становится .1
+                let dst = self.numbered(1);
+                for old in Self::variants(dst.clone()) {
+                    let _ = fs::remove_file(old);
+                }
+                move_file(closed, &dst)?;
+                dst
+            }
+            RotationNaming::Timestamped => {
+                let dst = match closed.file_name() {
+                    Some(name) => self.archive_dir.join(name),
+                    None => closed.to_path_buf(),
+                };
+                if dst != closed {
+                    move_file(closed, &dst)?;
+                }
+                dst
+            }
+        };
+
+        #[cfg(feature = "gzip")]
+        let stored = if self.compress {
+            compress(&stored)?
+        } else {
+            stored
+        };
+
+        if self.naming == RotationNaming::Timestamped && self.max_files > 0 {
+            self.prune_timestamped(&stored)?;
+        }
+        Ok(stored)
+    }
+
+    // Архивы не старше только что сохранённого; более новые — активный файл и
+    // ротации, которые ещё в очереди
+    fn prune_timestamped(&self, stored: &Path) -> io::Result<()> {
+        let stored_name = stored
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        let archives: Vec<String> = list_archived_files(&self.archive_dir, &self.basename)
+            .into_iter()
+            .filter(|name| stem(name) <= stem(stored_name))
+            .collect();
+        let excess = archives.len().saturating_sub(self.max_files);
+        for name in &archives[..excess] {
+            match fs::remove_file(self.archive_dir.join(name)) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    // Всё после переименования активного файла; ошибки — в системный лог
+    pub fn finish(
+        &self,
+        closed: &Path,
+        system_logger: &Option<SystemLogger>,
+        stats: &StatsCounters,
+    ) {
+        match self.store(closed) {
+            Ok(stored) => self.archived(&stored, system_logger, stats),
+            Err(e) => {
+                let msg = format!("Failed to archive {}: {}", closed.display(), e);
+                RotatingWriter::report(system_logger, stats, LogLevel::Error, &msg);
+                stats.set_last_error(msg);
+            }
+        }
+    }
+
+    pub fn archived(
+        &self,
+        stored: &Path,
+        system_logger: &Option<SystemLogger>,
+        stats: &StatsCounters,
+    ) {
+        for hook in &self.on_archive {
+            hook(stored);
+        }
+        if let Some(ref command) = self.command {
+            if let Err(e) = command.run(stored) {
+                let msg = format!(
+                    "Archive command {} failed for {}: {}",
+                    command.program,
+                    stored.display(),
+                    e
+                );
+                RotatingWriter::report(system_logger, stats, LogLevel::Error, &msg);
+                stats.set_last_error(msg);
+            }
+        }
+    }
+}
+
+// Имя без суффикса сжатия — для сравнения меток времени
+fn stem(name: &str) -> &str {
+    #[cfg(feature = "gzip")]
+    let name = name.strip_suffix(COMPRESSED_SUFFIX).unwrap_or(name);
+    name
+}
+
+// app.log.1 → app.log.1.gz; пишется во временный файл, чтобы при сбое не остался
+// обрезанный архив
+#[cfg(feature = "gzip")]
+fn compress(path: &Path) -> io::Result<PathBuf> {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    let compressed = compressed_path(path);
+    let mut partial = compressed.clone().into_os_string();
+    partial.push(".part");
+    let result = (|| {
+        let mut encoder = GzEncoder::new(fs::File::create(&partial)?, Compression::default());
+        io::copy(&mut fs::File::open(path)?, &mut encoder)?;
+        encoder.finish()?.sync_all()?;
+        fs::rename(&partial, &compressed)
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    fs::remove_file(path)?;
+    Ok(compressed)
+}
+
+type Job = (Arc<ArchivePlan>, PathBuf);
+
+// Фоновый поток обслуживания, один на логгер; запускается при первой ротации
+pub(crate) struct Maintenance {
+    sender: OnceLock<mpsc::Sender<Job>>,
+    system_logger: Option<SystemLogger>,
+    stats: Arc<StatsCounters>,
+}
+
+impl Maintenance {
+    pub fn new(system_logger: Option<SystemLogger>, stats: Arc<StatsCounters>) -> Self {
+        Maintenance {
+            sender: OnceLock::new(),
+            system_logger,
+            stats,
+        }
+    }
+
+    pub fn submit(&self, plan: Arc<ArchivePlan>, closed: PathBuf) {
+        let sender = self.sender.get_or_init(|| {
+            let (sender, receiver) = mpsc::channel::<Job>();
+            let system_logger = self.system_logger.clone();
+            let stats = Arc::clone(&self.stats);
+            let _ = std::thread::Builder::new()
+                .name("loglib-maintenance".to_owned())
+                .spawn(move || {
+                    for (plan, closed) in receiver {
+                        plan.finish(&closed, &system_logger, &stats);
+                        stats.maintenance_backlog.fetch_sub(1, Ordering::Relaxed);
+                    }
+                });
+            sender
+        });
+        self.stats
+            .maintenance_backlog
+            .fetch_add(1, Ordering::Relaxed);
+        if let Err(mpsc::SendError((plan, closed))) = sender.send((plan, closed)) {
+            // Поток не запустился — обслуживаем сами
+            plan.finish(&closed, &self.system_logger, &self.stats);
+            self.stats
+                .maintenance_backlog
+                .fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,456 @@
+//! Запись через отображённые в память сегменты (только unix) — для компонентов, которым
+//! важна задержка. Запись копируется в заранее выделенный сегмент без системного вызова;
+//! страницы сегмента принадлежат кэшу ОС, поэтому записи переживают падение процесса.
+//! Заполненный сегмент фоновый поток превращает в обычный текстовый файл с меткой
+//! времени (`app.20250405-143022-123.log`), который читает `LogReader`.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::thread::JoinHandle;
+
+use chrono::{DateTime, Local};
+
+use crate::{
+    instance_file_name, list_timestamped_files, write_thread_field, LogLevel, Record, Sink,
+    ThreadFormat, TIMESTAMP_NAME_FORMAT,
+};
+
+const SEGMENT_SUFFIX: &str = ".mmap";
+const MIN_SEGMENT_SIZE: usize = 64 * 1024;
+
+/// Приёмник, пишущий в сегменты `mmap`. Сегменты лежат рядом с логами под именами
+/// `app.log.<pid>-<n>.mmap`; следующий сегмент готовится заранее, поэтому переход
+/// на него — тоже без ожидания диска. Сегменты упавшего процесса превращаются в
+/// файлы при следующем запуске или через `finalize_segments`.
+/// ```ignore
+/// let logger = Logger::builder()
+///     .add_sink(MmapSink::new("logs", "trading.log", 64 * 1024 * 1024)?.max_files(20))
+///     .build()?;
+/// ```
+pub struct MmapSink {
+    config: SegmentConfig,
+    min_level: LogLevel,
+    active: Mutex<Active>,
+    standby: Arc<Mutex<Option<Segment>>>,
+    worker: OnceLock<mpsc::Sender<Job>>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+#[derive(Clone)]
+struct SegmentConfig {
+    dir: PathBuf,
+    basename: String,
+    segment_size: usize,
+    max_files: usize,
+    sequence: Arc<AtomicU64>,
+}
+
+impl SegmentConfig {
+    // app.log.4242-3.mmap
+    fn next_path(&self) -> PathBuf {
+        let n = self.sequence.fetch_add(1, Ordering::Relaxed);
+        self.dir.join(format!(
+            "{}.{}-{}{}",
+            self.basename,
+            std::process::id(),
+            n,
+            SEGMENT_SUFFIX
+        ))
+    }
+}
+
+struct Active {
+    segment: Segment,
+    used: usize,
+    started: DateTime<Local>, // метка для имени готового файла
+    line: Vec<u8>,            // переиспользуемый буфер строки
+}
+
+enum Job {
+    Prepare,
+    Finish(Segment, usize, DateTime<Local>),
+}
+
+impl MmapSink {
+    /// Сегменты по `segment_size` байт (не меньше 64 КиБ) в каталоге `dir`; готовые файлы
+    /// получают имена по `basename`, как при `RotationNaming::Timestamped`. Используйте
+    /// имя, которое не пишет основной файловый лог.
+    pub fn new<P: AsRef<Path>>(dir: P, basename: &str, segment_size: usize) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        finalize_segments(&dir, basename)?;
+        let config = SegmentConfig {
+            dir,
+            basename: basename.to_owned(),
+            segment_size: segment_size.max(MIN_SEGMENT_SIZE),
+            max_files: 0,
+            sequence: Arc::new(AtomicU64::new(1)),
+        };
+        let segment = Segment::create(config.next_path(), config.segment_size)?;
+        Ok(MmapSink {
+            config,
+            min_level: LogLevel::Trace,
+            active: Mutex::new(Active {
+                segment,
+                used: 0,
+                started: Local::now(),
+                line: Vec::with_capacity(256),
+            }),
+            standby: Arc::new(Mutex::new(None)),
+            worker: OnceLock::new(),
+            thread: Mutex::new(None),
+        })
+    }
+
+    /// Хранить не больше `count` готовых файлов; 0 — все (по умолчанию).
+    pub fn max_files(mut self, count: usize) -> Self {
+        self.config.max_files = count;
+        self
+    }
+
+    /// Записывать только записи не ниже `level`.
+    pub fn min_level(mut self, level: LogLevel) -> Self {
+        self.min_level = level;
+        self
+    }
+
+    // Фоновый поток запускается при первой записи и сразу готовит запасной сегмент
+    fn worker(&self) -> &mpsc::Sender<Job> {
+        self.worker.get_or_init(|| {
+            let (sender, receiver) = mpsc::channel();
+            let config = self.config.clone();
+            let standby = Arc::clone(&self.standby);
+            let handle = std::thread::Builder::new()
+                .name("loglib-mmap".to_owned())
+                .spawn(move || run_worker(config, standby, receiver));
+            *self.thread.lock().unwrap() = handle.ok();
+            let _ = sender.send(Job::Prepare);
+            sender
+        })
+    }
+
+    fn format_line(line: &mut Vec<u8>, record: &Record) -> io::Result<()> {
+        write!(
+            line,
+            "[{}] {} PID:{}",
+            record
+                .time()
+                .unwrap_or_else(Local::now)
+                .format("%Y-%m-%d %H:%M:%S%.3f"),
+            record.level().as_str(),
+            record.pid()
+        )?;
+        write_thread_field(line, ThreadFormat::NameOrId)?;
+        for (key, value) in record.fields() {
+            write!(line, " {}={}", key, value)?;
+        }
+        write!(line, " {}", record.args())?;
+        line.push(b'\n');
+        Ok(())
+    }
+
+    // Сегмент заполнен: берём запасной (или создаём, если поток не успел), а старый
+    // отдаём потоку
+    fn switch(&self, active: &mut Active) -> io::Result<()> {
+        let next = match self.standby.lock().unwrap().take() {
+            Some(segment) => segment,
+            None => Segment::create(self.config.next_path(), self.config.segment_size)?,
+        };
+        let full = std::mem::replace(&mut active.segment, next);
+        let used = std::mem::replace(&mut active.used, 0);
+        let started = std::mem::replace(&mut active.started, Local::now());
+        if let Err(mpsc::SendError(Job::Finish(segment, used, started))) =
+            self.worker().send(Job::Finish(full, used, started))
+        {
+            finish(&self.config, segment, used, started)?;
+        }
+        Ok(())
+    }
+}
+
+impl Sink for MmapSink {
+    fn write(&self, record: &Record) -> io::Result<()> {
+        if record.level() < self.min_level {
+            return Ok(());
+        }
+        self.worker();
+        let mut active = self.active.lock().unwrap();
+        let active = &mut *active;
+        active.line.clear();
+        Self::format_line(&mut active.line, record)?;
+        // Запись длиннее сегмента обрезается
+        if active.line.len() > self.config.segment_size {
+            active.line.truncate(self.config.segment_size - 1);
+            active.line.push(b'\n');
+        }
+        if active.used + active.line.len() > active.segment.len {
+            self.switch(active)?;
+        }
+        let end = active.used + active.line.len();
+        active.segment.bytes()[active.used..end].copy_from_slice(&active.line);
+        active.used = end;
+        Ok(())
+    }
+
+    /// msync записанной части: данные на диске, а не только в кэше ОС
+    fn flush(&self) -> io::Result<()> {
+        let active = self.active.lock().unwrap();
+        active.segment.sync(active.used)
+    }
+}
+
+impl Drop for MmapSink {
+    fn drop(&mut self) {
+        let active = self.active.get_mut().unwrap();
+        let segment = std::mem::replace(&mut active.segment, Segment::empty());
+        let _ = finish(&self.config, segment, active.used, active.started);
+        // Поток доделает очередь и выйдет, когда закроется канал
+        drop(self.worker.take());
+        if let Some(handle) = self.thread.get_mut().unwrap().take() {
+            let _ = handle.join();
+        }
+        if let Some(standby) = self.standby.lock().unwrap().take() {
+            standby.discard();
+        }
+    }
+}
+
+fn run_worker(
+    config: SegmentConfig,
+    standby: Arc<Mutex<Option<Segment>>>,
+    receiver: mpsc::Receiver<Job>,
+) {
+    for job in receiver {
+        if let Job::Finish(segment, used, started) = job {
+            let _ = finish(&config, segment, used, started);
+        }
+        if standby.lock().unwrap().is_none() {
+            if let Ok(segment) = Segment::create(config.next_path(), config.segment_size) {
+                *standby.lock().unwrap() = Some(segment);
+            }
+        }
+    }
+}
+
+// Сегмент → app.<метка>.log; пустой просто удаляется
+fn finish(
+    config: &SegmentConfig,
+    segment: Segment,
+    used: usize,
+    started: DateTime<Local>,
+) -> io::Result<()> {
+    if segment.path.as_os_str().is_empty() {
+        return Ok(());
+    }
+    if used == 0 {
+        segment.discard();
+        return Ok(());
+    }
+    let path = segment.path.clone();
+    segment.close(used)?;
+    publish(&path, &config.dir, &config.basename, started)?;
+    prune(config);
+    Ok(())
+}
+
+// Имя по метке; если занято (два сегмента за миллисекунду) — следующая миллисекунда
+fn publish(
+    path: &Path,
+    dir: &Path,
+    basename: &str,
+    mut started: DateTime<Local>,
+) -> io::Result<()> {
+    loop {
+        let stamp = started.format(TIMESTAMP_NAME_FORMAT).to_string();
+        let target = dir.join(instance_file_name(basename, &stamp));
+        if !target.exists() {
+            return fs::rename(path, target);
+        }
+        started += chrono::Duration::milliseconds(1);
+    }
+}
+
+fn prune(config: &SegmentConfig) {
+    if config.max_files == 0 {
+        return;
+    }
+    let files = list_timestamped_files(&config.dir, &config.basename);
+    let excess = files.len().saturating_sub(config.max_files);
+    for name in &files[..excess] {
+        let _ = fs::remove_file(config.dir.join(name));
+    }
+}
+
+/// Превращает в обычные файлы сегменты `basename`, оставшиеся от завершившихся
+/// процессов (например, после падения): обрезает незаписанный хвост и недописанную
+/// последнюю строку. Сегменты живых процессов не трогает. Возвращает число файлов.
+pub fn finalize_segments<P: AsRef<Path>>(dir: P, basename: &str) -> io::Result<usize> {
+    let dir = dir.as_ref();
+    let prefix = format!("{}.", basename);
+    let mut finalized = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        let Some(pid) = name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(SEGMENT_SUFFIX))
+            .and_then(|rest| rest.split_once('-'))
+            .and_then(|(pid, _)| pid.parse::<i32>().ok())
+        else {
+            continue;
+        };
+        if process_alive(pid) {
+            continue;
+        }
+        let path = entry.path();
+        let data = fs::read(&path)?;
+        let used = data
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |last| last + 1);
+        if used == 0 {
+            fs::remove_file(&path)?;
+            continue;
+        }
+        OpenOptions::new()
+            .write(true)
+            .open(&path)?
+            .set_len(used as u64)?;
+        let modified = entry.metadata()?.modified()?;
+        publish(&path, dir, basename, DateTime::<Local>::from(modified))?;
+        finalized += 1;
+    }
+    Ok(finalized)
+}
+
+// Свой процесс тоже считается живым: его сегменты могут быть у другого приёмника
+fn process_alive(pid: i32) -> bool {
+    if pid as u32 == std::process::id() {
+        return true;
+    }
+    let exists = unsafe { libc::kill(pid, 0) } == 0;
+    exists || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+// Файл, заранее выделенный на диске и отображённый в память
+struct Segment {
+    path: PathBuf,
+    file: Option<File>,
+    map: *mut u8,
+    len: usize,
+}
+
+// Отображение принадлежит только этому значению
+unsafe impl Send for Segment {}
+
+impl Segment {
+    fn create(path: PathBuf, len: usize) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        let mapped = Self::map(&file, len);
+        match mapped {
+            Ok(map) => Ok(Segment {
+                path,
+                file: Some(file),
+                map,
+                len,
+            }),
+            Err(e) => {
+                let _ = fs::remove_file(&path);
+                Err(e)
+            }
+        }
+    }
+
+    fn map(file: &File, len: usize) -> io::Result<*mut u8> {
+        file.set_len(len as u64)?;
+        // Блоки выделяются сразу: иначе при заполненном диске запись в память убьёт
+        // процесс сигналом SIGBUS
+        #[cfg(target_os = "linux")]
+        {
+            let rc = unsafe { libc::posix_fallocate(file.as_raw_fd(), 0, len as libc::off_t) };
+            if rc != 0 {
+                return Err(io::Error::from_raw_os_error(rc));
+            }
+        }
+        #[cfg(target_os = "linux")]
+        let flags = libc::MAP_SHARED | libc::MAP_POPULATE; // без page fault на первых записях
+        #[cfg(not(target_os = "linux"))]
+        let flags = libc::MAP_SHARED;
+        let map = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                flags,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if map == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(map as *mut u8)
+    }
+
+    // Заглушка на место активного сегмента при завершении
+    fn empty() -> Self {
+        Segment {
+            path: PathBuf::new(),
+            file: None,
+            map: std::ptr::null_mut(),
+            len: 0,
+        }
+    }
+
+    fn bytes(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.map, self.len) }
+    }
+
+    fn sync(&self, used: usize) -> io::Result<()> {
+        if self.map.is_null() || used == 0 {
+            return Ok(());
+        }
+        if unsafe { libc::msync(self.map as *mut libc::c_void, used, libc::MS_SYNC) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn unmap(&mut self) {
+        if !self.map.is_null() {
+            unsafe { libc::munmap(self.map as *mut libc::c_void, self.len) };
+            self.map = std::ptr::null_mut();
+        }
+    }
+
+    // Снимает отображение и обрезает файл до записанного
+    fn close(mut self, used: usize) -> io::Result<()> {
+        self.unmap();
+        match self.file.take() {
+            Some(file) => file.set_len(used as u64),
+            None => Ok(()),
+        }
+    }
+
+    fn discard(mut self) {
+        self.unmap();
+        self.file = None;
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl Drop for Segment {
+    fn drop(&mut self) {
+        self.unmap();
+    }
+}
@@ -0,0 +1,1072 @@
+//! Чтение логов, записанных этим крейтом, — для утилит разбора вместо самодельных регулярок.
+//! Понимает текстовый формат и JSON (`LineFormat::Json`, `LineFormat::Ecs`); файлы читаются
+//! по порядку записи: архивы от старых к новым, затем активный.
+//!
+//! ```ignore
+//! for entry in LogReader::open("/var/log/app", "app.log")? {
+//!     let entry = entry?;
+//!     if entry.record.level >= LogLevel::Error {
+//!         println!("{:?} {}", entry.time, entry.record.message);
+//!     }
+//! }
+//! ```
+//!
+//! В текстовом формате категория не пишется, а хост, процесс и статические поля не отличить
+//! от полей записи — все они попадают в `record.fields`. Сжатые архивы (`.gz`) читаются
+//! с фичей `gzip`.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Lines, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+
+use crate::{
+    list_archived_files, list_timestamped_files, open_for_reading, FileId, LogLevel, OwnedRecord,
+};
+
+/// Разобранная строка лога
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    /// Время записи; `None` для `TimestampMode::Elapsed`
+    pub time: Option<DateTime<Local>>,
+    /// Время от запуска логгера (`TimestampMode::Elapsed`/`Both`)
+    pub elapsed: Option<Duration>,
+    pub sequence: Option<u64>,
+    pub pid: Option<u32>,
+    /// Имя или номер потока
+    pub thread: Option<String>,
+    /// Файл и строка в исходниках (`source_location`)
+    pub location: Option<(String, u32)>,
+    /// Уровень, категория, сообщение и поля; `location` в нём всегда `None`
+    pub record: OwnedRecord,
+    /// Файл, из которого прочитана запись
+    pub file: PathBuf,
+}
+
+/// Итератор по записям одного или нескольких файлов лога. Продолжения многострочных
+/// сообщений склеиваются с записью через `\n`. Нераспознанная строка в начале файла
+/// даёт `Err` (`InvalidData`), после чего чтение продолжается.
+pub struct LogReader {
+    files: VecDeque<PathBuf>,
+    current: Option<(PathBuf, Lines<Box<dyn BufRead + Send>>)>,
+    line_number: usize,
+    lines: LineAssembler,
+}
+
+impl LogReader {
+    /// Все файлы лога `basename` в каталоге: архивы `app.log.N` или `app.<метка>.log`
+    /// от старых к новым, затем активный файл. Ссылка на активный файл
+    /// (`RotationNaming::Timestamped`) пропускается; сжатые архивы (`.gz`) читаются
+    /// с фичей `gzip`.
+    pub fn open<P: AsRef<Path>>(directory: P, basename: &str) -> io::Result<Self> {
+        let dir = directory.as_ref();
+        Self::open_in(dir, dir, basename)
+    }
+
+    /// Как `open`, но архивы лежат в отдельном каталоге (`LoggerBuilder::archive_dir`).
+    /// Относительный `archive_dir` отсчитывается от `directory`.
+    pub fn open_archived<P: AsRef<Path>, A: AsRef<Path>>(
+        directory: P,
+        archive_dir: A,
+        basename: &str,
+    ) -> io::Result<Self> {
+        let dir = directory.as_ref();
+        Self::open_in(dir, &dir.join(archive_dir), basename)
+    }
+
+    fn open_in(dir: &Path, archive_dir: &Path, basename: &str) -> io::Result<Self> {
+        let mut files = Vec::new();
+
+        let mut numbered: Vec<(u32, PathBuf)> = fs::read_dir(archive_dir)?
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                #[cfg(feature = "gzip")]
+                let name = match name.strip_suffix(crate::COMPRESSED_SUFFIX) {
+                    Some(plain) => plain.to_owned(),
+                    None => name,
+                };
+                let index = name
+                    .strip_prefix(basename)?
+                    .strip_prefix('.')?
+                    .parse()
+                    .ok()?;
+                Some((index, entry.path()))
+            })
+            .collect();
+        numbered.sort_by_key(|&(index, _)| std::cmp::Reverse(index));
+        files.extend(numbered.into_iter().map(|(_, path)| path));
+        files.extend(
+            list_archived_files(archive_dir, basename)
+                .into_iter()
+                .map(|name| archive_dir.join(name)),
+        );
+        if archive_dir != dir {
+            // Активный файл Timestamped остаётся рядом со ссылкой
+            files.extend(
+                list_timestamped_files(dir, basename)
+                    .into_iter()
+                    .map(|name| dir.join(name)),
+            );
+        }
+
+        let active = dir.join(basename);
+        if let Ok(meta) = fs::symlink_metadata(&active) {
+            if !meta.file_type().is_symlink() {
+                files.push(active);
+            }
+        }
+        Ok(Self::from_files(files))
+    }
+
+    /// Заданные файлы в заданном порядке
+    pub fn from_files<I, P>(files: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        LogReader {
+            files: files.into_iter().map(Into::into).collect(),
+            current: None,
+            line_number: 0,
+            lines: LineAssembler::default(),
+        }
+    }
+
+    /// Новые записи активного файла по мере их появления, как `tail -f`: чтение начинается
+    /// с конца файла, ротация и усечение файла отслеживаются. Файла может ещё не быть.
+    /// ```ignore
+    /// let mut live = LogReader::follow("/var/log/app", "app.log")?;
+    /// // в цикле интерфейса — без блокировки
+    /// while let Some(entry) = live.try_next() {
+    ///     view.push(entry?);
+    /// }
+    /// ```
+    pub fn follow<P: AsRef<Path>>(directory: P, basename: &str) -> io::Result<Follow> {
+        let mut follow = Follow {
+            path: directory.as_ref().join(basename),
+            file: None,
+            partial: Vec::new(),
+            lines: LineAssembler::default(),
+            ready: VecDeque::new(),
+            interval: Duration::from_millis(250),
+        };
+        follow.reopen(SeekFrom::End(0))?;
+        Ok(follow)
+    }
+
+    /// Файлы, которые ещё предстоит прочитать
+    pub fn remaining_files(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(PathBuf::as_path)
+    }
+
+    // Следующая строка из текущего или следующего файла; смена файла завершает запись
+    fn next_line(&mut self) -> Option<io::Result<(PathBuf, String)>> {
+        loop {
+            if let Some((ref path, ref mut lines)) = self.current {
+                match lines.next() {
+                    Some(Ok(line)) => {
+                        self.line_number += 1;
+                        return Some(Ok((path.clone(), line)));
+                    }
+                    Some(Err(e)) => return Some(Err(e)),
+                    None => self.current = None,
+                }
+            }
+            if self.lines.has_pending() {
+                return None;
+            }
+            let path = self.files.pop_front()?;
+            match open_log(&path) {
+                Ok(file) => {
+                    self.current = Some((path, file.lines()));
+                    self.line_number = 0;
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {} // удалён ротацией
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+
+    fn invalid_line(&self, line: &str) -> io::Error {
+        let file = match self.current {
+            Some((ref path, _)) => path.display().to_string(),
+            None => String::new(),
+        };
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{}:{}: unrecognized log line: {}",
+                file, self.line_number, line
+            ),
+        )
+    }
+}
+
+// Сжатые архивы (фича `gzip`) распаковываются на лету
+fn open_log(path: &Path) -> io::Result<Box<dyn BufRead + Send>> {
+    let file = open_for_reading(path)?;
+    #[cfg(feature = "gzip")]
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        return Ok(Box::new(BufReader::new(flate2::read::GzDecoder::new(file))));
+    }
+    Ok(Box::new(BufReader::new(file)))
+}
+
+impl Iterator for LogReader {
+    type Item = io::Result<LogEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (path, line) = match self.next_line() {
+                Some(Ok(line)) => line,
+                Some(Err(e)) => return Some(Err(e)),
+                None => return self.lines.finish().map(Ok),
+            };
+            match self.lines.push(&line, path) {
+                Ok(Some(entry)) => return Some(Ok(entry)),
+                Ok(None) => {}
+                Err(()) => return Some(Err(self.invalid_line(&line))),
+            }
+        }
+    }
+}
+
+// ===== Поиск =====
+
+/// Условия поиска записей; все заданные условия должны выполниться.
+/// ```ignore
+/// let query = Query::new()
+///     .min_level(LogLevel::Error)
+///     .since(Local::now() - chrono::Duration::hours(2))
+///     .contains("timeout");
+/// for entry in reader::search("/var/log/app", "app.log", &query)? {
+///     let entry = entry?;
+///     println!("{}: {}", entry.file.display(), entry.record.message);
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    min_level: Option<LogLevel>,
+    since: Option<DateTime<Local>>,
+    until: Option<DateTime<Local>>,
+    contains: Option<String>,
+    #[cfg(feature = "regex")]
+    pattern: Option<regex::Regex>,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Записи не ниже уровня
+    pub fn min_level(mut self, level: LogLevel) -> Self {
+        self.min_level = Some(level);
+        self
+    }
+
+    /// Записи не раньше `time`. Записи без времени (`TimestampMode::Elapsed`) не подходят.
+    pub fn since(mut self, time: DateTime<Local>) -> Self {
+        self.since = Some(time);
+        self
+    }
+
+    /// Записи раньше `time`
+    pub fn until(mut self, time: DateTime<Local>) -> Self {
+        self.until = Some(time);
+        self
+    }
+
+    /// Сообщение содержит подстроку
+    pub fn contains(mut self, text: &str) -> Self {
+        self.contains = Some(text.to_owned());
+        self
+    }
+
+    /// Сообщение совпадает с регулярным выражением (фича `regex`)
+    #[cfg(feature = "regex")]
+    pub fn pattern(mut self, pattern: regex::Regex) -> Self {
+        self.pattern = Some(pattern);
+        self
+    }
+
+    /// Подходит ли запись — например, для фильтрации `LogReader::follow`
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        if self.min_level.is_some_and(|min| entry.record.level < min) {
+            return false;
+        }
+        if self.since.is_some() || self.until.is_some() {
+            let Some(time) = entry.time else {
+                return false;
+            };
+            if self.since.is_some_and(|since| time < since)
+                || self.until.is_some_and(|until| time >= until)
+            {
+                return false;
+            }
+        }
+        if let Some(ref text) = self.contains {
+            if !entry.record.message.contains(text.as_str()) {
+                return false;
+            }
+        }
+        #[cfg(feature = "regex")]
+        if let Some(ref pattern) = self.pattern {
+            if !pattern.is_match(&entry.record.message) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Записи лога `basename` (архивы и активный файл, см. `LogReader::open`), подходящие
+/// под `query`; файл записи — в `LogEntry::file`. Файлы читаются лениво, а архивы,
+/// изменённые раньше `since`, пропускаются целиком. Нераспознанные строки пропускаются,
+/// ошибки чтения возвращаются.
+pub fn search<P: AsRef<Path>>(
+    directory: P,
+    basename: &str,
+    query: &Query,
+) -> io::Result<impl Iterator<Item = io::Result<LogEntry>>> {
+    let mut reader = LogReader::open(directory, basename)?;
+    if let Some(since) = query.since {
+        // Последняя запись файла не позже его изменения
+        reader.files.retain(|path| {
+            fs::metadata(path)
+                .and_then(|meta| meta.modified())
+                .map_or(true, |modified| DateTime::<Local>::from(modified) >= since)
+        });
+    }
+    let query = query.clone();
+    Ok(reader.filter(move |entry| match entry {
+        Ok(entry) => query.matches(entry),
+        Err(e) => e.kind() != io::ErrorKind::InvalidData,
+    }))
+}
+
+// ===== Сводка =====
+
+/// Параметры сводки (`summarize`)
+#[derive(Debug, Clone)]
+pub struct SummaryOptions {
+    bucket: Duration,
+    top: usize,
+    burst_threshold: usize,
+    burst_window: Duration,
+}
+
+impl Default for SummaryOptions {
+    fn default() -> Self {
+        SummaryOptions {
+            bucket: Duration::from_secs(3600),
+            top: 10,
+            burst_threshold: 10,
+            burst_window: Duration::from_secs(60),
+        }
+    }
+}
+
+impl SummaryOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Длина интервала для счётчиков по времени (по умолчанию час)
+    pub fn bucket(mut self, bucket: Duration) -> Self {
+        self.bucket = bucket.max(Duration::from_secs(1));
+        self
+    }
+
+    /// Сколько самых частых сообщений показать (по умолчанию 10)
+    pub fn top_messages(mut self, count: usize) -> Self {
+        self.top = count;
+        self
+    }
+
+    /// Всплеск — не меньше `threshold` записей уровня Error и выше за `window`
+    /// (по умолчанию 10 за минуту)
+    pub fn bursts(mut self, threshold: usize, window: Duration) -> Self {
+        self.burst_threshold = threshold.max(1);
+        self.burst_window = window;
+        self
+    }
+}
+
+/// Сводка по набору логов: для отчётов и пакетов диагностики. `Display` печатает её текстом.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LogSummary {
+    /// Записи по уровням, индекс — `LogLevel as usize`
+    pub records_by_level: [u64; 6],
+    pub first: Option<DateTime<Local>>,
+    pub last: Option<DateTime<Local>>,
+    /// Счётчики по интервалам, по возрастанию времени; пустые интервалы пропущены
+    pub buckets: Vec<SummaryBucket>,
+    /// Самые частые сообщения, по убыванию числа
+    pub top_messages: Vec<RepeatedMessage>,
+    pub error_bursts: Vec<ErrorBurst>,
+    /// Нераспознанные строки
+    pub unparsed_lines: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SummaryBucket {
+    pub start: DateTime<Local>,
+    pub records_by_level: [u64; 6],
+}
+
+/// Сообщения, совпадающие с точностью до чисел: `db timeout #` для `db timeout 37`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatedMessage {
+    pub pattern: String,
+    pub count: u64,
+    /// Самый высокий уровень среди этих записей
+    pub level: LogLevel,
+    /// Первое сообщение как есть
+    pub example: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorBurst {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub count: usize,
+}
+
+impl LogSummary {
+    /// Сводка по уже прочитанным записям (например, из `search`)
+    pub fn from_entries<I>(entries: I, options: &SummaryOptions) -> Self
+    where
+        I: IntoIterator<Item = LogEntry>,
+    {
+        let mut summary = LogSummary::default();
+        let bucket_secs = options.bucket.as_secs() as i64;
+        let mut buckets: BTreeMap<i64, [u64; 6]> = BTreeMap::new();
+        let mut messages: HashMap<String, RepeatedMessage> = HashMap::new();
+        let mut error_times = Vec::new();
+
+        for entry in entries {
+            let level = entry.record.level as usize;
+            if let Some(count) = summary.records_by_level.get_mut(level) {
+                *count += 1;
+            }
+            if let Some(time) = entry.time {
+                summary.first = Some(summary.first.map_or(time, |first| first.min(time)));
+                summary.last = Some(summary.last.map_or(time, |last| last.max(time)));
+                let start = time.timestamp().div_euclid(bucket_secs) * bucket_secs;
+                if let Some(count) = buckets.entry(start).or_default().get_mut(level) {
+                    *count += 1;
+                }
+                if entry.record.level >= LogLevel::Error {
+                    error_times.push(time);
+                }
+            }
+            let pattern = message_pattern(&entry.record.message);
+            let repeated = messages
+                .entry(pattern)
+                .or_insert_with_key(|pattern| RepeatedMessage {
+                    pattern: pattern.clone(),
+                    count: 0,
+                    level: entry.record.level,
+                    example: entry.record.message.clone(),
+                });
+            repeated.count += 1;
+            repeated.level = repeated.level.max(entry.record.level);
+        }
+
+        summary.buckets = buckets
+            .into_iter()
+            .filter_map(|(start, records_by_level)| {
+                Some(SummaryBucket {
+                    start: Local.timestamp_opt(start, 0).single()?,
+                    records_by_level,
+                })
+            })
+            .collect();
+        let mut top: Vec<RepeatedMessage> = messages.into_values().collect();
+        top.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.pattern.cmp(&b.pattern))
+        });
+        top.truncate(options.top);
+        summary.top_messages = top;
+        error_times.sort();
+        summary.error_bursts = find_bursts(&error_times, options);
+        summary
+    }
+
+    pub fn records(&self, level: LogLevel) -> u64 {
+        self.records_by_level
+            .get(level as usize)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn total_records(&self) -> u64 {
+        self.records_by_level.iter().sum()
+    }
+}
+
+/// Сводка по всем файлам лога `basename` (см. `LogReader::open`)
+pub fn summarize<P: AsRef<Path>>(
+    directory: P,
+    basename: &str,
+    options: &SummaryOptions,
+) -> io::Result<LogSummary> {
+    let mut reader = LogReader::open(directory, basename)?;
+    let mut unparsed = 0;
+    let mut failure = None;
+    // Записи идут в сводку по одной: весь набор файлов в память не читается
+    let entries = std::iter::from_fn(|| loop {
+        match reader.next()? {
+            Ok(entry) => return Some(entry),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => unparsed += 1,
+            Err(e) => {
+                failure = Some(e);
+                return None;
+            }
+        }
+    });
+    let mut summary = LogSummary::from_entries(entries, options);
+    if let Some(e) = failure {
+        return Err(e);
+    }
+    summary.unparsed_lines = unparsed;
+    Ok(summary)
+}
+
+// Числа в сообщении заменяются на #, чтобы `timeout 37` и `timeout 74` считались вместе
+fn message_pattern(message: &str) -> String {
+    let mut pattern = String::with_capacity(message.len());
+    let mut in_number = false;
+    for c in message.chars() {
+        if c.is_ascii_digit() {
+            if !in_number {
+                pattern.push('#');
+            }
+            in_number = true;
+        } else {
+            pattern.push(c);
+            in_number = false;
+        }
+    }
+    pattern
+}
+
+// Скользящее окно по временам ошибок; пересекающиеся всплески сливаются в один
+fn find_bursts(times: &[DateTime<Local>], options: &SummaryOptions) -> Vec<ErrorBurst> {
+    let window = chrono::Duration::from_std(options.burst_window).unwrap_or(chrono::Duration::MAX);
+    let mut bursts: Vec<(usize, usize)> = Vec::new();
+    let mut start = 0;
+    for (i, &time) in times.iter().enumerate() {
+        while time - times[start] > window {
+            start += 1;
+        }
+        if i + 1 - start >= options.burst_threshold {
+            match bursts.last_mut() {
+                Some(burst) if start <= burst.1 => burst.1 = i,
+                _ => bursts.push((start, i)),
+            }
+        }
+    }
+    bursts
+        .into_iter()
+        .map(|(first, last)| ErrorBurst {
+            start: times[first],
+            end: times[last],
+            count: last - first + 1,
+        })
+        .collect()
+}
+
+fn write_level_counts(f: &mut fmt::Formatter, counts: &[u64; 6]) -> fmt::Result {
+    for (level, &count) in LogLevel::ALL.iter().zip(counts) {
+        if count > 0 {
+            write!(f, " {} {}", level.as_str(), count)?;
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for LogSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const TIME: &str = "%Y-%m-%d %H:%M:%S";
+        write!(f, "Records: {}", self.total_records())?;
+        write_level_counts(f, &self.records_by_level)?;
+        writeln!(f)?;
+        if let (Some(first), Some(last)) = (self.first, self.last) {
+            writeln!(f, "Period: {} — {}", first.format(TIME), last.format(TIME))?;
+        }
+        if self.unparsed_lines > 0 {
+            writeln!(f, "Unparsed lines: {}", self.unparsed_lines)?;
+        }
+        if !self.buckets.is_empty() {
+            writeln!(f, "\nBy time:")?;
+            for bucket in &self.buckets {
+                write!(f, "  {}", bucket.start.format("%Y-%m-%d %H:%M"))?;
+                write_level_counts(f, &bucket.records_by_level)?;
+                writeln!(f)?;
+            }
+        }
+        if !self.top_messages.is_empty() {
+            writeln!(f, "\nTop messages:")?;
+            for message in &self.top_messages {
+                writeln!(
+                    f,
+                    "  {:>7} {} {}",
+                    message.count,
+                    message.level.as_str(),
+                    message.pattern
+                )?;
+            }
+        }
+        if !self.error_bursts.is_empty() {
+            writeln!(f, "\nError bursts:")?;
+            for burst in &self.error_bursts {
+                writeln!(
+                    f,
+                    "  {} — {}: {} records",
+                    burst.start.format(TIME),
+                    burst.end.format(TIME),
+                    burst.count
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Живое чтение активного файла (`LogReader::follow`). `try_next` не блокирует;
+/// `Iterator::next` ждёт новых записей, проверяя файл раз в `interval`, и не заканчивается.
+pub struct Follow {
+    path: PathBuf,
+    file: Option<(File, FileId)>,
+    partial: Vec<u8>, // начало строки, конец которой ещё не записан
+    lines: LineAssembler,
+    ready: VecDeque<io::Result<LogEntry>>,
+    interval: Duration,
+}
+
+impl Follow {
+    /// Как часто проверять файл в блокирующем `next` (по умолчанию 250 мс)
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Следующая новая запись или `None`, если пока ничего не записано.
+    pub fn try_next(&mut self) -> Option<io::Result<LogEntry>> {
+        if self.ready.is_empty() {
+            if let Err(e) = self.poll() {
+                return Some(Err(e));
+            }
+        }
+        self.ready.pop_front()
+    }
+
+    fn poll(&mut self) -> io::Result<()> {
+        self.read_available()?;
+        match fs::metadata(&self.path) {
+            Ok(meta) => {
+                // Ротация (по пути другой файл) или усечение на месте (copytruncate):
+                // новый файл читается с начала
+                let replaced = match self.file {
+                    Some((ref mut file, ref id)) => {
+                        FileId::at(&self.path).ok().as_ref() != Some(id)
+                            || meta.len() < file.stream_position()?
+                    }
+                    None => true,
+                };
+                if replaced {
+                    self.read_available()?; // дописанное в старый файл перед ротацией
+                    self.flush_partial();
+                    self.reopen(SeekFrom::Start(0))?;
+                    self.read_available()?;
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        // Логгер пишет запись целиком, поэтому дочитанная запись уже закончена
+        if self.partial.is_empty() {
+            self.ready.extend(self.lines.finish().map(Ok));
+        }
+        Ok(())
+    }
+
+    fn reopen(&mut self, from: SeekFrom) -> io::Result<()> {
+        self.file = match open_for_reading(&self.path) {
+            Ok(mut file) => {
+                let id = FileId::of(&file)?;
+                file.seek(from)?;
+                Some((file, id))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        Ok(())
+    }
+
+    fn read_available(&mut self) -> io::Result<()> {
+        let Some((ref mut file, _)) = self.file else {
+            return Ok(());
+        };
+        let start = self.partial.len();
+        file.read_to_end(&mut self.partial)?;
+        if self.partial.len() == start {
+            return Ok(());
+        }
+        let complete = match self.partial.iter().rposition(|&b| b == b'\n') {
+            Some(pos) => pos + 1,
+            None => return Ok(()),
+        };
+        let chunk: Vec<u8> = self.partial.drain(..complete).collect();
+        for line in String::from_utf8_lossy(&chunk).lines() {
+            self.push_line(line);
+        }
+        Ok(())
+    }
+
+    fn flush_partial(&mut self) {
+        if !self.partial.is_empty() {
+            let line = String::from_utf8_lossy(&self.partial).into_owned();
+            self.partial.clear();
+            self.push_line(&line);
+        }
+        self.ready.extend(self.lines.finish().map(Ok));
+    }
+
+    fn push_line(&mut self, line: &str) {
+        match self.lines.push(line, self.path.clone()) {
+            Ok(entry) => self.ready.extend(entry.map(Ok)),
+            Err(()) => self.ready.push_back(Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: unrecognized log line: {}", self.path.display(), line),
+            ))),
+        }
+    }
+}
+
+impl Iterator for Follow {
+    type Item = io::Result<LogEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.try_next() {
+                return Some(entry);
+            }
+            std::thread::sleep(self.interval);
+        }
+    }
+}
+
+// Собирает записи из строк: запись готова, когда начинается следующая
+#[derive(Default)]
+struct LineAssembler {
+    pending: Option<LogEntry>,
+}
+
+impl LineAssembler {
+    // Err — строка не похожа ни на запись, ни на продолжение
+    fn push(&mut self, line: &str, file: PathBuf) -> Result<Option<LogEntry>, ()> {
+        // BOM в начале файла (LoggerBuilder::utf8_bom)
+        let line = line.strip_prefix('\u{feff}').unwrap_or(line);
+        let parsed = match line.chars().next() {
+            Some('[') => parse_text_line(line, file),
+            Some('{') => parse_json_line(line, file),
+            _ => None,
+        };
+        if let Some(entry) = parsed {
+            return Ok(self.pending.replace(entry));
+        }
+        match self.pending {
+            // Продолжение многострочного сообщения
+            Some(ref mut entry) => {
+                entry.record.message.push('\n');
+                entry.record.message.push_str(line);
+                Ok(None)
+            }
+            None if line.is_empty() => Ok(None),
+            None => Err(()),
+        }
+    }
+
+    fn has_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    fn finish(&mut self) -> Option<LogEntry> {
+        self.pending.take()
+    }
+}
+
+fn empty_entry(file: PathBuf) -> LogEntry {
+    LogEntry {
+        time: None,
+        elapsed: None,
+        sequence: None,
+        pid: None,
+        thread: None,
+        location: None,
+        record: OwnedRecord {
+            level: LogLevel::Info,
+            target: String::new(),
+            message: String::new(),
+            fields: Vec::new(),
+            location: None,
+        },
+        file,
+    }
+}
+
+// ===== Текстовый формат =====
+
+// [2025-04-05 14:30:22.123] [+00:01:02.345] INFO SEQ:7 PID:42 TID:main k=v file.rs:10 сообщение
+fn parse_text_line(line: &str, file: PathBuf) -> Option<LogEntry> {
+    let mut entry = empty_entry(file);
+    let mut rest = line;
+
+    if !rest.starts_with("[+") {
+        let end = rest.find(']')?;
+        let time = NaiveDateTime::parse_from_str(&rest[1..end], "%Y-%m-%d %H:%M:%S%.3f").ok()?;
+        entry.time = Local.from_local_datetime(&time).earliest();
+        rest = rest[end + 1..]
+            .strip_prefix(' ')
+            .unwrap_or(&rest[end + 1..]);
+    }
+    if let Some(elapsed) = rest.strip_prefix("[+") {
+        let end = elapsed.find(']')?;
+        entry.elapsed = Some(parse_elapsed(&elapsed[..end])?);
+        rest = elapsed[end + 1..].strip_prefix(' ')?;
+    }
+
+    let (level, tail) = rest.split_once(' ').unwrap_or((rest, ""));
+    entry.record.level = level.parse().ok()?;
+    rest = tail;
+
+    if let Some(tail) = rest.strip_prefix("SEQ:") {
+        let (seq, tail) = tail.split_once(' ').unwrap_or((tail, ""));
+        entry.sequence = Some(seq.parse().ok()?);
+        rest = tail;
+    }
+    let tail = rest.strip_prefix("PID:")?;
+    let (pid, tail) = tail.split_once(' ').unwrap_or((tail, ""));
+    entry.pid = Some(pid.parse().ok()?);
+    rest = tail;
+    if let Some(tail) = rest.strip_prefix("TID:") {
+        let (thread, tail) = tail.split_once(' ').unwrap_or((tail, ""));
+        entry.thread = Some(thread.to_owned());
+        rest = tail;
+    }
+
+    // Поля `ключ=значение` до первого слова без `=`, затем необязательное file:line
+    loop {
+        let (word, tail) = rest.split_once(' ').unwrap_or((rest, ""));
+        match word.split_once('=') {
+            Some((key, value)) if !key.is_empty() => {
+                entry.record.fields.push((key.to_owned(), value.to_owned()));
+                rest = tail;
+            }
+            _ => break,
+        }
+    }
+    let (word, tail) = rest.split_once(' ').unwrap_or((rest, ""));
+    if let Some((file, line)) = word.rsplit_once(':') {
+        if file.ends_with(".rs") {
+            if let Ok(line) = line.parse() {
+                entry.location = Some((file.to_owned(), line));
+                rest = tail;
+            }
+        }
+    }
+    entry.record.message = rest.to_owned();
+    Some(entry)
+}
+
+// 01:02:03.456
+fn parse_elapsed(s: &str) -> Option<Duration> {
+    let mut parts = s.splitn(3, ':');
+    let hours: u64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let (secs, millis) = parts.next()?.split_once('.')?;
+    let secs: u64 = secs.parse().ok()?;
+    let millis: u64 = millis.parse().ok()?;
+    Some(Duration::from_millis(
+        ((hours * 60 + minutes) * 60 + secs) * 1000 + millis,
+    ))
+}
+
+// ===== JSON =====
+
+fn parse_json_line(line: &str, file: PathBuf) -> Option<LogEntry> {
+    let mut entry = empty_entry(file);
+    let mut level = None;
+    let mut location_file = None;
+    let mut location_line = None;
+    for (key, value) in JsonParser::new(line).object()? {
+        match (key.as_str(), value) {
+            ("time" | "@timestamp", JsonValue::Str(time)) => {
+                let time = DateTime::parse_from_rfc3339(&time).ok()?;
+                entry.time = Some(time.with_timezone(&Local));
+            }
+            ("elapsed", JsonValue::Number(secs)) => {
+                entry.elapsed = Duration::try_from_secs_f64(secs.parse().ok()?).ok();
+            }
+            ("level" | "log.level", JsonValue::Str(name)) => level = Some(name.parse().ok()?),
+            ("seq" | "event.sequence", JsonValue::Number(seq)) => {
+                entry.sequence = Some(seq.parse().ok()?)
+            }
+            ("pid" | "process.pid", JsonValue::Number(pid)) => entry.pid = Some(pid.parse().ok()?),
+            ("thread" | "process.thread.name", JsonValue::Str(thread))
+            | ("thread" | "process.thread.id", JsonValue::Number(thread)) => {
+                entry.thread = Some(thread)
+            }
+            ("target" | "log.logger", JsonValue::Str(target)) => entry.record.target = target,
+            ("file" | "log.origin.file.name", JsonValue::Str(file)) => location_file = Some(file),
+            ("line" | "log.origin.file.line", JsonValue::Number(line)) => {
+                location_line = Some(line.parse().ok()?)
+            }
+            ("message", JsonValue::Str(message)) => entry.record.message = message,
+            ("host.name", JsonValue::Str(host)) => {
+                entry.record.fields.push(("host".to_owned(), host))
+            }
+            ("process.name", JsonValue::Str(name)) => {
+                entry.record.fields.push(("process".to_owned(), name))
+            }
+            ("fields" | "labels", JsonValue::Object(fields)) => entry.record.fields.extend(fields),
+            _ => {}
+        }
+    }
+    entry.record.level = level?;
+    entry.location = location_file.zip(location_line);
+    Some(entry)
+}
+
+enum JsonValue {
+    Str(String),
+    Number(String),
+    Object(Vec<(String, String)>),
+    Other,
+}
+
+// Разбирает ровно то, что пишет логгер: объект со строками, числами и вложенными
+// объектами строк. Остальные значения пропускаются как `Other`.
+struct JsonParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl<'a> JsonParser<'a> {
+    fn new(s: &'a str) -> Self {
+        JsonParser {
+            chars: s.chars().peekable(),
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Option<()> {
+        self.skip_spaces();
+        self.chars.next_if_eq(&expected).map(|_| ())
+    }
+
+    fn object(&mut self) -> Option<Vec<(String, JsonValue)>> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        if self.expect('}').is_some() {
+            return Some(members);
+        }
+        loop {
+            self.skip_spaces();
+            let key = self.string()?;
+            self.expect(':')?;
+            members.push((key, self.value()?));
+            if self.expect(',').is_none() {
+                self.expect('}')?;
+                return Some(members);
+            }
+        }
+    }
+
+    fn value(&mut self) -> Option<JsonValue> {
+        self.skip_spaces();
+        match *self.chars.peek()? {
+            '"' => self.string().map(JsonValue::Str),
+            '{' => {
+                let members = self.object()?;
+                Some(JsonValue::Object(
+                    members
+                        .into_iter()
+                        .filter_map(|(key, value)| match value {
+                            JsonValue::Str(value) | JsonValue::Number(value) => Some((key, value)),
+                            _ => None,
+                        })
+                        .collect(),
+                ))
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(c) = self
+                    .chars
+                    .next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+                {
+                    number.push(c);
+                }
+                Some(JsonValue::Number(number))
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(c) = self.chars.next_if(char::is_ascii_alphabetic) {
+                    word.push(c);
+                }
+                matches!(word.as_str(), "true" | "false" | "null").then_some(JsonValue::Other)
+            }
+        }
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.chars.next_if_eq(&'"')?;
+        let mut s = String::new();
+        loop {
+            match self.chars.next()? {
+                '"' => return Some(s),
+                '\\' => match self.chars.next()? {
+                    'n' => s.push('\n'),
+                    'r' => s.push('\r'),
+                    't' => s.push('\t'),
+                    'b' => s.push('\u{8}'),
+                    'f' => s.push('\u{c}'),
+                    'u' => {
+                        let high = self.hex4()?;
+                        let code = if (0xD800..0xDC00).contains(&high) {
+                            self.chars.next_if_eq(&'\\')?;
+                            self.chars.next_if_eq(&'u')?;
+                            let low = self.hex4()?;
+                            0x10000 + ((high - 0xD800) << 10) + (low.checked_sub(0xDC00)?)
+                        } else {
+                            high
+                        };
+                        s.push(char::from_u32(code)?);
+                    }
+                    c => s.push(c), // \" \\ \/
+                },
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Option<u32> {
+        let mut code = 0;
+        for _ in 0..4 {
+            code = code * 16 + self.chars.next()?.to_digit(16)?;
+        }
+        Some(code)
+    }
+}
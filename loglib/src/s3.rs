@@ -0,0 +1,363 @@
+//! Выгрузка архивов лога в S3-совместимое хранилище: AWS S3, MinIO, Ceph (фича `s3`).
+//!
+//! ```ignore
+//! let uploader = S3Uploader::new("https://s3.eu-central-1.amazonaws.com", "fleet-logs", "/var/spool/app-logs")
+//!     .region("eu-central-1")
+//!     .credentials(&access_key, &secret_key)
+//!     .prefix("edge/")
+//!     .max_spool_size(50 << 20);
+//! let logger = Logger::builder()
+//!     .file("/var/log/app", "app.log", 1 << 20, 3)
+//!     .upload(uploader.clone())
+//!     .build()?;
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use chrono::{Local, Utc};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+const FIRST_RETRY: Duration = Duration::from_secs(1);
+const UPLOADED_SUFFIX: &str = ".uploaded";
+// Недокопированный в очередь архив; воркер его не видит
+const PART_SUFFIX: &str = ".part";
+
+/// Что делать с архивом после успешной выгрузки
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AfterUpload {
+    /// Удалить из очереди
+    #[default]
+    Delete,
+    /// Оставить в каталоге очереди с суффиксом `.uploaded`
+    Mark,
+}
+
+/// Выгрузчик архивов. Архив после ротации переносится в каталог очереди (spool) и
+/// отправляется PUT-запросом с подписью AWS Signature V4 в фоновом потоке; при ошибке
+/// попытка повторяется с растущей паузой до `max_retry_interval`. Очередь переживает
+/// перезапуск: оставшиеся в ней файлы отправляются при следующем старте.
+///
+/// Ключ объекта — `<prefix><хост>/<время переноса>.<имя архива>`.
+/// Клоны делят очередь и счётчики, поэтому клон можно оставить себе для `pending()`.
+#[derive(Clone)]
+pub struct S3Uploader {
+    config: UploadConfig,
+    state: Arc<UploadState>,
+}
+
+#[derive(Clone)]
+struct UploadConfig {
+    endpoint: String,
+    bucket: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+    prefix: String,
+    spool_dir: PathBuf,
+    after_upload: AfterUpload,
+    max_spool_size: Option<u64>,
+    max_retry_interval: Duration,
+}
+
+#[derive(Default)]
+struct UploadState {
+    sender: OnceLock<mpsc::Sender<()>>,
+    pending: AtomicU64,
+    uploaded: AtomicU64,
+    failures: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl S3Uploader {
+    /// `endpoint` — адрес хранилища (`https://s3.amazonaws.com`, `http://minio:9000`),
+    /// объекты адресуются в стиле пути: `<endpoint>/<bucket>/<ключ>`.
+    pub fn new<P: AsRef<Path>>(endpoint: &str, bucket: &str, spool_dir: P) -> Self {
+        S3Uploader {
+            config: UploadConfig {
+                endpoint: endpoint.trim_end_matches('/').to_owned(),
+                bucket: bucket.to_owned(),
+                region: "us-east-1".to_owned(),
+                access_key: String::new(),
+                secret_key: String::new(),
+                prefix: String::new(),
+                spool_dir: spool_dir.as_ref().to_path_buf(),
+                after_upload: AfterUpload::Delete,
+                max_spool_size: None,
+                max_retry_interval: Duration::from_secs(300),
+            },
+            state: Arc::new(UploadState::default()),
+        }
+    }
+
+    /// Регион подписи (по умолчанию `us-east-1`; MinIO принимает его же)
+    pub fn region(mut self, region: &str) -> Self {
+        self.config.region = region.to_owned();
+        self
+    }
+
+    pub fn credentials(mut self, access_key: &str, secret_key: &str) -> Self {
+        self.config.access_key = access_key.to_owned();
+        self.config.secret_key = secret_key.to_owned();
+        self
+    }
+
+    /// Начало ключа объекта, например `edge/`
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.config.prefix = prefix.to_owned();
+        self
+    }
+
+    pub fn after_upload(mut self, action: AfterUpload) -> Self {
+        self.config.after_upload = action;
+        self
+    }
+
+    /// Предел размера очереди: если связи долго нет, самые старые архивы удаляются,
+    /// чтобы не занять весь диск.
+    pub fn max_spool_size(mut self, bytes: u64) -> Self {
+        self.config.max_spool_size = Some(bytes);
+        self
+    }
+
+    /// Наибольшая пауза между повторами (по умолчанию 5 минут)
+    pub fn max_retry_interval(mut self, interval: Duration) -> Self {
+        self.config.max_retry_interval = interval.max(FIRST_RETRY);
+        self
+    }
+
+    /// Архивов в очереди
+    pub fn pending(&self) -> u64 {
+        self.state.pending.load(Ordering::Relaxed)
+    }
+
+    pub fn uploaded(&self) -> u64 {
+        self.state.uploaded.load(Ordering::Relaxed)
+    }
+
+    /// Неудачных попыток выгрузки
+    pub fn failures(&self) -> u64 {
+        self.state.failures.load(Ordering::Relaxed)
+    }
+
+    /// Архивов, удалённых из переполненной очереди без выгрузки
+    pub fn dropped(&self) -> u64 {
+        self.state.dropped.load(Ordering::Relaxed)
+    }
+
+    // Поток выгрузки стартует при сборке логгера: в очереди могут быть архивы
+    // прошлого запуска
+    pub(crate) fn start(&self) {
+        self.state.sender.get_or_init(|| {
+            let (sender, receiver) = mpsc::channel();
+            let config = self.config.clone();
+            let state = Arc::clone(&self.state);
+            std::thread::spawn(move || run_worker(config, receiver, state));
+            sender
+        });
+    }
+
+    // Вызывается при ротации: только перенос файла, выгрузка — в фоне
+    pub(crate) fn enqueue(&self, archive: &Path) {
+        let Some(name) = archive.file_name().and_then(|name| name.to_str()) else {
+            return;
+        };
+        if fs::create_dir_all(&self.config.spool_dir).is_err() {
+            self.state.failures.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        let spooled = format!("{}.{}", Local::now().format("%Y%m%d-%H%M%S-%3f"), name);
+        let spooled = self.config.spool_dir.join(spooled);
+        // Между файловыми системами move_file копирует: под своим именем архив появляется
+        // в очереди только целиком
+        let mut part = spooled.clone().into_os_string();
+        part.push(PART_SUFFIX);
+        let part = PathBuf::from(part);
+        if crate::move_file(archive, &part).is_err() {
+            self.state.failures.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        if fs::rename(&part, &spooled).is_err() {
+            let _ = fs::remove_file(&part);
+            self.state.failures.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.state.pending.fetch_add(1, Ordering::Relaxed);
+        if let Some(limit) = self.config.max_spool_size {
+            self.trim_spool(limit);
+        }
+        if let Some(sender) = self.state.sender.get() {
+            let _ = sender.send(());
+        }
+    }
+
+    fn trim_spool(&self, limit: u64) {
+        let files = spooled_files(&self.config.spool_dir);
+        let mut total: u64 = files
+            .iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .map(|meta| meta.len())
+            .sum();
+        // Самый свежий архив не трогаем, даже если он один больше предела
+        for path in &files[..files.len().saturating_sub(1)] {
+            if total <= limit {
+                break;
+            }
+            let size = fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
+            if fs::remove_file(path).is_ok() {
+                total = total.saturating_sub(size);
+                self.state.pending.fetch_sub(1, Ordering::Relaxed);
+                self.state.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+// Ждущие выгрузки файлы очереди, от старых к новым
+fn spooled_files(spool_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(spool_dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    !name.ends_with(UPLOADED_SUFFIX) && !name.ends_with(PART_SUFFIX)
+                })
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+fn run_worker(config: UploadConfig, receiver: mpsc::Receiver<()>, state: Arc<UploadState>) {
+    let agent = ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build();
+    state.pending.store(
+        spooled_files(&config.spool_dir).len() as u64,
+        Ordering::Relaxed,
+    );
+    let mut retry = FIRST_RETRY;
+    loop {
+        let Some(path) = spooled_files(&config.spool_dir).into_iter().next() else {
+            if receiver.recv().is_err() {
+                return;
+            }
+            continue;
+        };
+        match upload(&agent, &config, &path) {
+            Ok(()) => {
+                retry = FIRST_RETRY;
+                let done = match config.after_upload {
+                    AfterUpload::Delete => fs::remove_file(&path),
+                    AfterUpload::Mark => {
+                        let mut marked = path.clone().into_os_string();
+                        marked.push(UPLOADED_SUFFIX);
+                        fs::rename(&path, marked)
+                    }
+                };
+                if done.is_ok() {
+                    state.pending.fetch_sub(1, Ordering::Relaxed);
+                    state.uploaded.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+                // Файл не убрать из очереди: повторная выгрузка перезапишет тот же объект
+                state.failures.fetch_add(1, Ordering::Relaxed);
+                if let Err(RecvTimeoutError::Disconnected) = receiver.recv_timeout(retry) {
+                    return;
+                }
+                retry = (retry * 2).min(config.max_retry_interval);
+            }
+            Err(_) => {
+                state.failures.fetch_add(1, Ordering::Relaxed);
+                if let Err(RecvTimeoutError::Disconnected) = receiver.recv_timeout(retry) {
+                    return;
+                }
+                retry = (retry * 2).min(config.max_retry_interval);
+            }
+        }
+    }
+}
+
+fn upload(agent: &ureq::Agent, config: &UploadConfig, path: &Path) -> io::Result<()> {
+    let body = fs::read(path)?;
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let key = format!("{}{}/{}", config.prefix, crate::hostname(), name);
+    let content_type = if name.ends_with(crate::COMPRESSED_SUFFIX) {
+        "application/gzip"
+    } else {
+        "text/plain; charset=utf-8"
+    };
+    let uri = format!("/{}/{}", uri_encode(&config.bucket), uri_encode(&key));
+    let host = config
+        .endpoint
+        .split_once("://")
+        .map_or(config.endpoint.as_str(), |(_, rest)| rest);
+
+    let now = Utc::now();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let payload_hash = hex(&hmac_sha256::Hash::hash(&body));
+
+    let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+    let canonical_request = format!(
+        "PUT\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+        uri, host, payload_hash, amz_date, signed_headers, payload_hash
+    );
+    let scope = format!("{}/{}/s3/aws4_request", date, config.region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex(&hmac_sha256::Hash::hash(canonical_request.as_bytes()))
+    );
+    let mut key = hmac_sha256::HMAC::mac(date, format!("AWS4{}", config.secret_key));
+    for part in [config.region.as_str(), "s3", "aws4_request"] {
+        key = hmac_sha256::HMAC::mac(part, key);
+    }
+    let signature = hex(&hmac_sha256::HMAC::mac(string_to_sign, key));
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        config.access_key, scope, signed_headers, signature
+    );
+
+    agent
+        .put(&format!("{}{}", config.endpoint, uri))
+        .set("Host", host)
+        .set("x-amz-date", &amz_date)
+        .set("x-amz-content-sha256", &payload_hash)
+        .set("Authorization", &authorization)
+        .set("Content-Type", content_type)
+        .send_bytes(&body)
+        .map_err(|e| io::Error::other(e.to_string()))?;
+    Ok(())
+}
+
+// Кодирование пути по правилам SigV4: всё, кроме A-Z a-z 0-9 - . _ ~ и '/'
+fn uri_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
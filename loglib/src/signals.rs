@@ -0,0 +1,130 @@
+//! Уровень по сигналам (`LoggerBuilder::verbosity_signals`, unix): SIGUSR1 — на уровень
+//! подробнее, SIGUSR2 — уровни из настроек. Обработчик только пишет номер сигнала в
+//! pipe; уровни меняет поток `loglib-signals`. Обработчик ставится один раз на процесс
+//! и остаётся до его завершения.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::unix::io::FromRawFd;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Mutex;
+
+use crate::control::Target;
+
+// Пишущий конец pipe; -1 — обработчик ещё не установлен
+static PIPE_WRITE: AtomicI32 = AtomicI32::new(-1);
+
+// Логгеры, подписанные на сигналы; закрытые убираются при следующей подписке
+static TARGETS: Mutex<Vec<Target>> = Mutex::new(Vec::new());
+
+pub(crate) fn register(target: Target) -> io::Result<()> {
+    let mut targets = TARGETS.lock().unwrap();
+    if PIPE_WRITE.load(Ordering::Acquire) < 0 {
+        install()?;
+    }
+    targets.retain(|target| target.levels.strong_count() > 0);
+    targets.push(target);
+    Ok(())
+}
+
+fn install() -> io::Result<()> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    unsafe {
+        libc::fcntl(fds[0], libc::F_SETFD, libc::FD_CLOEXEC);
+        libc::fcntl(fds[1], libc::F_SETFD, libc::FD_CLOEXEC);
+        // Полный pipe не должен останавливать обработчик: лишние сигналы теряются
+        libc::fcntl(fds[1], libc::F_SETFL, libc::O_NONBLOCK);
+    }
+    let reader = unsafe { File::from_raw_fd(fds[0]) };
+    std::thread::Builder::new()
+        .name("loglib-signals".to_owned())
+        .spawn(move || dispatch(reader))?;
+    PIPE_WRITE.store(fds[1], Ordering::Release);
+
+    for signal in [libc::SIGUSR1, libc::SIGUSR2] {
+        let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+        action.sa_sigaction = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        action.sa_flags = libc::SA_RESTART;
+        unsafe { libc::sigemptyset(&mut action.sa_mask) };
+        if unsafe { libc::sigaction(signal, &action, std::ptr::null_mut()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+// Внутри обработчика можно только async-signal-safe вызовы: write и errno
+extern "C" fn on_signal(signal: libc::c_int) {
+    let errno = errno_location();
+    let saved = unsafe { errno.as_ref().copied() };
+    let byte = signal as u8;
+    unsafe {
+        libc::write(
+            PIPE_WRITE.load(Ordering::Relaxed),
+            &byte as *const u8 as *const libc::c_void,
+            1,
+        );
+        if let Some(saved) = saved {
+            *errno = saved;
+        }
+    }
+}
+
+// Где лежит errno потока: у каждой libc своя функция
+#[cfg(any(target_os = "linux", target_os = "dragonfly"))]
+fn errno_location() -> *mut libc::c_int {
+    unsafe { libc::__errno_location() }
+}
+
+#[cfg(any(target_os = "android", target_os = "openbsd", target_os = "netbsd"))]
+fn errno_location() -> *mut libc::c_int {
+    unsafe { libc::__errno() }
+}
+
+#[cfg(any(target_vendor = "apple", target_os = "freebsd"))]
+fn errno_location() -> *mut libc::c_int {
+    unsafe { libc::__error() }
+}
+
+#[cfg(any(target_os = "solaris", target_os = "illumos"))]
+fn errno_location() -> *mut libc::c_int {
+    unsafe { libc::___errno() }
+}
+
+#[cfg(target_os = "haiku")]
+fn errno_location() -> *mut libc::c_int {
+    unsafe { libc::_errnop() }
+}
+
+// Прочие unix: errno не сохраняется, write в обработчике его может изменить
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "android",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "solaris",
+    target_os = "illumos",
+    target_os = "haiku"
+)))]
+fn errno_location() -> *mut libc::c_int {
+    std::ptr::null_mut()
+}
+
+fn dispatch(mut reader: File) {
+    let mut signal = [0u8; 1];
+    while let Ok(1) = reader.read(&mut signal) {
+        let targets = TARGETS.lock().unwrap();
+        for target in targets.iter() {
+            let _ = match signal[0] as libc::c_int {
+                libc::SIGUSR1 => target.more_verbose(),
+                _ => target.set_level(None).map(drop),
+            };
+        }
+    }
+}
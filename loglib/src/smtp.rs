@@ -0,0 +1,230 @@
+//! Оповещения по почте о серьёзных записях (фича `smtp`).
+
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+
+use crate::{Clock, LogLevel, Record, Sink, SystemClock};
+
+const SUBJECT_MAX_CHARS: usize = 100;
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Как подключаться к SMTP-серверу
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpTls {
+    /// STARTTLS, обычно порт 587 (по умолчанию)
+    StartTls,
+    /// TLS с самого начала соединения, порт 465
+    Wrapper,
+    /// Без шифрования — только для локального relay на localhost:25
+    Off,
+}
+
+/// Отправляет письмо о записях уровня Fatal (или заданного через `min_level`).
+/// Не чаще одного письма за `rate_limit`: записи в промежутке только считаются,
+/// и их число попадает в следующее письмо. Письмо уходит в отдельном потоке,
+/// `Logger::flush` дожидается его отправки.
+pub struct SmtpSink {
+    relay: String,
+    port: Option<u16>,
+    tls: SmtpTls,
+    credentials: Option<Credentials>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    min_level: LogLevel,
+    rate_limit: Duration,
+    clock: Arc<dyn Clock>,
+    state: Mutex<AlertState>,
+    failures: Arc<AtomicU64>,
+}
+
+#[derive(Default)]
+struct AlertState {
+    last_sent: Option<Duration>,
+    suppressed: u64,
+    sending: Option<JoinHandle<()>>,
+}
+
+impl SmtpSink {
+    /// `relay` — адрес SMTP-сервера, `from` и `to` — адреса вида `ops@example.com`
+    /// или `Ops <ops@example.com>`. Неверный адрес — ошибка `InvalidInput`.
+    pub fn new(relay: &str, from: &str, to: &[&str]) -> io::Result<Self> {
+        let from = parse_mailbox(from)?;
+        let to = to
+            .iter()
+            .map(|address| parse_mailbox(address))
+            .collect::<io::Result<Vec<_>>>()?;
+        if to.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "at least one recipient is required",
+            ));
+        }
+        Ok(SmtpSink {
+            relay: relay.to_owned(),
+            port: None,
+            tls: SmtpTls::StartTls,
+            credentials: None,
+            from,
+            to,
+            min_level: LogLevel::Fatal,
+            rate_limit: Duration::from_secs(15 * 60),
+            clock: Arc::new(SystemClock),
+            state: Mutex::new(AlertState::default()),
+            failures: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    pub fn tls(mut self, tls: SmtpTls) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    pub fn credentials(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some(Credentials::new(username.to_owned(), password.to_owned()));
+        self
+    }
+
+    /// Отправлять письма о записях не ниже `level` (по умолчанию Fatal).
+    pub fn min_level(mut self, level: LogLevel) -> Self {
+        self.min_level = level;
+        self
+    }
+
+    /// Не чаще одного письма за `interval` (по умолчанию 15 минут).
+    pub fn rate_limit(mut self, interval: Duration) -> Self {
+        self.rate_limit = interval;
+        self
+    }
+
+    /// Источник времени для ограничения частоты и поля Time в письме.
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Сколько писем не удалось отправить
+    pub fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+
+    fn transport(&self) -> io::Result<SmtpTransport> {
+        let builder = match self.tls {
+            SmtpTls::StartTls => {
+                SmtpTransport::starttls_relay(&self.relay).map_err(io::Error::other)?
+            }
+            SmtpTls::Wrapper => SmtpTransport::relay(&self.relay).map_err(io::Error::other)?,
+            SmtpTls::Off => SmtpTransport::builder_dangerous(&self.relay),
+        };
+        let mut builder = builder.timeout(Some(SEND_TIMEOUT));
+        if let Some(port) = self.port {
+            builder = builder.port(port);
+        }
+        if let Some(ref credentials) = self.credentials {
+            builder = builder.credentials(credentials.clone());
+        }
+        Ok(builder.build())
+    }
+
+    fn message(&self, record: &Record, suppressed: u64) -> io::Result<Message> {
+        let text = record.args().to_string();
+        let host = crate::hostname();
+        let first_line = text.lines().next().unwrap_or("");
+        let mut subject = format!("[{}] {}: ", host, record.level());
+        subject.extend(first_line.chars().take(SUBJECT_MAX_CHARS));
+
+        let mut body = format!(
+            "Time: {}\nHost: {}\nLevel: {}\n",
+            self.clock.now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            host,
+            record.level()
+        );
+        if !record.target().is_empty() {
+            body.push_str(&format!("Target: {}\n", record.target()));
+        }
+        if let Some(location) = record.location() {
+            body.push_str(&format!("Location: {}:{}\n", location.file, location.line));
+        }
+        for (key, value) in record.fields() {
+            body.push_str(&format!("{}: {}\n", key, value));
+        }
+        body.push('\n');
+        body.push_str(&text);
+        body.push('\n');
+        if suppressed > 0 {
+            body.push_str(&format!(
+                "\n{} more record(s) at {} or above were not sent since the previous alert.\n",
+                suppressed, self.min_level
+            ));
+        }
+
+        let mut message = Message::builder().from(self.from.clone());
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        message
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body)
+            .map_err(io::Error::other)
+    }
+}
+
+impl Sink for SmtpSink {
+    fn write(&self, record: &Record) -> io::Result<()> {
+        if record.level() < self.min_level {
+            return Ok(());
+        }
+        let mut state = self.state.lock().unwrap();
+        let in_flight = state.sending.as_ref().is_some_and(|h| !h.is_finished());
+        let now = self.clock.monotonic();
+        let too_soon = state
+            .last_sent
+            .is_some_and(|sent| now.saturating_sub(sent) < self.rate_limit);
+        if in_flight || too_soon {
+            state.suppressed += 1;
+            return Ok(());
+        }
+
+        let message = self.message(record, state.suppressed)?;
+        let transport = self.transport()?;
+        let failures = Arc::clone(&self.failures);
+        state.last_sent = Some(now);
+        state.suppressed = 0;
+        state.sending = Some(std::thread::spawn(move || {
+            if transport.send(&message).is_err() {
+                failures.fetch_add(1, Ordering::Relaxed);
+            }
+        }));
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let sending = self.state.lock().unwrap().sending.take();
+        if let Some(handle) = sending {
+            let _ = handle.join();
+        }
+        Ok(())
+    }
+}
+
+fn parse_mailbox(address: &str) -> io::Result<Mailbox> {
+    address.parse().map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid email address {:?}: {}", address, e),
+        )
+    })
+}
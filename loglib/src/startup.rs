@@ -0,0 +1,155 @@
+//! Сведения о системе для стартовой записи (`LoggerBuilder::startup_banner`): то, что
+//! поддержка спрашивает первым делом, — ОС, хост, процессоры, память, исполняемый файл,
+//! командная строка и локаль. Что узнать не удалось, пишется как `unknown`.
+
+use crate::hostname;
+
+const UNKNOWN: &str = "unknown";
+
+// Поля стартовой записи по порядку
+pub(crate) fn fields() -> Vec<(&'static str, String)> {
+    let exe = std::env::current_exe()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| UNKNOWN.to_owned());
+    vec![
+        ("os", os_version()),
+        ("host", hostname()),
+        (
+            "cpus",
+            std::thread::available_parallelism()
+                .map_or_else(|_| UNKNOWN.to_owned(), |n| n.to_string()),
+        ),
+        (
+            "memory_mb",
+            total_memory().map_or_else(|| UNKNOWN.to_owned(), |bytes| (bytes >> 20).to_string()),
+        ),
+        ("exe", exe),
+        ("args", command_line()),
+        ("locale", locale().unwrap_or_else(|| UNKNOWN.to_owned())),
+    ]
+}
+
+// Аргументы с пробелами — в кавычках, чтобы строку можно было повторить в shell
+fn command_line() -> String {
+    std::env::args_os()
+        .map(|arg| {
+            let arg = arg.to_string_lossy();
+            if arg.is_empty() || arg.contains(char::is_whitespace) {
+                format!("\"{}\"", arg)
+            } else {
+                arg.into_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// "Linux 6.8.0-45-generic (Ubuntu 24.04.1 LTS)", "Darwin 23.6.0"
+#[cfg(unix)]
+fn os_version() -> String {
+    let mut name: libc::utsname = unsafe { std::mem::zeroed() };
+    if unsafe { libc::uname(&mut name) } != 0 {
+        return std::env::consts::OS.to_owned();
+    }
+    let field = |chars: &[libc::c_char]| {
+        let bytes: Vec<u8> = chars
+            .iter()
+            .take_while(|&&c| c != 0)
+            .map(|&c| c as u8)
+            .collect();
+        String::from_utf8_lossy(&bytes).into_owned()
+    };
+    let kernel = format!("{} {}", field(&name.sysname), field(&name.release));
+    match distribution() {
+        Some(distribution) => format!("{} ({})", kernel, distribution),
+        None => kernel,
+    }
+}
+
+// PRETTY_NAME из /etc/os-release
+#[cfg(unix)]
+fn distribution() -> Option<String> {
+    let release = std::fs::read_to_string("/etc/os-release").ok()?;
+    release.lines().find_map(|line| {
+        let value = line.strip_prefix("PRETTY_NAME=")?;
+        Some(value.trim().trim_matches('"').to_owned())
+    })
+}
+
+#[cfg(unix)]
+fn total_memory() -> Option<u64> {
+    let pages = unsafe { libc::sysconf(libc::_SC_PHYS_PAGES) };
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    (pages > 0 && page_size > 0).then(|| pages as u64 * page_size as u64)
+}
+
+// Как у setlocale: LC_ALL важнее LC_CTYPE, тот — LANG
+#[cfg(unix)]
+fn locale() -> Option<String> {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .or_else(|| Some("C".to_owned()))
+}
+
+// GetVersionExW без манифеста приложения возвращает 6.2, поэтому версия — из реестра:
+// "Windows 10 Pro (build 19045)"
+#[cfg(target_os = "windows")]
+fn os_version() -> String {
+    let product = registry_string("ProductName");
+    let build = registry_string("CurrentBuildNumber");
+    match (product, build) {
+        (Some(product), Some(build)) => format!("{} (build {})", product, build),
+        (Some(product), None) => product,
+        (None, _) => "Windows".to_owned(),
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn registry_string(value: &str) -> Option<String> {
+    use windows_sys::Win32::Foundation::ERROR_SUCCESS;
+    use windows_sys::Win32::System::Registry::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_SZ};
+
+    let wide = |text: &str| text.encode_utf16().chain(Some(0)).collect::<Vec<u16>>();
+    let key = wide("SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion");
+    let value = wide(value);
+    let mut buf = [0u16; 256];
+    let mut size = std::mem::size_of_val(&buf) as u32;
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            key.as_ptr(),
+            value.as_ptr(),
+            RRF_RT_REG_SZ,
+            std::ptr::null_mut(),
+            buf.as_mut_ptr().cast(),
+            &mut size,
+        )
+    };
+    if status != ERROR_SUCCESS {
+        return None;
+    }
+    let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+    Some(String::from_utf16_lossy(&buf[..len]))
+}
+
+#[cfg(target_os = "windows")]
+fn total_memory() -> Option<u64> {
+    use windows_sys::Win32::System::SystemInformation::{GlobalMemoryStatusEx, MEMORYSTATUSEX};
+
+    let mut status: MEMORYSTATUSEX = unsafe { std::mem::zeroed() };
+    status.dwLength = std::mem::size_of::<MEMORYSTATUSEX>() as u32;
+    (unsafe { GlobalMemoryStatusEx(&mut status) } != 0).then_some(status.ullTotalPhys)
+}
+
+#[cfg(target_os = "windows")]
+fn locale() -> Option<String> {
+    use windows_sys::Win32::Globalization::GetUserDefaultLocaleName;
+
+    // LOCALE_NAME_MAX_LENGTH
+    let mut buf = [0u16; 85];
+    let len = unsafe { GetUserDefaultLocaleName(buf.as_mut_ptr(), buf.len() as i32) };
+    // Длина включает завершающий ноль
+    (len > 1).then(|| String::from_utf16_lossy(&buf[..len as usize - 1]))
+}
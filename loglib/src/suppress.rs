@@ -0,0 +1,88 @@
+//! Подавление известных шумных сообщений по регулярным выражениям — без правки
+//! зависимости, которая их пишет. Правила задаются в построителе или в
+//! `LoggerConfig::suppress`.
+//!
+//! ```ignore
+//! Logger::builder()
+//!     .suppress(SuppressRule::new(r"^connection reset by peer", SuppressAction::Drop))
+//!     .suppress(SuppressRule::new(
+//!         r"option `\w+` is deprecated",
+//!         SuppressAction::Downgrade(LogLevel::Debug),
+//!     ))
+//! ```
+
+use std::io;
+
+use regex::Regex;
+
+use crate::{LogLevel, OwnedRecord};
+
+/// Что сделать с записью, сообщение которой совпало с шаблоном
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum SuppressAction {
+    /// Отбросить запись
+    #[default]
+    Drop,
+    /// Понизить уровень до заданного; запись уровнем ниже не меняется
+    Downgrade(LogLevel),
+}
+
+/// Шаблон и действие. Шаблон ищется в отформатированном сообщении записи.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(deny_unknown_fields)
+)]
+pub struct SuppressRule {
+    pub pattern: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub action: SuppressAction,
+}
+
+impl SuppressRule {
+    pub fn new(pattern: &str, action: SuppressAction) -> Self {
+        SuppressRule {
+            pattern: pattern.to_owned(),
+            action,
+        }
+    }
+}
+
+// Перехватчик из правил; применяется первое совпавшее правило
+pub(crate) fn interceptor(
+    rules: &[SuppressRule],
+) -> io::Result<impl Fn(OwnedRecord) -> Option<OwnedRecord> + Send + Sync + 'static> {
+    let compiled = rules
+        .iter()
+        .map(|rule| {
+            Regex::new(&rule.pattern)
+                .map(|regex| (regex, rule.action))
+                .map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("invalid suppress pattern {:?}: {}", rule.pattern, e),
+                    )
+                })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    Ok(move |mut record: OwnedRecord| {
+        let matched = compiled
+            .iter()
+            .find(|(regex, _)| regex.is_match(&record.message));
+        match matched {
+            None => Some(record),
+            Some((_, SuppressAction::Drop)) => None,
+            Some(&(_, SuppressAction::Downgrade(level))) => {
+                record.level = record.level.min(level);
+                Some(record)
+            }
+        }
+    })
+}
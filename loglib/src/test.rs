@@ -0,0 +1,342 @@
+//! Логгер для тестов: записи хранятся в памяти, а не в файлах.
+//!
+//! ```ignore
+//! let capture = CaptureLogger::new();
+//! loglib::warning!(capture, "disk almost full");
+//! capture.assert_logged(LogLevel::Warning, "almost full");
+//! ```
+//!
+//! `ManualClock` — часы, которые идут только по команде: метки времени, имена
+//! файлов и ограничения частоты в тестах получаются одинаковыми при каждом запуске.
+//!
+//! `FaultInjector` — сбои по заказу: каждая N-я запись, переименование при ротации,
+//! переполненный диск. Подключается к файлам через `LoggerBuilder::fault_injection`
+//! и к любому приёмнику через `FaultySink`.
+
+use std::io;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use chrono::{DateTime, Local, TimeZone};
+
+use crate::{Clock, LogLevel, Logger, Record, Sink, GLOBAL_LOGGER};
+
+/// Запись, сохранённая `CaptureLogger`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedRecord {
+    pub level: LogLevel,
+    pub target: String,
+    pub message: String,
+    pub fields: Vec<(String, String)>,
+}
+
+type Store = Arc<Mutex<Vec<CapturedRecord>>>;
+
+struct CaptureSink(Store);
+
+impl Sink for CaptureSink {
+    fn write(&self, record: &Record) -> io::Result<()> {
+        self.0.lock().unwrap().push(CapturedRecord {
+            level: record.level(),
+            target: record.target().to_owned(),
+            message: record.args().to_string(),
+            fields: record
+                .fields()
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        });
+        Ok(())
+    }
+}
+
+/// Логгер, который складывает записи в память. Разыменовывается в `Logger`,
+/// поэтому подходит для макросов и функций, принимающих `&Logger`.
+/// Глобальный уровень (`set_global_log_level`) действует и на него.
+pub struct CaptureLogger {
+    logger: Logger,
+    records: Store,
+}
+
+impl Default for CaptureLogger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CaptureLogger {
+    pub fn new() -> Self {
+        let records = Store::default();
+        CaptureLogger {
+            logger: capture_logger(&records),
+            records,
+        }
+    }
+
+    pub fn logger(&self) -> &Logger {
+        &self.logger
+    }
+
+    /// Копия сохранённых записей
+    pub fn records(&self) -> Vec<CapturedRecord> {
+        self.records.lock().unwrap().clone()
+    }
+
+    pub fn clear(&self) {
+        self.records.lock().unwrap().clear();
+    }
+
+    /// Есть ли запись уровня `level`, в тексте которой встречается `substring`
+    pub fn contains(&self, level: LogLevel, substring: &str) -> bool {
+        self.records
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|r| r.level == level && r.message.contains(substring))
+    }
+
+    /// Паникует со списком сохранённых записей, если подходящей нет.
+    #[track_caller]
+    pub fn assert_logged(&self, level: LogLevel, substring: &str) {
+        if !self.contains(level, substring) {
+            let records = self.records();
+            let listing: Vec<String> = records
+                .iter()
+                .map(|r| format!("  {} {}", r.level, r.message))
+                .collect();
+            panic!(
+                "no {} record containing {:?}; captured {} record(s):\n{}",
+                level,
+                substring,
+                records.len(),
+                listing.join("\n")
+            );
+        }
+    }
+
+    /// Делает этот логгер глобальным до уничтожения guard'а: записи `ginfo!` и других
+    /// глобальных макросов попадают сюда же. Потом возвращается прежний глобальный логгер.
+    /// Тесты, которые ставят глобальный логгер, выполняются по очереди: guard держит
+    /// общую блокировку.
+    pub fn install_global(&self) -> GlobalCaptureGuard {
+        let serial = GLOBAL_CAPTURE.lock().unwrap_or_else(|e| e.into_inner());
+        let previous = GLOBAL_LOGGER
+            .lock()
+            .unwrap()
+            .replace(capture_logger(&self.records));
+        GlobalCaptureGuard {
+            previous,
+            _serial: serial,
+        }
+    }
+}
+
+impl Deref for CaptureLogger {
+    type Target = Logger;
+
+    fn deref(&self) -> &Logger {
+        &self.logger
+    }
+}
+
+// Тесты с глобальным логгером мешали бы друг другу при параллельном запуске
+static GLOBAL_CAPTURE: Mutex<()> = Mutex::new(());
+
+/// Возвращает прежний глобальный логгер при уничтожении (см. `CaptureLogger::install_global`)
+#[must_use = "глобальный логгер возвращается, как только guard уничтожен"]
+pub struct GlobalCaptureGuard {
+    previous: Option<Logger>,
+    _serial: MutexGuard<'static, ()>,
+}
+
+impl Drop for GlobalCaptureGuard {
+    fn drop(&mut self) {
+        let mut global = GLOBAL_LOGGER.lock().unwrap_or_else(|e| e.into_inner());
+        *global = self.previous.take();
+    }
+}
+
+fn capture_logger(records: &Store) -> Logger {
+    Logger::builder()
+        .add_sink(CaptureSink(Arc::clone(records)))
+        .build()
+        .expect("a logger without files cannot fail to build")
+}
+
+/// Часы для тестов: стоят на месте, пока их не переведут.
+/// ```ignore
+/// let clock = Arc::new(ManualClock::at(2024, 1, 15, 9, 30, 0));
+/// let logger = Logger::builder().clock(clock.clone()).build()?;
+/// clock.advance(Duration::from_secs(90));
+/// ```
+pub struct ManualClock {
+    state: Mutex<(DateTime<Local>, Duration)>,
+}
+
+impl ManualClock {
+    pub fn new(start: DateTime<Local>) -> Self {
+        ManualClock {
+            state: Mutex::new((start, Duration::ZERO)),
+        }
+    }
+
+    /// Часы на заданном местном времени. Паникует, если такого времени нет
+    /// (например, его пропускает переход на летнее время).
+    #[track_caller]
+    pub fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> Self {
+        let start = Local
+            .with_ymd_and_hms(year, month, day, hour, minute, second)
+            .earliest()
+            .expect("no such local time");
+        Self::new(start)
+    }
+
+    /// Перевести вперёд и настенное, и монотонное время.
+    pub fn advance(&self, by: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.0 += chrono::Duration::from_std(by).expect("duration out of range");
+        state.1 += by;
+    }
+
+    /// Поставить настенное время, как при переводе системных часов;
+    /// монотонное время не меняется.
+    pub fn set(&self, now: DateTime<Local>) {
+        self.state.lock().unwrap().0 = now;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Local> {
+        self.state.lock().unwrap().0
+    }
+
+    fn monotonic(&self) -> Duration {
+        self.state.lock().unwrap().1
+    }
+}
+
+/// Набор сбоев для проверки обработки ошибок. Переключается на ходу,
+/// поэтому можно сломать запись, а потом убедиться, что логгер восстановился.
+/// ```ignore
+/// let faults = Arc::new(FaultInjector::new());
+/// let logger = Logger::builder()
+///     .file(dir, "app.log", 1024, 3)
+///     .fault_injection(faults.clone())
+///     .build()?;
+/// faults.set_disk_full(true);
+/// assert!(logger.try_write(LogLevel::Info, "lost").is_err());
+/// ```
+#[derive(Debug, Default)]
+pub struct FaultInjector {
+    fail_every: AtomicU64, // 0 — не отказывать
+    writes: AtomicU64,
+    fail_renames: AtomicBool,
+    fail_opens: AtomicBool,
+    disk_full: AtomicBool,
+    injected: AtomicU64,
+}
+
+impl FaultInjector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Отказывать на каждой `n`-й записи; 0 — выключить.
+    pub fn fail_every_nth_write(&self, n: u64) {
+        self.fail_every.store(n, Ordering::Relaxed);
+        self.writes.store(0, Ordering::Relaxed);
+    }
+
+    /// Ротация файла завершается ошибкой, как при неудачном переименовании.
+    pub fn set_fail_renames(&self, enabled: bool) {
+        self.fail_renames.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Файл не открывается после ротации, как при пропавшем каталоге или блокировке
+    /// антивирусом (см. `RetryPolicy`).
+    pub fn set_fail_opens(&self, enabled: bool) {
+        self.fail_opens.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Все записи завершаются ошибкой ENOSPC.
+    pub fn set_disk_full(&self, enabled: bool) {
+        self.disk_full.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Сколько ошибок выдано
+    pub fn injected(&self) -> u64 {
+        self.injected.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn before_write(&self) -> io::Result<()> {
+        if self.disk_full.load(Ordering::Relaxed) {
+            return Err(self.inject(disk_full_error()));
+        }
+        let every = self.fail_every.load(Ordering::Relaxed);
+        if every > 0 && (self.writes.fetch_add(1, Ordering::Relaxed) + 1).is_multiple_of(every) {
+            return Err(self.inject(io::Error::other("injected write failure")));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn before_rename(&self) -> io::Result<()> {
+        if self.fail_renames.load(Ordering::Relaxed) {
+            return Err(self.inject(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "injected rename failure",
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn before_open(&self) -> io::Result<()> {
+        if self.fail_opens.load(Ordering::Relaxed) {
+            return Err(self.inject(io::Error::new(
+                io::ErrorKind::NotFound,
+                "injected open failure",
+            )));
+        }
+        Ok(())
+    }
+
+    fn inject(&self, error: io::Error) -> io::Error {
+        self.injected.fetch_add(1, Ordering::Relaxed);
+        error
+    }
+}
+
+#[cfg(unix)]
+fn disk_full_error() -> io::Error {
+    io::Error::from_raw_os_error(libc::ENOSPC)
+}
+
+#[cfg(not(unix))]
+fn disk_full_error() -> io::Error {
+    io::Error::new(io::ErrorKind::StorageFull, "injected disk full")
+}
+
+/// Приёмник-обёртка: пропускает записи во вложенный приёмник, пока `FaultInjector`
+/// не прикажет отказать. Сбои ротации на приёмники не действуют.
+pub struct FaultySink<S> {
+    inner: S,
+    faults: Arc<FaultInjector>,
+}
+
+impl<S: Sink> FaultySink<S> {
+    pub fn new(inner: S, faults: Arc<FaultInjector>) -> Self {
+        FaultySink { inner, faults }
+    }
+}
+
+impl<S: Sink> Sink for FaultySink<S> {
+    fn write(&self, record: &Record) -> io::Result<()> {
+        self.faults.before_write()?;
+        self.inner.write(record)
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
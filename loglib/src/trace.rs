@@ -0,0 +1,226 @@
+//! Идентификаторы распределённой трассировки в записях: поля `trace_id` и `span_id`
+//! (в ECS — `trace.id` и `span.id`), по которым Grafana, Tempo и Jaeger находят строки
+//! лога нужного спана. Источник — активный спан OpenTelemetry
+//! (`LoggerBuilder::trace_context`) или заголовок `traceparent`, пришедший в запросе.
+//!
+//! ```ignore
+//! use opentelemetry::trace::TraceContextExt;
+//!
+//! let logger = Logger::builder()
+//!     .trace_context(|| {
+//!         let context = opentelemetry::Context::current();
+//!         let span = context.span();
+//!         let span = span.span_context();
+//!         span.is_valid()
+//!             .then(|| TraceContext::new(span.trace_id().to_bytes(), span.span_id().to_bytes()))
+//!     })
+//!     .build()?;
+//! ```
+
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::Record;
+
+// Активный спан по мнению приложения (см. LoggerBuilder::trace_context)
+pub(crate) type TraceSource = Arc<dyn Fn() -> Option<TraceContext> + Send + Sync>;
+
+// Открытые TraceGuard во всех потоках: пока их нет, запись не заглядывает в thread-local
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static CURRENT: RefCell<Option<TraceContext>> = const { RefCell::new(None) };
+}
+
+/// Идентификаторы трассы и спана в формате W3C Trace Context
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TraceContext {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+    /// Флаг sampled из `traceparent`
+    pub sampled: bool,
+}
+
+impl TraceContext {
+    pub const TRACE_FIELD: &'static str = "trace_id";
+    pub const SPAN_FIELD: &'static str = "span_id";
+    /// Заголовок W3C Trace Context
+    pub const HEADER: &'static str = "traceparent";
+
+    pub fn new(trace_id: [u8; 16], span_id: [u8; 8]) -> Self {
+        TraceContext {
+            trace_id,
+            span_id,
+            sampled: true,
+        }
+    }
+
+    /// Нулевые идентификаторы по W3C недействительны; такие в записи не попадают
+    pub fn is_valid(&self) -> bool {
+        self.trace_id != [0; 16] && self.span_id != [0; 8]
+    }
+
+    /// Разбор `traceparent`: `00-<trace_id 32 hex>-<span_id 16 hex>-<флаги 2 hex>`
+    pub fn from_traceparent(header: &str) -> Option<Self> {
+        let mut parts = header.trim().split('-');
+        let version = parts.next()?;
+        if version.len() != 2 || version.eq_ignore_ascii_case("ff") {
+            return None;
+        }
+        let mut context = TraceContext::new([0; 16], [0; 8]);
+        decode_hex(parts.next()?, &mut context.trace_id)?;
+        decode_hex(parts.next()?, &mut context.span_id)?;
+        let mut flags = [0; 1];
+        decode_hex(parts.next()?, &mut flags)?;
+        context.sampled = flags[0] & 1 == 1;
+        // Версия 00 — ровно четыре части; будущие версии могут добавить свои
+        if version == "00" && parts.next().is_some() {
+            return None;
+        }
+        context.is_valid().then_some(context)
+    }
+
+    /// Значение `traceparent` для исходящего запроса
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-{:02x}",
+            self.trace_id_hex(),
+            self.span_id_hex(),
+            self.sampled as u8
+        )
+    }
+
+    pub fn trace_id_hex(&self) -> String {
+        encode_hex(&self.trace_id)
+    }
+
+    pub fn span_id_hex(&self) -> String {
+        encode_hex(&self.span_id)
+    }
+
+    /// Делает контекст текущим для потока до drop guard — для сервисов без
+    /// OpenTelemetry, которые только передают дальше пришедший `traceparent`.
+    /// Источник из `LoggerBuilder::trace_context` имеет приоритет.
+    pub fn enter(&self) -> TraceGuard {
+        let previous = CURRENT.with(|current| current.replace(Some(*self)));
+        ACTIVE.fetch_add(1, Ordering::Relaxed);
+        TraceGuard {
+            previous,
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Выполняет `future` с этим контекстом в контексте задачи (см. `context::scope`)
+    #[cfg(feature = "tokio")]
+    pub async fn scope<F: std::future::Future>(&self, future: F) -> F::Output {
+        let (trace_id, span_id) = (self.trace_id_hex(), self.span_id_hex());
+        let fields = [
+            (Self::TRACE_FIELD, &*trace_id),
+            (Self::SPAN_FIELD, &*span_id),
+        ];
+        crate::context::scope(&fields, future).await
+    }
+
+    /// Текущий контекст: из контекста задачи tokio, иначе из `enter` этого потока.
+    /// Источник логгера сюда не входит — спан OpenTelemetry берите у него самого.
+    pub fn current() -> Option<Self> {
+        #[cfg(feature = "tokio")]
+        {
+            let fields = crate::context::current();
+            let find = |name: &str| fields.iter().rev().find(|(key, _)| key == name);
+            if let (Some((_, trace_id)), Some((_, span_id))) =
+                (find(Self::TRACE_FIELD), find(Self::SPAN_FIELD))
+            {
+                let mut context = TraceContext::new([0; 16], [0; 8]);
+                if decode_hex(trace_id, &mut context.trace_id)
+                    .and(decode_hex(span_id, &mut context.span_id))
+                    .is_some()
+                {
+                    return Some(context);
+                }
+            }
+        }
+        if ACTIVE.load(Ordering::Relaxed) == 0 {
+            return None;
+        }
+        CURRENT.try_with(|current| *current.borrow()).ok().flatten()
+    }
+}
+
+/// Область `TraceContext::enter`; привязана к потоку
+#[must_use = "контекст действует, пока guard жив"]
+pub struct TraceGuard {
+    previous: Option<TraceContext>,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for TraceGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        let _ = CURRENT.try_with(|current| *current.borrow_mut() = previous);
+        ACTIVE.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn decode_hex(text: &str, out: &mut [u8]) -> Option<()> {
+    if text.len() != out.len() * 2 || !text.is_ascii() {
+        return None;
+    }
+    for (byte, pair) in out.iter_mut().zip(text.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+    }
+    Some(())
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(out, "{:02x}", byte);
+    }
+    out
+}
+
+// Добавляет trace_id и span_id, если их ещё нет (их мог поставить контекст задачи):
+// сначала из источника логгера, иначе из области потока
+pub(crate) fn with_trace<R>(
+    record: &Record,
+    source: Option<&TraceSource>,
+    f: impl FnOnce(&Record) -> R,
+) -> R {
+    if source.is_none() && ACTIVE.load(Ordering::Relaxed) == 0 {
+        return f(record);
+    }
+    if record
+        .fields
+        .iter()
+        .any(|(key, _)| *key == TraceContext::TRACE_FIELD)
+    {
+        return f(record);
+    }
+    let context = source.and_then(|source| source()).or_else(|| {
+        if ACTIVE.load(Ordering::Relaxed) == 0 {
+            return None;
+        }
+        CURRENT.try_with(|current| *current.borrow()).ok().flatten()
+    });
+    let Some(context) = context.filter(TraceContext::is_valid) else {
+        return f(record);
+    };
+    let (trace_id, span_id) = (context.trace_id_hex(), context.span_id_hex());
+    let fields: Vec<(&str, &str)> = record
+        .fields
+        .iter()
+        .copied()
+        .chain([
+            (TraceContext::TRACE_FIELD, trace_id.as_str()),
+            (TraceContext::SPAN_FIELD, span_id.as_str()),
+        ])
+        .collect();
+    f(&Record {
+        fields: &fields,
+        ..*record
+    })
+}
@@ -0,0 +1,139 @@
+//! Запись активного файла через io_uring (фича `io_uring`, только Linux). Каждый
+//! сброшенный буфер уходит в кольцо отправки без ожидания; завершения разбираются
+//! при следующих записях, `flush` ждёт все. Пишется по явным смещениям через
+//! отдельный дескриптор без `O_APPEND`, поэтому порядок завершений не важен.
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+use io_uring::{opcode, types, IoUring};
+
+pub(crate) struct Ring {
+    ring: IoUring,
+    file: File,  // тот же файл без O_APPEND
+    offset: u64, // куда пойдёт следующий буфер
+    next_id: u64,
+    in_flight: HashMap<u64, (Vec<u8>, u64)>, // буфер и его смещение до завершения
+    spare: Vec<Vec<u8>>,                     // буферы завершённых записей
+    error: Option<io::Error>,                // первая ошибка завершения
+}
+
+impl Ring {
+    const ENTRIES: u32 = 64;
+
+    // Err — ядро без io_uring или он запрещён (seccomp в контейнерах)
+    pub fn new(path: &Path, active: &File) -> io::Result<Self> {
+        let ring = IoUring::new(Self::ENTRIES)?;
+        let file = OpenOptions::new().write(true).open(path)?;
+        Ok(Ring {
+            ring,
+            file,
+            offset: active.metadata()?.len(),
+            next_id: 0,
+            in_flight: HashMap::new(),
+            spare: Vec::new(),
+            error: None,
+        })
+    }
+
+    pub fn write(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.check()?;
+        if self.in_flight.len() >= Self::ENTRIES as usize {
+            self.wait(1)?;
+            self.check()?;
+        }
+        let mut data = self.spare.pop().unwrap_or_default();
+        data.clear();
+        data.extend_from_slice(buf);
+
+        let id = self.next_id;
+        self.next_id += 1;
+        let entry = opcode::Write::new(
+            types::Fd(self.file.as_raw_fd()),
+            data.as_ptr(),
+            data.len() as u32,
+        )
+        .offset(self.offset)
+        .build()
+        .user_data(id);
+        // Буфер живёт в in_flight до завершения; в очереди место есть — записей в
+        // полёте меньше ENTRIES
+        self.in_flight.insert(id, (data, self.offset));
+        if unsafe { self.ring.submission().push(&entry) }.is_err() {
+            let _ = self.in_flight.remove(&id);
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "io_uring submission queue is full",
+            ));
+        }
+        self.offset += buf.len() as u64;
+        self.ring.submit()?;
+        self.reap();
+        Ok(())
+    }
+
+    // Дожидается всех отправленных записей
+    pub fn drain(&mut self) -> io::Result<()> {
+        while !self.in_flight.is_empty() {
+            self.wait(1)?;
+        }
+        self.check()
+    }
+
+    fn wait(&mut self, want: usize) -> io::Result<()> {
+        loop {
+            match self.ring.submit_and_wait(want) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+                Ok(_) => break,
+            }
+        }
+        self.reap();
+        Ok(())
+    }
+
+    // Разбирает готовые завершения; недописанный хвост дописывается обычным pwrite
+    fn reap(&mut self) {
+        let done: Vec<(u64, i32)> = self
+            .ring
+            .completion()
+            .map(|cqe| (cqe.user_data(), cqe.result()))
+            .collect();
+        for (id, result) in done {
+            let Some((data, offset)) = self.in_flight.remove(&id) else {
+                continue;
+            };
+            let outcome = match usize::try_from(result) {
+                Ok(written) if written < data.len() => self
+                    .file
+                    .write_all_at(&data[written..], offset + written as u64),
+                Ok(_) => Ok(()),
+                Err(_) => Err(io::Error::from_raw_os_error(-result)),
+            };
+            if let Err(e) = outcome {
+                self.error.get_or_insert(e);
+            }
+            self.spare.push(data);
+        }
+    }
+
+    fn check(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Ring {
+    fn drop(&mut self) {
+        // Ядро может ещё читать буферы: если дождаться не вышло, не освобождаем их
+        if self.drain().is_err() && !self.in_flight.is_empty() {
+            std::mem::forget(std::mem::take(&mut self.in_flight));
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! Оповещения в чат через webhook: Slack, Teams или свой сервис (фича `webhook`).
+
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+
+use crate::{LogLevel, Record, Sink};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Шаблон по умолчанию — формат входящих webhook'ов Slack и Teams
+pub const DEFAULT_TEMPLATE: &str = r#"{"text": "{text}"}"#;
+
+/// Отправляет записи Error и Fatal (или уровня из `min_level`) POST-запросом с JSON
+/// по шаблону. Записи копятся `batch_interval` и уходят одним запросом, а запросы
+/// идут не чаще раза в `rate_limit`. Отправка — в фоновом потоке.
+///
+/// Подстановки в шаблоне (значения экранируются для JSON-строки):
+/// `{text}` — записи пачки по строке на каждую, `{count}` — их число,
+/// `{level}` — самый высокий уровень в пачке, `{host}` — имя хоста.
+pub struct WebhookSink {
+    config: WebhookConfig,
+    sender: OnceLock<mpsc::Sender<Command>>,
+    failures: Arc<AtomicU64>,
+}
+
+#[derive(Clone)]
+struct WebhookConfig {
+    url: String,
+    template: String,
+    min_level: LogLevel,
+    batch_interval: Duration,
+    rate_limit: Duration,
+    max_batch: usize,
+}
+
+enum Command {
+    Record(LogLevel, String),
+    Flush(mpsc::Sender<()>),
+}
+
+impl WebhookSink {
+    pub fn new(url: &str) -> Self {
+        WebhookSink {
+            config: WebhookConfig {
+                url: url.to_owned(),
+                template: DEFAULT_TEMPLATE.to_owned(),
+                min_level: LogLevel::Error,
+                batch_interval: Duration::from_secs(5),
+                rate_limit: Duration::from_secs(30),
+                max_batch: 20,
+            },
+            sender: OnceLock::new(),
+            failures: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// JSON-шаблон запроса, например `{"content": "{text}"}` для Discord.
+    pub fn template(mut self, template: &str) -> Self {
+        self.config.template = template.to_owned();
+        self
+    }
+
+    pub fn min_level(mut self, level: LogLevel) -> Self {
+        self.config.min_level = level;
+        self
+    }
+
+    /// Сколько ждать остальные записи пачки после первой (по умолчанию 5 с).
+    pub fn batch_interval(mut self, interval: Duration) -> Self {
+        self.config.batch_interval = interval;
+        self
+    }
+
+    /// Не чаще одного запроса за `interval` (по умолчанию 30 с).
+    pub fn rate_limit(mut self, interval: Duration) -> Self {
+        self.config.rate_limit = interval;
+        self
+    }
+
+    /// Сколько записей показывать в одном запросе (по умолчанию 20); об остальных
+    /// пишется только их число.
+    pub fn max_batch(mut self, records: usize) -> Self {
+        self.config.max_batch = records.max(1);
+        self
+    }
+
+    /// Сколько запросов не удалось отправить
+    pub fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+
+    // Поток отправки запускается при первой записи, когда настройки уже заданы
+    fn sender(&self) -> &mpsc::Sender<Command> {
+        self.sender.get_or_init(|| {
+            let (sender, receiver) = mpsc::channel();
+            let config = self.config.clone();
+            let failures = Arc::clone(&self.failures);
+            std::thread::spawn(move || run_worker(config, receiver, failures));
+            sender
+        })
+    }
+}
+
+impl Sink for WebhookSink {
+    fn write(&self, record: &Record) -> io::Result<()> {
+        if record.level() < self.config.min_level {
+            return Ok(());
+        }
+        let mut line = format!(
+            "{} {}",
+            record
+                .time()
+                .unwrap_or_else(Local::now)
+                .format("%Y-%m-%d %H:%M:%S"),
+            record.level()
+        );
+        if !record.target().is_empty() {
+            line.push_str(&format!(" {}", record.target()));
+        }
+        line.push_str(&format!(": {}", record.args()));
+        for (key, value) in record.fields() {
+            line.push_str(&format!(" {}={}", key, value));
+        }
+        self.sender()
+            .send(Command::Record(record.level(), line))
+            .map_err(|_| io::Error::other("webhook worker has stopped"))
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        let Some(sender) = self.sender.get() else {
+            return Ok(());
+        };
+        let (ack, done) = mpsc::channel();
+        if sender.send(Command::Flush(ack)).is_ok() {
+            let _ = done.recv();
+        }
+        Ok(())
+    }
+}
+
+// Пачка записей, ждущих отправки
+struct Batch {
+    started: Instant,
+    lines: Vec<String>,
+    skipped: usize,
+    level: LogLevel,
+}
+
+fn run_worker(config: WebhookConfig, receiver: mpsc::Receiver<Command>, failures: Arc<AtomicU64>) {
+    let agent = ureq::AgentBuilder::new().timeout(REQUEST_TIMEOUT).build();
+    let mut batch: Option<Batch> = None;
+    let mut last_post: Option<Instant> = None;
+    loop {
+        let command = match batch {
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            Some(ref batch) => {
+                let mut due = batch.started + config.batch_interval;
+                if let Some(last) = last_post {
+                    due = due.max(last + config.rate_limit);
+                }
+                receiver.recv_timeout(due.saturating_duration_since(Instant::now()))
+            }
+        };
+        match command {
+            Ok(Command::Record(level, line)) => {
+                let batch = batch.get_or_insert_with(|| Batch {
+                    started: Instant::now(),
+                    lines: Vec::new(),
+                    skipped: 0,
+                    level,
+                });
+                batch.level = batch.level.max(level);
+                if batch.lines.len() < config.max_batch {
+                    batch.lines.push(line);
+                } else {
+                    batch.skipped += 1;
+                }
+            }
+            Ok(Command::Flush(ack)) => {
+                if let Some(batch) = batch.take() {
+                    post(&agent, &config, &batch, &failures);
+                    last_post = Some(Instant::now());
+                }
+                let _ = ack.send(());
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                if let Some(batch) = batch.take() {
+                    post(&agent, &config, &batch, &failures);
+                    last_post = Some(Instant::now());
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                if let Some(batch) = batch.take() {
+                    post(&agent, &config, &batch, &failures);
+                }
+                return;
+            }
+        }
+    }
+}
+
+fn post(agent: &ureq::Agent, config: &WebhookConfig, batch: &Batch, failures: &AtomicU64) {
+    let mut text = batch.lines.join("\n");
+    if batch.skipped > 0 {
+        text.push_str(&format!("\n... and {} more", batch.skipped));
+    }
+    let count = (batch.lines.len() + batch.skipped).to_string();
+    let host = json_escape(&crate::hostname());
+    let text = json_escape(&text);
+    let body = render(
+        &config.template,
+        &[
+            ("text", &text),
+            ("count", &count),
+            ("level", batch.level.as_str()),
+            ("host", &host),
+        ],
+    );
+    let result = agent
+        .post(&config.url)
+        .set("Content-Type", "application/json")
+        .send_string(&body);
+    if result.is_err() {
+        failures.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// Подстановка за один проход: "{text}" внутри самих записей не раскрывается
+fn render(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = rest.find('}').and_then(|end| {
+            let name = &rest[1..end];
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| (*value, end))
+        });
+        match value {
+            Some((value, end)) => {
+                out.push_str(value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// Содержимое JSON-строки без кавычек
+fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
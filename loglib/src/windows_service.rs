@@ -0,0 +1,105 @@
+//! Помощники для Windows-служб: запись переходов состояния SCM и сброс логгера
+//! при остановке службы или закрытии консоли, чтобы последние строки не терялись.
+
+use std::io;
+
+use windows_sys::Win32::Foundation::{BOOL, FALSE, TRUE};
+use windows_sys::Win32::System::Console::{
+    SetConsoleCtrlHandler, CTRL_BREAK_EVENT, CTRL_CLOSE_EVENT, CTRL_C_EVENT, CTRL_LOGOFF_EVENT,
+    CTRL_SHUTDOWN_EVENT,
+};
+use windows_sys::Win32::System::Services::{
+    SERVICE_CONTROL_CONTINUE, SERVICE_CONTROL_PAUSE, SERVICE_CONTROL_PRESHUTDOWN,
+    SERVICE_CONTROL_SHUTDOWN, SERVICE_CONTROL_STOP,
+};
+
+use crate::{LogLevel, Logger, GLOBAL_LOGGER};
+
+/// Переход состояния службы
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceEvent {
+    Start,
+    Stop,
+    Pause,
+    Continue,
+    /// Выключение системы (SERVICE_CONTROL_SHUTDOWN / PRESHUTDOWN)
+    Shutdown,
+}
+
+impl ServiceEvent {
+    /// Событие по коду управления из обработчика службы (`SERVICE_CONTROL_*`).
+    /// Для остальных кодов (INTERROGATE и т.п.) — `None`.
+    pub fn from_control(control: u32) -> Option<Self> {
+        match control {
+            SERVICE_CONTROL_STOP => Some(ServiceEvent::Stop),
+            SERVICE_CONTROL_PAUSE => Some(ServiceEvent::Pause),
+            SERVICE_CONTROL_CONTINUE => Some(ServiceEvent::Continue),
+            SERVICE_CONTROL_SHUTDOWN | SERVICE_CONTROL_PRESHUTDOWN => Some(ServiceEvent::Shutdown),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            ServiceEvent::Start => "started",
+            ServiceEvent::Stop => "stopping",
+            ServiceEvent::Pause => "paused",
+            ServiceEvent::Continue => "resumed",
+            ServiceEvent::Shutdown => "stopping (system shutdown)",
+        }
+    }
+}
+
+/// Пишет переход в файл и в Event Log. На `Stop` и `Shutdown` сразу сбрасывает логгер:
+/// после ответа SCM процесс могут завершить в любой момент.
+pub fn log_transition(logger: &Logger, event: ServiceEvent) {
+    let message = format!("Service {}: {}", logger.app_name(), event.as_str());
+    logger.log(LogLevel::Info, format_args!("{}", message));
+    logger.platform_log(LogLevel::Info, &message);
+    if matches!(event, ServiceEvent::Stop | ServiceEvent::Shutdown) {
+        logger.flush();
+    }
+}
+
+/// Для обработчика управления службой: записывает известный переход и возвращает его.
+/// ```ignore
+/// let event = loglib::windows_service::handle_control(&logger, control);
+/// if event == Some(ServiceEvent::Stop) { /* остановить работу */ }
+/// ```
+pub fn handle_control(logger: &Logger, control: u32) -> Option<ServiceEvent> {
+    let event = ServiceEvent::from_control(control)?;
+    log_transition(logger, event);
+    Some(event)
+}
+
+/// Ставит обработчик Ctrl+C, закрытия консоли, выхода из системы и выключения,
+/// который сбрасывает глобальный логгер. Обработка по умолчанию (завершение процесса)
+/// после этого продолжается.
+pub fn install_console_ctrl_handler() -> io::Result<()> {
+    if unsafe { SetConsoleCtrlHandler(Some(console_ctrl_handler), TRUE) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+unsafe extern "system" fn console_ctrl_handler(ctrl_type: u32) -> BOOL {
+    let reason = match ctrl_type {
+        CTRL_C_EVENT => "Ctrl+C",
+        CTRL_BREAK_EVENT => "Ctrl+Break",
+        CTRL_CLOSE_EVENT => "console closed",
+        CTRL_LOGOFF_EVENT => "user logoff",
+        CTRL_SHUTDOWN_EVENT => "system shutdown",
+        _ => return FALSE,
+    };
+    // обработчик вызывается в отдельном потоке, блокировка здесь безопасна
+    if let Ok(global) = GLOBAL_LOGGER.lock() {
+        if let Some(ref logger) = *global {
+            logger.log(
+                LogLevel::Info,
+                format_args!("Received {}, flushing log", reason),
+            );
+            logger.flush();
+        }
+    }
+    FALSE
+}
@@ -0,0 +1,24 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Свой каталог на каждый тест: тесты идут параллельно
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("loglib-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// Содержимое всех файлов лога в каталоге, по строкам
+#[allow(dead_code)]
+pub fn all_lines(dir: &Path) -> Vec<String> {
+    let mut lines = Vec::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_file() {
+            let text = fs::read_to_string(&path).unwrap_or_default();
+            lines.extend(text.lines().map(str::to_owned));
+        }
+    }
+    lines
+}
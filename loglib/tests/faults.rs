@@ -0,0 +1,188 @@
+mod common;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::{all_lines, scratch_dir};
+use loglib::test::FaultInjector;
+use loglib::{LogLevel, Logger, LoggerBuilder, RetryPolicy};
+
+fn builder(dir: &Path, max_size: u64, faults: &Arc<FaultInjector>) -> LoggerBuilder {
+    Logger::builder()
+        .file(dir, "app.log", max_size, 3)
+        .system_log(false)
+        .fault_injection(Arc::clone(faults))
+}
+
+fn contains(lines: &[String], message: &str) -> bool {
+    lines.iter().any(|line| line.ends_with(message))
+}
+
+#[test]
+fn disk_full_fails_writes_until_space_returns() {
+    let dir = scratch_dir("faults-disk-full");
+    let faults = Arc::new(FaultInjector::new());
+    let logger = builder(&dir, 1 << 20, &faults).build().unwrap();
+
+    logger.try_write(LogLevel::Info, "before").unwrap();
+    faults.set_disk_full(true);
+    let error = logger.try_write(LogLevel::Info, "lost").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::StorageFull);
+    assert_eq!(faults.injected(), 1);
+    assert_eq!(logger.stats().file_errors, 1);
+
+    faults.set_disk_full(false);
+    logger.try_write(LogLevel::Info, "after").unwrap();
+    logger.try_flush().unwrap();
+
+    let lines = all_lines(&dir);
+    assert!(contains(&lines, "before"));
+    assert!(contains(&lines, "after"));
+    assert!(!contains(&lines, "lost"));
+
+    drop(logger);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn every_nth_write_fails() {
+    let dir = scratch_dir("faults-nth-write");
+    let faults = Arc::new(FaultInjector::new());
+    let logger = builder(&dir, 1 << 20, &faults).build().unwrap();
+
+    faults.fail_every_nth_write(3);
+    let failed = (0..9)
+        .filter(|i| {
+            logger
+                .try_write(LogLevel::Info, &format!("record {}", i))
+                .is_err()
+        })
+        .count();
+    assert_eq!(failed, 3);
+    assert_eq!(faults.injected(), 3);
+
+    faults.fail_every_nth_write(0);
+    logger.try_flush().unwrap();
+    let lines = all_lines(&dir);
+    for i in [0, 1, 3, 4, 6, 7] {
+        assert!(contains(&lines, &format!("record {}", i)));
+    }
+
+    drop(logger);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// Переименование при ротации не удалось: записи продолжают идти в активный файл,
+// а когда переименование снова проходит, ротация выполняется
+#[test]
+fn failed_rename_keeps_active_file() {
+    let dir = scratch_dir("faults-rename");
+    let faults = Arc::new(FaultInjector::new());
+    let logger = builder(&dir, 1024, &faults).build().unwrap();
+
+    faults.set_fail_renames(true);
+    for i in 0..50 {
+        logger
+            .try_write(LogLevel::Info, &format!("kept {}", i))
+            .unwrap();
+    }
+    logger.try_flush().unwrap();
+    assert!(faults.injected() > 0);
+    assert_eq!(logger.stats().rotations, 0);
+    let active = fs::read_to_string(dir.join("app.log")).unwrap();
+    assert_eq!(active.lines().filter(|l| l.contains(" kept ")).count(), 50);
+
+    faults.set_fail_renames(false);
+    logger.try_write(LogLevel::Info, "rotated").unwrap();
+    logger.try_flush().unwrap();
+    assert_eq!(logger.stats().rotations, 1);
+
+    drop(logger);
+    let lines = all_lines(&dir);
+    assert!(contains(&lines, "kept 49"));
+    assert!(contains(&lines, "rotated"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// Файл не открылся после ротации: записи ждут в памяти и дописываются,
+// как только файл снова открывается
+#[test]
+fn failed_open_keeps_records_until_recovery() {
+    let dir = scratch_dir("faults-open");
+    let faults = Arc::new(FaultInjector::new());
+    let logger = builder(&dir, 1 << 20, &faults)
+        .retry_policy(RetryPolicy {
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(10),
+            ..RetryPolicy::default()
+        })
+        .build()
+        .unwrap();
+
+    faults.set_fail_opens(true);
+    assert!(logger.rotate().is_err());
+    for i in 0..5 {
+        assert!(logger
+            .try_write(LogLevel::Info, &format!("waiting {}", i))
+            .is_err());
+    }
+    assert_eq!(logger.stats().buffered_records, 5);
+
+    // Следующая попытка открыть файл — не раньше паузы из RetryPolicy
+    faults.set_fail_opens(false);
+    std::thread::sleep(Duration::from_millis(50));
+    logger.try_write(LogLevel::Info, "recovered").unwrap();
+    logger.try_flush().unwrap();
+    assert_eq!(logger.stats().buffered_records, 0);
+
+    let active = fs::read_to_string(dir.join("app.log")).unwrap();
+    let order: Vec<&str> = active
+        .lines()
+        .filter_map(|line| {
+            ["waiting 0", "waiting 4", "recovered"]
+                .into_iter()
+                .find(|message| line.ends_with(message))
+        })
+        .collect();
+    assert_eq!(order, ["waiting 0", "waiting 4", "recovered"]);
+
+    drop(logger);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// Места меньше порога: Trace/Debug отбрасываются, остальное пишется
+#[test]
+fn low_disk_space_drops_debug_records() {
+    let dir = scratch_dir("faults-free-space");
+    let plain = Logger::builder()
+        .file(&dir, "plain.log", 1 << 20, 3)
+        .system_log(false)
+        .build()
+        .unwrap();
+    plain
+        .try_write(LogLevel::Debug, "debug is written")
+        .unwrap();
+    drop(plain);
+
+    let logger = Logger::builder()
+        .file(&dir, "app.log", 1 << 20, 3)
+        .system_log(false)
+        .min_free_space(u64::MAX)
+        .build()
+        .unwrap();
+
+    logger.try_write(LogLevel::Debug, "noise").unwrap();
+    logger.try_write(LogLevel::Error, "important").unwrap();
+    logger.try_flush().unwrap();
+
+    let lines = all_lines(&dir);
+    assert!(contains(&lines, "debug is written"));
+    assert!(contains(&lines, "important"));
+    assert!(!contains(&lines, "noise"));
+
+    drop(logger);
+    let _ = fs::remove_dir_all(&dir);
+}
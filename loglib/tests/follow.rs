@@ -0,0 +1,84 @@
+mod common;
+
+use std::fs;
+
+use common::scratch_dir;
+use loglib::reader::LogReader;
+use loglib::{LogLevel, Logger};
+
+fn messages(follow: &mut loglib::reader::Follow) -> Vec<String> {
+    let mut messages = Vec::new();
+    while let Some(entry) = follow.try_next() {
+        messages.push(entry.unwrap().record.message);
+    }
+    messages
+}
+
+// Новый файл после ротации уже длиннее позиции чтения в старом: follow всё равно
+// должен заметить ротацию по смене файла, а не по размеру
+#[test]
+fn follow_picks_up_rotation_to_longer_file() {
+    let dir = scratch_dir("follow-rotation");
+    let logger = Logger::builder()
+        .file(&dir, "app.log", 1 << 20, 3)
+        .build()
+        .unwrap();
+    for i in 0..3 {
+        logger.log(LogLevel::Error, format_args!("before {}", i));
+    }
+    logger.try_flush().unwrap();
+
+    let mut follow = LogReader::follow(&dir, "app.log").unwrap();
+    assert!(messages(&mut follow).is_empty());
+
+    logger.rotate().unwrap();
+    for i in 0..50 {
+        logger.log(LogLevel::Error, format_args!("after {}", i));
+    }
+    logger.try_flush().unwrap();
+
+    let after: Vec<String> = messages(&mut follow)
+        .into_iter()
+        .filter(|message| message.starts_with("after"))
+        .collect();
+    let expected: Vec<String> = (0..50).map(|i| format!("after {}", i)).collect();
+    assert_eq!(after, expected);
+
+    drop(logger);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+// Усечение на месте (copytruncate): тот же файл, но короче позиции чтения
+#[test]
+fn follow_rereads_truncated_file() {
+    let dir = scratch_dir("follow-truncate");
+    let logger = Logger::builder()
+        .file(&dir, "app.log", 1 << 20, 3)
+        .build()
+        .unwrap();
+    for i in 0..20 {
+        logger.log(LogLevel::Error, format_args!("before {}", i));
+    }
+    logger.try_flush().unwrap();
+    drop(logger);
+
+    let mut follow = LogReader::follow(&dir, "app.log").unwrap();
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .open(dir.join("app.log"))
+        .unwrap();
+    file.set_len(0).unwrap();
+    drop(file);
+
+    let logger = Logger::builder()
+        .file(&dir, "app.log", 1 << 20, 3)
+        .build()
+        .unwrap();
+    logger.log(LogLevel::Error, format_args!("after truncate"));
+    logger.try_flush().unwrap();
+
+    assert!(messages(&mut follow).contains(&"after truncate".to_owned()));
+
+    drop(logger);
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -49,7 +49,7 @@ fn main() {
         }
     };
 
-    let _ = system_logger.platform_log(
+    system_logger.platform_log(
         loglib::LogLevel::Info,
         &format!("Starting {} v{}", APP_NAME, APP_VERSION),
     );
@@ -58,7 +58,7 @@ fn main() {
     let file_logger = match Logger::file_only("logs", "advanced.log", 1024 * 1024, 5) {
         Ok(l) => l,
         Err(e) => {
-            let _ = system_logger.platform_log(
+            system_logger.platform_log(
                 loglib::LogLevel::Error,
                 &format!("Failed to create log file: {}", e),
             );
@@ -93,7 +93,7 @@ fn main() {
     debug!(shared_logger, "All workers have finished");
 
     // 4. Финальная часть: завершение
-    let _ = system_logger.platform_log(
+    system_logger.platform_log(
         loglib::LogLevel::Info,
         "Application finished successfully",
     );
@@ -19,13 +19,13 @@ fn main() {
     };
 
     // ✅ Заменяем Info → Debug
-    let _ = logger.platform_log(LogLevel::Debug, &format!("Starting {} v{}", APP_NAME, APP_VERSION));
+    logger.platform_log(LogLevel::Debug, &format!("Starting {} v{}", APP_NAME, APP_VERSION));
 
     // 2. Инициализация
     let file_logger = match Logger::file_only("logs", "advanced.log", 1024 * 1024, 5) {
         Ok(l) => l,
         Err(e) => {
-            let _ = logger.platform_log(LogLevel::Error, &format!("Failed to create log file: {}", e));
+            logger.platform_log(LogLevel::Error, &format!("Failed to create log file: {}", e));
             std::process::exit(1);
         }
     };
@@ -37,7 +37,7 @@ fn main() {
     let config_ok = true;
     if !config_ok {
         fatal!(file_logger, "Configuration validation failed");
-        let _ = logger.platform_log(LogLevel::Error, "Application failed to initialize");
+        logger.platform_log(LogLevel::Error, "Application failed to initialize");
         std::process::exit(1);
     }
 
@@ -68,5 +68,5 @@ fn main() {
     error!(file_logger, "Finalizing with one last error log");
 
     // 4. Финальная часть
-    let _ = logger.platform_log(LogLevel::Debug, "Application finished successfully");
+    logger.platform_log(LogLevel::Debug, "Application finished successfully");
 }
\ No newline at end of file
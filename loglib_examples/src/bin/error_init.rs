@@ -15,14 +15,14 @@ fn main() {
         }
     };
 
-    let _ = logger.platform_log(LogLevel::Info, &format!("Starting {} v{}", APP_NAME, APP_VERSION));
+    logger.platform_log(LogLevel::Info, &format!("Starting {} v{}", APP_NAME, APP_VERSION));
 
     // 2. Инициализация — имитируем ошибку
     let log_dir = "/root/forbidden"; // Недоступная директория
     let file_logger = match Logger::file_only(log_dir, "error.log", 1024, 1) {
         Ok(l) => l,
         Err(e) => {
-            let _ = logger.platform_log(LogLevel::Error, &format!("Failed to open log file in {}: {}", log_dir, e));
+            logger.platform_log(LogLevel::Error, &format!("Failed to open log file in {}: {}", log_dir, e));
             std::process::exit(1);
         }
     };
@@ -31,7 +31,7 @@ fn main() {
     let enough_memory = false;
     if !enough_memory {
         fatal!(file_logger, "Insufficient memory to continue");
-        let _ = logger.platform_log(LogLevel::Error, "Application failed to initialize");
+        logger.platform_log(LogLevel::Error, "Application failed to initialize");
         std::process::exit(1);
     }
 
@@ -39,5 +39,5 @@ fn main() {
     debug!(file_logger, "This will not be logged");
 
     // 4. Финальная часть
-    let _ = logger.platform_log(LogLevel::Info, "Application finished successfully");
+    logger.platform_log(LogLevel::Info, "Application finished successfully");
 }
\ No newline at end of file
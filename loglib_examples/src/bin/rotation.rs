@@ -22,7 +22,7 @@ fn main() {
         }
     };
 
-    let _ = system_logger.platform_log(
+    system_logger.platform_log(
         loglib::LogLevel::Debug,
         &format!("Starting {} v{}", APP_NAME, APP_VERSION),
     );
@@ -31,7 +31,7 @@ fn main() {
     let file_logger = match Logger::file_only("logs", "rotation.log", MAX_LOG_SIZE, MAX_LOG_FILES) {
         Ok(l) => l,
         Err(e) => {
-            let _ = system_logger.platform_log(
+            system_logger.platform_log(
                 loglib::LogLevel::Error,
                 &format!("Failed to create log file: {}", e),
             );
@@ -68,7 +68,7 @@ fn main() {
     }
 
     // 4. Финальная часть
-    let _ = system_logger.platform_log(
+    system_logger.platform_log(
         loglib::LogLevel::Debug,
         "Application finished successfully",
     );
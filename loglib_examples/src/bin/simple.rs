@@ -15,13 +15,13 @@ fn main() {
         }
     };
 
-    let _ = logger.platform_log(LogLevel::Info, &format!("Starting {} v{}", APP_NAME, APP_VERSION));
+    logger.platform_log(LogLevel::Info, &format!("Starting {} v{}", APP_NAME, APP_VERSION));
 
     // 2. Инициализация: открываем файловый лог
     let file_logger = match Logger::file_only("logs", "simple.log", 1024 * 1024, 3) {
         Ok(l) => l,
         Err(e) => {
-            let _ = logger.platform_log(LogLevel::Error, &format!("Failed to open log file: {}", e));
+            logger.platform_log(LogLevel::Error, &format!("Failed to open log file: {}", e));
             std::process::exit(1);
         }
     };
@@ -29,7 +29,7 @@ fn main() {
     // Фатальные ошибки инициализации пишем в файл (но файл уже открыт)
     if false {
         fatal!(file_logger, "Simulated fatal during init");
-        let _ = logger.platform_log(LogLevel::Error, "Application failed to initialize");
+        logger.platform_log(LogLevel::Error, "Application failed to initialize");
         std::process::exit(1);
     }
 
@@ -41,5 +41,5 @@ fn main() {
     debug!(file_logger, "Processing data block #2");
 
     // 4. Финальная часть
-    let _ = logger.platform_log(LogLevel::Info, "Application finished successfully");
+    logger.platform_log(LogLevel::Info, "Application finished successfully");
 }
\ No newline at end of file
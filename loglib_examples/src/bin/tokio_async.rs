@@ -26,7 +26,7 @@ impl Worker {
         // Имитация асинхронной работы
         sleep(Duration::from_millis(50 + (self.id as u64) * 100)).await;
 
-        if self.id % 3 == 0 {
+        if self.id.is_multiple_of(3) {
             warning!(self.log, "Worker {} has high priority task", self.id);
         }
 
@@ -49,7 +49,7 @@ async fn main() {
         }
     };
 
-    let _ = system_logger.platform_log(
+    system_logger.platform_log(
         loglib::LogLevel::Info,
         &format!("Starting {} v{}", APP_NAME, APP_VERSION),
     );
@@ -58,7 +58,7 @@ async fn main() {
     let file_logger = match Logger::file_only("logs", "tokio.log", 1024 * 1024, 5) {
         Ok(l) => l,
         Err(e) => {
-            let _ = system_logger.platform_log(
+            system_logger.platform_log(
                 loglib::LogLevel::Error,
                 &format!("Failed to open log file: {}", e),
             );
@@ -91,7 +91,7 @@ async fn main() {
     debug!(shared_logger, "All async tasks completed");
 
     // 4. Финальная часть
-    let _ = system_logger.platform_log(
+    system_logger.platform_log(
         loglib::LogLevel::Info,
         "Application finished successfully",
     );
@@ -2,18 +2,22 @@
 //!
 //! Простая библиотека для записи в Windows Event Log.
 //! Если кастомный источник не зарегистрирован — использует "Application" с префиксом.
+//!
+//! Чтобы Event Viewer показывал текст сообщений, зарегистрируйте источник с файлом сообщений:
+//! `install_source_generic("MyService", "Application")` или своим ресурсом из `res/messages.mc`.
+
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+pub use windows::{install_source, source_log, uninstall_source, WinEventLogger};
+
+// На остальных платформах — заглушка с тем же API, которая ничего не делает
+#[cfg(not(windows))]
+mod stub;
+#[cfg(not(windows))]
+pub use stub::{install_source, source_log, uninstall_source, WinEventLogger};
 
-use windows_sys::core::PCSTR;
-use windows_sys::Win32::Foundation::{HANDLE, HMODULE, PSID};
-use windows_sys::Win32::System::EventLog::{
-    DeregisterEventSource,
-    RegisterEventSourceA,
-    ReportEventA,
-    EVENTLOG_ERROR_TYPE,
-    EVENTLOG_WARNING_TYPE,
-    EVENTLOG_INFORMATION_TYPE,
-};
-use std::ffi::CString;
+use std::fmt;
 
 #[derive(Debug, Clone, Copy)]
 pub enum LogLevel {
@@ -25,76 +29,74 @@ pub enum LogLevel {
     Fatal,
 }
 
-impl LogLevel {
-    fn to_event_type(self) -> u16 {
-        match self {
-            LogLevel::Trace | LogLevel::Debug | LogLevel::Info => EVENTLOG_INFORMATION_TYPE,
-            LogLevel::Warning => EVENTLOG_WARNING_TYPE,
-            LogLevel::Error | LogLevel::Fatal => EVENTLOG_ERROR_TYPE,
-        }
-    }
-}
-#[derive(Debug, Clone)] // Добавили Clone
-pub struct WinEventLogger {
-    preferred_source: String,
-    fallback_source: &'static str,
+/// Ошибка записи в Event Log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WinlogError {
+    /// Строка содержит нулевой символ
+    InvalidString,
+    /// Источник не зарегистрирован в журнале, заданном через `with_log`
+    WrongLog,
+    /// Код `GetLastError()`
+    Win32(u32),
 }
 
-impl WinEventLogger {
-    pub fn new(preferred_source: &str) -> Self {
-        Self {
-            preferred_source: preferred_source.to_owned(),
-            fallback_source: "Application",
+// Коды Win32, которые стоит назвать словами
+const ERROR_ACCESS_DENIED: u32 = 5;
+const ERROR_INVALID_HANDLE: u32 = 6;
+const ERROR_INVALID_PARAMETER: u32 = 87;
+const RPC_S_SERVER_UNAVAILABLE: u32 = 1722; // служба Event Log не запущена
+
+impl WinlogError {
+    /// Код Win32, если он есть
+    pub fn code(&self) -> Option<u32> {
+        match self {
+            WinlogError::Win32(code) => Some(*code),
+            WinlogError::InvalidString | WinlogError::WrongLog => None,
         }
     }
+}
 
-    pub fn report(&self, level: LogLevel, message: &str) {
-        if self.try_report(&self.preferred_source, level, message) {
-            return;
+impl fmt::Display for WinlogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WinlogError::InvalidString => write!(f, "string contains a NUL character"),
+            WinlogError::WrongLog => {
+                write!(f, "event source is not registered in the requested log")
+            }
+            WinlogError::Win32(code) => {
+                let reason = match *code {
+                    ERROR_ACCESS_DENIED => "access denied",
+                    ERROR_INVALID_HANDLE => "invalid event source handle",
+                    ERROR_INVALID_PARAMETER => "invalid parameter (message too long?)",
+                    RPC_S_SERVER_UNAVAILABLE => "event log service unavailable",
+                    _ => "Win32 error",
+                };
+                write!(f, "{} (code {})", reason, code)
+            }
         }
-
-        let prefixed = format!("[{}] {}", self.preferred_source, message);
-        let _ = self.try_report(self.fallback_source, level, &prefixed);
     }
+}
 
-    fn try_report(&self, source: &str, level: LogLevel, message: &str) -> bool {
-        let c_source = match to_cstring(source) {
-            Some(s) => s,
-            None => return false,
-        };
-        let c_message = match to_cstring(message) {
-            Some(s) => s,
-            None => return false,
-        };
-
-        let source_ptr: PCSTR = c_source.as_ptr() as _;
-        let msg_ptr: PCSTR = c_message.as_ptr() as _;
+impl std::error::Error for WinlogError {}
 
-        let h_source = unsafe { RegisterEventSourceA(std::ptr::null(), source_ptr) };
-        if h_source == 0 {
-            return false;
-        }
+/// Файл сообщений из .NET Framework, где для любого Event ID текст равен `%1`.
+/// Есть почти на любой Windows; с ним Event Viewer показывает сообщение без
+/// "The description for Event ID ... cannot be found".
+pub const GENERIC_MESSAGE_FILE: &str =
+    "%SystemRoot%\\Microsoft.NET\\Framework\\v4.0.30319\\EventLogMessages.dll";
 
-        let success: i32 = unsafe {
-            ReportEventA(
-                h_source,
-                level.to_event_type(),
-                0,
-                1000,
-                0 as PSID,
-                1,
-                0,
-                &msg_ptr,
-                std::ptr::null_mut(),
-            )
-        };
-
-        let _ = unsafe { DeregisterEventSource(h_source) };
+/// Путь к текущему исполняемому файлу — для EventMessageFile, если в него встроен
+/// ресурс из `res/messages.mc` (Event ID 1000–1005, текст `%1`).
+pub fn current_exe_message_file() -> std::io::Result<String> {
+    Ok(std::env::current_exe()?.to_string_lossy().into_owned())
+}
 
-        success != 0
-    }
+/// Регистрирует источник с универсальным файлом сообщений `GENERIC_MESSAGE_FILE`.
+pub fn install_source_generic(source: &str, log: &str) -> Result<(), WinlogError> {
+    install_source(source, GENERIC_MESSAGE_FILE, log)
 }
 
-fn to_cstring(s: &str) -> Option<CString> {
-    CString::new(s).ok()
-}
\ No newline at end of file
+/// Зарегистрирован ли источник в каком-либо журнале.
+pub fn is_registered(source: &str) -> bool {
+    source_log(source).is_some()
+}
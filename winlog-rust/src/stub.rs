@@ -0,0 +1,80 @@
+// Заглушка для не-Windows платформ: тот же API, события никуда не пишутся.
+// Позволяет собирать кроссплатформенный код без #[cfg] на каждом вызове.
+
+use crate::{LogLevel, WinlogError};
+
+#[derive(Debug, Clone)]
+pub struct WinEventLogger {
+    log_name: Option<String>,
+}
+
+impl WinEventLogger {
+    pub fn new(_preferred_source: &str) -> Self {
+        Self { log_name: None }
+    }
+
+    pub fn with_log(mut self, log_name: &str) -> Self {
+        self.log_name = Some(log_name.to_owned());
+        self
+    }
+
+    pub fn log_name(&self) -> Option<&str> {
+        self.log_name.as_deref()
+    }
+
+    pub fn with_user_sid(self, _enabled: bool) -> Self {
+        self
+    }
+
+    pub fn with_level_event(self, _level: LogLevel, _event_id: u32, _category: u16) -> Self {
+        self
+    }
+
+    pub fn report(&self, _level: LogLevel, _message: &str) {}
+
+    pub fn try_report_result(&self, _level: LogLevel, _message: &str) -> Result<(), WinlogError> {
+        Ok(())
+    }
+
+    pub fn report_with_strings(
+        &self,
+        _level: LogLevel,
+        _event_id: u32,
+        _strings: &[&str],
+    ) -> Result<(), WinlogError> {
+        Ok(())
+    }
+
+    pub fn report_event(
+        &self,
+        _level: LogLevel,
+        _event_id: u32,
+        _category: u16,
+        _strings: &[&str],
+    ) -> Result<(), WinlogError> {
+        Ok(())
+    }
+
+    pub fn report_with_data(
+        &self,
+        _level: LogLevel,
+        _event_id: u32,
+        _message: &str,
+        _data: &[u8],
+    ) -> Result<(), WinlogError> {
+        Ok(())
+    }
+}
+
+/// Журналов событий здесь нет — источник нигде не зарегистрирован.
+pub fn source_log(_source: &str) -> Option<String> {
+    None
+}
+
+pub fn install_source(_source: &str, _message_file: &str, _log: &str) -> Result<(), WinlogError> {
+    Ok(())
+}
+
+pub fn uninstall_source(_source: &str) -> Result<(), WinlogError> {
+    Ok(())
+}
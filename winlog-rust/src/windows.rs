@@ -0,0 +1,450 @@
+use std::sync::{Arc, Mutex, OnceLock};
+use windows_sys::core::PCWSTR;
+use windows_sys::Win32::Foundation::{
+    CloseHandle, GetLastError, ERROR_INVALID_PARAMETER, HANDLE, PSID,
+};
+use windows_sys::Win32::Security::{
+    CopySid, GetLengthSid, GetTokenInformation, TokenUser, TOKEN_QUERY, TOKEN_USER,
+};
+use windows_sys::Win32::System::EventLog::{
+    DeregisterEventSource, RegisterEventSourceW, ReportEventW, EVENTLOG_ERROR_TYPE,
+    EVENTLOG_INFORMATION_TYPE, EVENTLOG_WARNING_TYPE,
+};
+use windows_sys::Win32::System::Registry::{
+    RegCloseKey, RegCreateKeyExW, RegDeleteKeyW, RegEnumKeyExW, RegOpenKeyExW, RegSetValueExW,
+    HKEY, HKEY_LOCAL_MACHINE, KEY_READ, KEY_WRITE, REG_DWORD, REG_EXPAND_SZ,
+    REG_OPTION_NON_VOLATILE,
+};
+use windows_sys::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+use crate::{LogLevel, WinlogError};
+
+const DEFAULT_EVENT_ID: u32 = 1000;
+
+impl LogLevel {
+    fn to_event_type(self) -> u16 {
+        match self {
+            LogLevel::Trace | LogLevel::Debug | LogLevel::Info => EVENTLOG_INFORMATION_TYPE,
+            LogLevel::Warning => EVENTLOG_WARNING_TYPE,
+            LogLevel::Error | LogLevel::Fatal => EVENTLOG_ERROR_TYPE,
+        }
+    }
+}
+
+#[derive(Debug, Clone)] // Добавили Clone
+pub struct WinEventLogger {
+    preferred_source: String,
+    log_name: Option<String>, // журнал, в котором зарегистрирован источник
+    level_events: [(u32, u16); 6], // (event id, категория) по уровню
+    user_sid: Option<Arc<Vec<u8>>>, // SID учётной записи процесса, копия в своём буфере
+    // Клоны логгера делят одни и те же дескрипторы
+    preferred: Arc<CachedSource>,
+    fallback: Arc<CachedSource>,
+}
+
+impl WinEventLogger {
+    pub fn new(preferred_source: &str) -> Self {
+        Self {
+            preferred_source: preferred_source.to_owned(),
+            log_name: None,
+            level_events: [(DEFAULT_EVENT_ID, 0); 6],
+            user_sid: None,
+            preferred: Arc::new(CachedSource::new(preferred_source)),
+            fallback: Arc::new(CachedSource::new("Application")),
+        }
+    }
+
+    /// Писать в отдельный журнал (например, "MyService") вместо Application.
+    /// Журнал события Windows определяет по регистрации источника, поэтому источник должен
+    /// быть зарегистрирован именно в этом журнале. Иначе событие всё равно пишется туда,
+    /// где зарегистрирован источник, но `try_report_result` возвращает `WrongLog`.
+    pub fn with_log(mut self, log_name: &str) -> Self {
+        self.log_name = Some(log_name.to_owned());
+        self
+    }
+
+    pub fn log_name(&self) -> Option<&str> {
+        self.log_name.as_deref()
+    }
+
+    /// Указывать в событиях SID учётной записи, под которой работает процесс,
+    /// чтобы в Event Log было видно, от чьего имени записано событие.
+    /// Если SID получить не удалось, события пишутся без него.
+    pub fn with_user_sid(mut self, enabled: bool) -> Self {
+        self.user_sid = if enabled {
+            current_user_sid().map(Arc::new)
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Event ID и категория для записей уровня `level` (по умолчанию 1000 и 0),
+    /// чтобы события можно было фильтровать в Event Viewer.
+    pub fn with_level_event(mut self, level: LogLevel, event_id: u32, category: u16) -> Self {
+        self.level_events[level as usize] = (event_id, category);
+        self
+    }
+
+    pub fn report(&self, level: LogLevel, message: &str) {
+        let _ = self.try_report_result(level, message);
+    }
+
+    /// Как `report`, но возвращает причину, по которой запись не удалась.
+    /// Если не удалось записать и через "Application", возвращается ошибка основного источника.
+    pub fn try_report_result(&self, level: LogLevel, message: &str) -> Result<(), WinlogError> {
+        let (event_id, category) = self.level_events[level as usize];
+        self.report_event(level, event_id, category, &[message])
+    }
+
+    /// Событие с несколькими строками подстановки (%1, %2, ...) для зарегистрированного
+    /// файла сообщений. При записи через "Application" имя источника добавляется к первой строке.
+    pub fn report_with_strings(
+        &self,
+        level: LogLevel,
+        event_id: u32,
+        strings: &[&str],
+    ) -> Result<(), WinlogError> {
+        let (_, category) = self.level_events[level as usize];
+        self.report_event(level, event_id, category, strings)
+    }
+
+    /// Событие с явно заданными Event ID и категорией.
+    pub fn report_event(
+        &self,
+        level: LogLevel,
+        event_id: u32,
+        category: u16,
+        strings: &[&str],
+    ) -> Result<(), WinlogError> {
+        self.report_full(level, event_id, category, strings, &[])
+    }
+
+    /// Событие с двоичными данными (структура ошибки, кадр протокола). Данные видны
+    /// на вкладке "Details" и извлекаются через `wevtutil qe`.
+    pub fn report_with_data(
+        &self,
+        level: LogLevel,
+        event_id: u32,
+        message: &str,
+        data: &[u8],
+    ) -> Result<(), WinlogError> {
+        let (_, category) = self.level_events[level as usize];
+        self.report_full(level, event_id, category, &[message], data)
+    }
+
+    fn report_full(
+        &self,
+        level: LogLevel,
+        event_id: u32,
+        category: u16,
+        strings: &[&str],
+        data: &[u8],
+    ) -> Result<(), WinlogError> {
+        let log_check = self.check_log();
+        let error = match self.try_report(&self.preferred, level, event_id, category, strings, data)
+        {
+            Ok(()) => return log_check,
+            Err(e) => e,
+        };
+
+        let mut prefixed: Vec<String> = strings.iter().map(|s| s.to_string()).collect();
+        match prefixed.first_mut() {
+            Some(first) => *first = format!("[{}] {}", self.preferred_source, first),
+            None => prefixed.push(format!("[{}]", self.preferred_source)),
+        }
+        let prefixed: Vec<&str> = prefixed.iter().map(String::as_str).collect();
+        self.try_report(&self.fallback, level, event_id, category, &prefixed, data)
+            .map_err(|_| error)
+            .and(log_check)
+    }
+
+    // Проверяем один раз: регистрация источника меняется только установщиком
+    fn check_log(&self) -> Result<(), WinlogError> {
+        let expected = match self.log_name {
+            Some(ref log) => log,
+            None => return Ok(()),
+        };
+        let actual = self
+            .preferred
+            .log
+            .get_or_init(|| source_log(&self.preferred_source));
+        match actual {
+            Some(log) if log.eq_ignore_ascii_case(expected) => Ok(()),
+            _ => Err(WinlogError::WrongLog),
+        }
+    }
+
+    fn try_report(
+        &self,
+        source: &CachedSource,
+        level: LogLevel,
+        event_id: u32,
+        category: u16,
+        strings: &[&str],
+        data: &[u8],
+    ) -> Result<(), WinlogError> {
+        let data_len =
+            u32::try_from(data.len()).map_err(|_| WinlogError::Win32(ERROR_INVALID_PARAMETER))?;
+        let w_strings = strings
+            .iter()
+            .map(|s| to_wide(s).ok_or(WinlogError::InvalidString))
+            .collect::<Result<Vec<_>, _>>()?;
+        let string_ptrs: Vec<PCWSTR> = w_strings.iter().map(|s| s.as_ptr()).collect();
+        let num_strings = u16::try_from(string_ptrs.len())
+            .map_err(|_| WinlogError::Win32(ERROR_INVALID_PARAMETER))?;
+
+        // Дескриптор мог устареть (например, служба Event Log перезапускалась) —
+        // тогда регистрируем источник заново и пробуем ещё раз
+        let mut error = WinlogError::Win32(0);
+        for _ in 0..2 {
+            let mut handle = source.handle.lock().unwrap();
+            if *handle == 0 {
+                *handle = source.register()?;
+            }
+
+            let success: i32 = unsafe {
+                ReportEventW(
+                    *handle,
+                    level.to_event_type(),
+                    category,
+                    event_id,
+                    self.user_sid
+                        .as_ref()
+                        .map_or(std::ptr::null_mut(), |sid| sid.as_ptr() as PSID),
+                    num_strings,
+                    data_len,
+                    if string_ptrs.is_empty() {
+                        std::ptr::null()
+                    } else {
+                        string_ptrs.as_ptr()
+                    },
+                    if data.is_empty() {
+                        std::ptr::null()
+                    } else {
+                        data.as_ptr() as _
+                    },
+                )
+            };
+            if success != 0 {
+                return Ok(());
+            }
+            error = WinlogError::last();
+
+            unsafe { DeregisterEventSource(*handle) };
+            *handle = 0;
+        }
+        Err(error)
+    }
+}
+
+// Источник событий, регистрируется при первой записи и освобождается в Drop
+#[derive(Debug)]
+struct CachedSource {
+    name: String,
+    handle: Mutex<HANDLE>,         // 0 — не зарегистрирован
+    log: OnceLock<Option<String>>, // журнал из реестра, для with_log
+}
+
+impl CachedSource {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            handle: Mutex::new(0),
+            log: OnceLock::new(),
+        }
+    }
+
+    fn register(&self) -> Result<HANDLE, WinlogError> {
+        let w_name = to_wide(&self.name).ok_or(WinlogError::InvalidString)?;
+        let handle = unsafe { RegisterEventSourceW(std::ptr::null(), w_name.as_ptr()) };
+        if handle == 0 {
+            return Err(WinlogError::last());
+        }
+        Ok(handle)
+    }
+}
+
+impl Drop for CachedSource {
+    fn drop(&mut self) {
+        let handle = *self.handle.get_mut().unwrap_or_else(|e| e.into_inner());
+        if handle != 0 {
+            unsafe { DeregisterEventSource(handle) };
+        }
+    }
+}
+
+impl WinlogError {
+    fn last() -> Self {
+        WinlogError::Win32(unsafe { GetLastError() })
+    }
+}
+
+// UTF-16 с завершающим нулём; строки с нулём внутри Event Log обрезал бы молча
+fn to_wide(s: &str) -> Option<Vec<u16>> {
+    if s.contains('\0') {
+        return None;
+    }
+    Some(s.encode_utf16().chain(Some(0)).collect())
+}
+
+const EVENTLOG_KEY: &str = "SYSTEM\\CurrentControlSet\\Services\\EventLog";
+
+/// Журнал, в котором зарегистрирован источник (`"Application"`, `"MyService"`), или `None`.
+pub fn source_log(source: &str) -> Option<String> {
+    let root = RegKey::open(HKEY_LOCAL_MACHINE, EVENTLOG_KEY)?;
+    root.subkeys()
+        .into_iter()
+        .find(|log| RegKey::open(root.0, &format!("{}\\{}", log, source)).is_some())
+}
+
+/// Регистрирует источник в журнале `log` ("Application" или свой, например "MyService"):
+/// создаёт ключ с `EventMessageFile` и `TypesSupported`. Нужны права администратора.
+/// Если журнала ещё нет, Windows создаст его при первой записи.
+pub fn install_source(source: &str, message_file: &str, log: &str) -> Result<(), WinlogError> {
+    let path = format!("{}\\{}\\{}", EVENTLOG_KEY, log, source);
+    let key = RegKey::create(HKEY_LOCAL_MACHINE, &path)?;
+    key.set_expand_string("EventMessageFile", message_file)?;
+    key.set_dword(
+        "TypesSupported",
+        (EVENTLOG_ERROR_TYPE | EVENTLOG_WARNING_TYPE | EVENTLOG_INFORMATION_TYPE) as u32,
+    )
+}
+
+/// Удаляет регистрацию источника. Отсутствие регистрации ошибкой не считается.
+pub fn uninstall_source(source: &str) -> Result<(), WinlogError> {
+    let log = match source_log(source) {
+        Some(log) => log,
+        None => return Ok(()),
+    };
+    let path = format!("{}\\{}\\{}", EVENTLOG_KEY, log, source);
+    let w_path = to_wide(&path).ok_or(WinlogError::InvalidString)?;
+    let status = unsafe { RegDeleteKeyW(HKEY_LOCAL_MACHINE, w_path.as_ptr()) };
+    if status != 0 {
+        return Err(WinlogError::Win32(status));
+    }
+    Ok(())
+}
+
+// SID пользователя из токена процесса
+fn current_user_sid() -> Option<Vec<u8>> {
+    let mut token: HANDLE = 0;
+    if unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) } == 0 {
+        return None;
+    }
+
+    // Первый вызов узнаёт размер буфера
+    let mut len: u32 = 0;
+    unsafe { GetTokenInformation(token, TokenUser, std::ptr::null_mut(), 0, &mut len) };
+    // TOKEN_USER содержит указатели: буфер из u64, чтобы он был выровнен под них
+    let mut buf = vec![0u64; (len as usize).div_ceil(8)];
+    let ok = unsafe { GetTokenInformation(token, TokenUser, buf.as_mut_ptr() as _, len, &mut len) };
+    unsafe { CloseHandle(token) };
+    if ok == 0 {
+        return None;
+    }
+
+    let user = unsafe { &*(buf.as_ptr() as *const TOKEN_USER) };
+    let sid_len = unsafe { GetLengthSid(user.User.Sid) };
+    let mut sid = vec![0u8; sid_len as usize];
+    if unsafe { CopySid(sid_len, sid.as_mut_ptr() as PSID, user.User.Sid) } == 0 {
+        return None;
+    }
+    Some(sid)
+}
+
+// Открытый ключ реестра, закрывается в Drop
+struct RegKey(HKEY);
+
+impl RegKey {
+    fn open(parent: HKEY, path: &str) -> Option<RegKey> {
+        let w_path = to_wide(path)?;
+        let mut key: HKEY = 0;
+        let status = unsafe { RegOpenKeyExW(parent, w_path.as_ptr(), 0, KEY_READ, &mut key) };
+        if status != 0 {
+            return None;
+        }
+        Some(RegKey(key))
+    }
+
+    fn create(parent: HKEY, path: &str) -> Result<RegKey, WinlogError> {
+        let w_path = to_wide(path).ok_or(WinlogError::InvalidString)?;
+        let mut key: HKEY = 0;
+        let status = unsafe {
+            RegCreateKeyExW(
+                parent,
+                w_path.as_ptr(),
+                0,
+                std::ptr::null(),
+                REG_OPTION_NON_VOLATILE,
+                KEY_WRITE,
+                std::ptr::null(),
+                &mut key,
+                std::ptr::null_mut(),
+            )
+        };
+        if status != 0 {
+            return Err(WinlogError::Win32(status));
+        }
+        Ok(RegKey(key))
+    }
+
+    fn set_expand_string(&self, name: &str, value: &str) -> Result<(), WinlogError> {
+        let w_value = to_wide(value).ok_or(WinlogError::InvalidString)?;
+        let bytes: Vec<u8> = w_value.iter().flat_map(|c| c.to_le_bytes()).collect();
+        self.set_value(name, REG_EXPAND_SZ, &bytes)
+    }
+
+    fn set_dword(&self, name: &str, value: u32) -> Result<(), WinlogError> {
+        self.set_value(name, REG_DWORD, &value.to_le_bytes())
+    }
+
+    fn set_value(&self, name: &str, kind: u32, data: &[u8]) -> Result<(), WinlogError> {
+        let w_name = to_wide(name).ok_or(WinlogError::InvalidString)?;
+        let status = unsafe {
+            RegSetValueExW(
+                self.0,
+                w_name.as_ptr(),
+                0,
+                kind,
+                data.as_ptr(),
+                data.len() as u32,
+            )
+        };
+        if status != 0 {
+            return Err(WinlogError::Win32(status));
+        }
+        Ok(())
+    }
+
+    fn subkeys(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        let mut buf = [0u16; 256];
+        for index in 0.. {
+            let mut len = buf.len() as u32;
+            let status = unsafe {
+                RegEnumKeyExW(
+                    self.0,
+                    index,
+                    buf.as_mut_ptr(),
+                    &mut len,
+                    std::ptr::null(),
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                )
+            };
+            if status != 0 {
+                break;
+            }
+            names.push(String::from_utf16_lossy(&buf[..len as usize]));
+        }
+        names
+    }
+}
+
+impl Drop for RegKey {
+    fn drop(&mut self) {
+        unsafe { RegCloseKey(self.0) };
+    }
+}